@@ -0,0 +1,221 @@
+use crate::{
+    game_move::GameMove,
+    game_session::{PlayerStats, ResourceAmount},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+/// Attributes that make players different from each other
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct PlayerAttributes {
+    // how many resources the player gets for every resource taken from the stock
+    pub efficiency: f32,
+    // max amount of resources the player can take in a single move. None means no cap
+    pub capacity: Option<ResourceAmount>,
+    // how many resources the player pays for every resource taken from the stock
+    pub cost_per_unit: f32,
+}
+
+// Attributes of players who didn't get any: every player is the same
+impl Default for PlayerAttributes {
+    fn default() -> Self {
+        PlayerAttributes {
+            efficiency: 1.0,
+            capacity: None,
+            cost_per_unit: 0.0,
+        }
+    }
+}
+
+/// Alias to avoid writing the generic type specification every time
+pub type PlayersAttributes = BTreeMap<AgentPubKey, PlayerAttributes>;
+
+/// Ranges from which attributes are drawn when they're assigned randomly.
+/// Every range is (min, max), both included
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RandomAttributesParams {
+    pub efficiency: (f32, f32),
+    // None means that nobody's capacity is capped
+    pub capacity: Option<(ResourceAmount, ResourceAmount)>,
+    pub cost_per_unit: (f32, f32),
+}
+
+/// Defines how players get their attributes when the session starts
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "assignment_name", content = "assignment_value")]
+pub enum AttributesAssignment {
+    // host chooses attributes for every player. Players who aren't
+    // in the map get the default attributes
+    Host(PlayersAttributes),
+    // every player gets attributes drawn from the given ranges
+    Random(RandomAttributesParams),
+}
+
+/// Returns the attributes of the player, or the default ones if they don't have any
+pub fn attributes_of(attributes: &PlayersAttributes, player: &AgentPubKey) -> PlayerAttributes {
+    attributes.get(player).copied().unwrap_or_default()
+}
+
+/// Assigns attributes to the players of a new session.
+/// NOTE: random attributes are drawn by the host when they start the session,
+/// and then are stored on the GameSession entry, so everyone sees the same ones
+pub fn assign_attributes(
+    assignment: AttributesAssignment,
+    players: &[AgentPubKey],
+) -> ExternResult<PlayersAttributes> {
+    match assignment {
+        AttributesAssignment::Host(attributes) => {
+            check_players_attributes(&attributes, players)?;
+            Ok(attributes)
+        }
+        AttributesAssignment::Random(ranges) => {
+            let mut attributes = PlayersAttributes::new();
+            for player in players {
+                let bytes = random_bytes(12)?.to_vec();
+                let draw = |i: usize| {
+                    let mut buf = [0u8; 4];
+                    buf.copy_from_slice(&bytes[i * 4..i * 4 + 4]);
+                    u32::from_le_bytes(buf) as f32 / u32::MAX as f32
+                };
+                let player_attributes = PlayerAttributes {
+                    efficiency: ranges.efficiency.0
+                        + (ranges.efficiency.1 - ranges.efficiency.0) * draw(0),
+                    capacity: ranges.capacity.map(|(min, max)| {
+                        min + ((max - min) as f32 * draw(1)).round() as ResourceAmount
+                    }),
+                    cost_per_unit: ranges.cost_per_unit.0
+                        + (ranges.cost_per_unit.1 - ranges.cost_per_unit.0) * draw(2),
+                };
+                check_attributes(&player_attributes)?;
+                attributes.insert(player.clone(), player_attributes);
+            }
+            Ok(attributes)
+        }
+    }
+}
+
+/// Makes sure that every player with attributes plays in the game,
+/// and that their attributes make sense
+pub fn check_players_attributes(
+    attributes: &PlayersAttributes,
+    players: &[AgentPubKey],
+) -> ExternResult<()> {
+    for (player, player_attributes) in attributes {
+        if !players.contains(player) {
+            return Err(WasmError::Guest(format!(
+                "Can't assign attributes to {:?}: they aren't a player of this game",
+                player
+            )));
+        }
+        check_attributes(player_attributes)?;
+    }
+    Ok(())
+}
+
+/// Makes sure that the attributes make sense for the game
+pub fn check_attributes(attributes: &PlayerAttributes) -> ExternResult<()> {
+    if !(0.0..=f32::MAX).contains(&attributes.efficiency)
+        || !(0.0..=f32::MAX).contains(&attributes.cost_per_unit)
+    {
+        return Err(WasmError::Guest(format!(
+            "Efficiency and cost per unit have to be >= 0, but they are {} and {}",
+            attributes.efficiency, attributes.cost_per_unit
+        )));
+    }
+    match attributes.capacity {
+        Some(capacity) if capacity <= 0 => Err(WasmError::Guest(format!(
+            "Capacity has to be > 0, but it is {}",
+            capacity
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Adjusts player stats, which already include the resources every player took
+/// with their move, to what players actually got given their efficiency and costs.
+/// Returns the costs every player paid during the round.
+/// NOTE: yield and costs are rounded down for every move
+pub fn apply_player_attributes(
+    player_stats: &mut PlayerStats,
+    player_moves: &[GameMove],
+    attributes: &PlayersAttributes,
+) -> PlayerStats {
+    let mut costs = PlayerStats::new();
+    for m in player_moves {
+        let player_attributes = attributes_of(attributes, &m.owner);
+        let harvested = (m.resource_amount as f32 * player_attributes.efficiency) as ResourceAmount;
+        let cost = (m.resource_amount as f32 * player_attributes.cost_per_unit) as ResourceAmount;
+        *player_stats.entry(m.owner.clone()).or_insert(0) += harvested - m.resource_amount - cost;
+        if cost > 0 {
+            *costs.entry(m.owner.clone()).or_insert(0) += cost;
+        }
+    }
+    costs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move};
+
+    fn attributes(
+        efficiency: f32,
+        capacity: Option<ResourceAmount>,
+        cost_per_unit: f32,
+    ) -> PlayerAttributes {
+        PlayerAttributes {
+            efficiency,
+            capacity,
+            cost_per_unit,
+        }
+    }
+
+    #[test]
+    fn players_without_attributes_get_the_default_ones() {
+        let mut players_attributes = PlayersAttributes::new();
+        players_attributes.insert(agent(1), attributes(2.0, Some(5), 0.5));
+        assert_eq!(
+            attributes_of(&players_attributes, &agent(1)),
+            attributes(2.0, Some(5), 0.5)
+        );
+        assert_eq!(
+            attributes_of(&players_attributes, &agent(2)),
+            PlayerAttributes::default()
+        );
+    }
+
+    #[test]
+    fn host_can_only_assign_valid_attributes_to_players() {
+        let players = vec![agent(1), agent(2)];
+        let assign = |player: u8, player_attributes: PlayerAttributes| {
+            let mut players_attributes = PlayersAttributes::new();
+            players_attributes.insert(agent(player), player_attributes);
+            assign_attributes(AttributesAssignment::Host(players_attributes), &players)
+        };
+        assert!(assign(1, attributes(2.0, Some(5), 0.5)).is_ok());
+        assert!(assign(3, attributes(2.0, Some(5), 0.5)).is_err());
+        assert!(assign(1, attributes(-1.0, None, 0.0)).is_err());
+        assert!(assign(1, attributes(1.0, None, -0.5)).is_err());
+        assert!(assign(1, attributes(1.0, Some(0), 0.0)).is_err());
+        assert!(assign(1, attributes(f32::NAN, None, 0.0)).is_err());
+        assert!(assign(1, attributes(1.0, None, f32::NAN)).is_err());
+    }
+
+    #[test]
+    fn players_get_what_their_efficiency_yields_minus_costs() {
+        let mut players_attributes = PlayersAttributes::new();
+        players_attributes.insert(agent(1), attributes(2.0, None, 0.5));
+        // player stats already include the takes of the round
+        let mut player_stats: PlayerStats =
+            vec![(agent(1), 10), (agent(2), 10)].into_iter().collect();
+        let costs = apply_player_attributes(
+            &mut player_stats,
+            &[game_move(1, 10), game_move(2, 10)],
+            &players_attributes,
+        );
+        assert_eq!(player_stats[&agent(1)], 15);
+        assert_eq!(player_stats[&agent(2)], 10);
+        let expected_costs: PlayerStats = vec![(agent(1), 5)].into_iter().collect();
+        assert_eq!(costs, expected_costs);
+    }
+}
@@ -0,0 +1,644 @@
+use crate::{
+    game_move::GameMove,
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    game_subsistence::active_players,
+    game_taxation::Redistribution,
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const BID_COMMITMENT_LINK_TAG: &str = "BID_COMMITMENT";
+// size of the salt that keeps the bid secret until it's revealed
+pub const BID_SALT_SIZE: u32 = 32;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct AuctionParams {
+    // amount of resources players can win the right to take every round
+    pub rights_per_round: ResourceAmount,
+    // min price per resource a bid has to offer to win anything
+    pub reserve_price: ResourceAmount,
+    // what happens with the resources winners pay for their rights
+    pub revenue: Redistribution,
+}
+
+/// Player's bid, which stays private on their source chain until they reveal it
+#[hdk_entry(id = "bid_secret", visibility = "private")]
+#[derive(Clone)]
+pub struct BidSecret {
+    pub round_hash: EntryHash,
+    pub units: ResourceAmount,
+    pub price_per_unit: ResourceAmount,
+    pub salt: Vec<u8>,
+}
+
+/// Commitment to the player's bid: it only contains the hash of the BidReveal
+/// entry that the player would publish later, so nobody can learn the bid from it
+#[hdk_entry(id = "bid_commitment", visibility = "public")]
+#[derive(Clone)]
+pub struct BidCommitment {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    pub round_hash: EntryHash,
+    pub reveal_hash: EntryHash,
+}
+
+/// Player's revealed bid: the owner wants the right to take `units` resources
+/// in the round and offers to pay `price_per_unit` for every one of them.
+/// The salt makes sure nobody can guess the bid by hashing all possible ones
+#[hdk_entry(id = "bid_reveal", visibility = "public")]
+#[derive(Clone)]
+pub struct BidReveal {
+    pub owner: AgentPubKey,
+    pub round_hash: EntryHash,
+    pub units: ResourceAmount,
+    pub price_per_unit: ResourceAmount,
+    pub salt: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BidInput {
+    pub round_hash: EntryHash,
+    pub units: ResourceAmount,
+    pub price_per_unit: ResourceAmount,
+}
+
+/// Outcome of the auction of a single round
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuctionResult {
+    // resources every winner got the right to take
+    pub rights: PlayerStats,
+    // resources every winner paid for their rights
+    pub payments: PlayerStats,
+    // total amount of resources paid by the winners
+    pub revenue: ResourceAmount,
+}
+
+/// Makes sure that the auction params make sense for the game. Players have
+/// nothing before the first round, so the reserve price can't be more than the
+/// one resource every right lets them take, otherwise nobody could ever win a right
+pub fn check_auction(params: &AuctionParams) -> ExternResult<()> {
+    if params.rights_per_round <= 0 || !(0..=1).contains(&params.reserve_price) {
+        return Err(WasmError::Guest(format!(
+            "Auction has to sell rights to > 0 resources for a reserve price between 0 and 1, but it sells {} for {}",
+            params.rights_per_round, params.reserve_price
+        )));
+    }
+    Ok(())
+}
+
+/// Checks if the owner of the bid can pay for all the rights they bid for. Winners pay
+/// for their rights with what they had when the round started and what they take
+/// with the rights during the round, so the first round can be played too
+pub fn can_afford_bid(bid: &BidReveal, units: ResourceAmount, balance: ResourceAmount) -> bool {
+    units.saturating_mul(bid.price_per_unit) <= balance.saturating_add(units)
+}
+
+/// Keeps the bid of the agent executing this fn private and publishes a commitment to it
+pub fn commit_bid(input: BidInput) -> ExternResult<EntryHash> {
+    let owner = agent_info()?.agent_latest_pubkey;
+    let game_round: GameRound =
+        try_get_and_convert(input.round_hash.clone(), GetOptions::latest())?;
+    check_round_is_open(input.round_hash.clone(), "commit to a bid")?;
+    // players can only commit to one bid per round, so that they can't reveal
+    // the one that suits them best later. Their commitments are on their source chain
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .header_type(HeaderType::Create)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(BidCommitment)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Public,
+        )));
+    for el in query(filter)? {
+        let prev_commitment: BidCommitment = try_from_element(el)?;
+        if prev_commitment.round_hash == input.round_hash {
+            return Err(WasmError::Guest(String::from(
+                "Players can only commit to one bid per round",
+            )));
+        }
+    }
+    let salt = random_bytes(BID_SALT_SIZE)?.to_vec();
+    // we would need the bid later to reveal it
+    create_entry(&BidSecret {
+        round_hash: input.round_hash.clone(),
+        units: input.units,
+        price_per_unit: input.price_per_unit,
+        salt: salt.clone(),
+    })?;
+
+    let bid_reveal = BidReveal {
+        owner: owner.clone(),
+        round_hash: input.round_hash.clone(),
+        units: input.units,
+        price_per_unit: input.price_per_unit,
+        salt,
+    };
+    let bid_commitment = BidCommitment {
+        owner,
+        session: game_round.session,
+        round_hash: input.round_hash.clone(),
+        // NOTE: hash_entry doesn't write anything to DHT, so the bid stays secret
+        reveal_hash: hash_entry(&bid_reveal)?,
+    };
+    create_entry(&bid_commitment)?;
+    let bid_commitment_entry_hash = hash_entry(&bid_commitment)?;
+
+    // Link from the round so that whoever closes it can find the commitment
+    create_link(
+        input.round_hash,
+        bid_commitment_entry_hash.clone(),
+        LinkTag::new(String::from(BID_COMMITMENT_LINK_TAG)),
+    )?;
+
+    Ok(bid_commitment_entry_hash)
+}
+
+/// Publishes the bid the agent executing this fn has committed to in the round.
+/// There's no need to link the reveal from anywhere: it's hash is stored
+/// in the commitment already
+pub fn reveal_bid(round_hash: EntryHash) -> ExternResult<EntryHash> {
+    // bids are only revealed after everyone has committed to theirs, otherwise
+    // the last player to commit could outbid the others knowing their bids.
+    // NOTE: this check depends on what the agent can see on DHT, so it's done
+    // here instead of the validation of BidReveal. Revealing a bid too early
+    // only gives away the owner's own bid
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let game_session: GameSession =
+        try_get_and_convert(game_round.session.clone(), GetOptions::latest())?;
+    let players = active_players(&game_session.players, &game_round.state.eliminated_players);
+    let mut committed_players: Vec<AgentPubKey> = vec![];
+    for (_, commitment) in get_bid_commitments_for_round(round_hash.clone())? {
+        if players.contains(&commitment.owner) && !committed_players.contains(&commitment.owner) {
+            committed_players.push(commitment.owner);
+        }
+    }
+    if committed_players.len() < players.len() {
+        return Err(WasmError::Guest(String::from(
+            "Can't reveal the bid until every player has committed to theirs",
+        )));
+    }
+
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(BidSecret)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Private,
+        )));
+    let mut bid_secret: Option<BidSecret> = None;
+    for el in query(filter)? {
+        let secret: BidSecret = try_from_element(el)?;
+        if secret.round_hash == round_hash {
+            bid_secret = Some(secret);
+            break;
+        }
+    }
+    let bid_secret = bid_secret.ok_or(WasmError::Guest(
+        "Can't reveal the bid: there's no bid committed for this round".into(),
+    ))?;
+
+    let bid_reveal = BidReveal {
+        owner: agent_info()?.agent_latest_pubkey,
+        round_hash,
+        units: bid_secret.units,
+        price_per_unit: bid_secret.price_per_unit,
+        salt: bid_secret.salt,
+    };
+    create_entry(&bid_reveal)?;
+    hash_entry(&bid_reveal)
+}
+
+/// Get all bid commitments made during the round, together with their entry hashes
+pub fn get_bid_commitments_for_round(
+    round_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, BidCommitment)>> {
+    let links = get_links(
+        round_hash,
+        Some(LinkTag::new(String::from(BID_COMMITMENT_LINK_TAG))),
+    )?;
+    let mut commitments: Vec<(EntryHash, BidCommitment)> = vec![];
+    for link in links.into_inner() {
+        let commitment: BidCommitment =
+            try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        commitments.push((link.target, commitment));
+    }
+    Ok(commitments)
+}
+
+/// Tries to collect the revealed bids of every player for the round, together
+/// with the hashes of their commitments, sorted by the commitment hash.
+/// Returns None if some of the players haven't committed to or revealed their bid yet.
+/// If a player has several commitments, the one with the smallest entry hash is used.
+pub fn get_revealed_bids_for_round(
+    round_hash: EntryHash,
+    players: &[AgentPubKey],
+) -> ExternResult<Option<Vec<(EntryHash, BidReveal)>>> {
+    let mut commitment_per_player: BTreeMap<AgentPubKey, (EntryHash, BidCommitment)> =
+        BTreeMap::new();
+    for (commitment_hash, commitment) in get_bid_commitments_for_round(round_hash)? {
+        if !players.contains(&commitment.owner) {
+            continue;
+        }
+        let is_first = match commitment_per_player.get(&commitment.owner) {
+            Some((prev_hash, _)) => commitment_hash < *prev_hash,
+            None => true,
+        };
+        if is_first {
+            commitment_per_player.insert(commitment.owner.clone(), (commitment_hash, commitment));
+        }
+    }
+    if commitment_per_player.len() < players.len() {
+        info!(
+            "Can't resolve the auction: only {} out of {} players have committed to their bids",
+            commitment_per_player.len(),
+            players.len()
+        );
+        return Ok(None);
+    }
+
+    let mut bids: Vec<(EntryHash, BidReveal)> = vec![];
+    for (_, (commitment_hash, commitment)) in commitment_per_player {
+        match get(commitment.reveal_hash, GetOptions::latest())? {
+            Some(element) => bids.push((commitment_hash, try_from_element(element)?)),
+            None => {
+                info!(
+                    "Can't resolve the auction: {:?} hasn't revealed their bid yet",
+                    commitment.owner
+                );
+                return Ok(None);
+            }
+        }
+    }
+    bids.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(Some(bids))
+}
+
+/// Collects the revealed bids from the commitments the auction was resolved with.
+/// Returns None if the commitments don't come from every player of the round,
+/// one commitment per player.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn verify_auction_bids(
+    commitments: &[EntryHash],
+    round_hash: &EntryHash,
+    players: &[AgentPubKey],
+) -> ExternResult<Option<Vec<(EntryHash, BidReveal)>>> {
+    if commitments.len() != players.len() {
+        return Ok(None);
+    }
+    let mut owners: Vec<AgentPubKey> = vec![];
+    let mut bids: Vec<(EntryHash, BidReveal)> = vec![];
+    for commitment_hash in commitments {
+        let commitment = must_get_entry_struct::<BidCommitment>(commitment_hash.clone())?;
+        if &commitment.round_hash != round_hash
+            || !players.contains(&commitment.owner)
+            || owners.contains(&commitment.owner)
+        {
+            return Ok(None);
+        }
+        // reveal_hash is the hash of the BidReveal entry, so if we can get it,
+        // it's guaranteed to be the bid the player has committed to
+        let bid = must_get_entry_struct::<BidReveal>(commitment.reveal_hash)?;
+        owners.push(commitment.owner);
+        bids.push((commitment_hash.clone(), bid));
+    }
+    Ok(Some(bids))
+}
+
+/// Sells the rights to take resources in the round to the highest bidders.
+/// Every winner pays the price they've bid for every resource they won.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent:
+/// - bids are served from the highest price per unit to the lowest, and bids
+///   with the same price are served in the order of their commitment hashes
+/// - the last winner gets the rights that are left, even if it's less than they bid for
+/// - bids below the reserve price, and bids the owner couldn't pay for with the
+///   resources they had when the round started and the ones the rights let them
+///   take, are skipped
+pub fn resolve_auction(
+    params: &AuctionParams,
+    mut bids: Vec<(EntryHash, BidReveal)>,
+    balances: &PlayerStats,
+) -> AuctionResult {
+    bids.sort_by(|a, b| {
+        b.1.price_per_unit
+            .cmp(&a.1.price_per_unit)
+            .then(a.0.cmp(&b.0))
+    });
+    bids.dedup_by(|a, b| a.0 == b.0);
+
+    let mut result = AuctionResult::default();
+    let mut rights_left = params.rights_per_round;
+    for (_, bid) in bids {
+        if rights_left <= 0 {
+            break;
+        }
+        if bid.units <= 0 || bid.price_per_unit < params.reserve_price {
+            continue;
+        }
+        let units = std::cmp::min(bid.units, rights_left);
+        if !can_afford_bid(&bid, units, *balances.get(&bid.owner).unwrap_or(&0)) {
+            info!("Skipping bid: {:?} can't afford it", bid.owner);
+            continue;
+        }
+        let price = units * bid.price_per_unit;
+        *result.rights.entry(bid.owner.clone()).or_insert(0) += units;
+        *result.payments.entry(bid.owner).or_insert(0) += price;
+        result.revenue += price;
+        rights_left -= units;
+    }
+    result
+}
+
+/// Takes the payments of the winners from the player_stats and gives the revenue
+/// back to the players or puts it into the resource stock.
+/// Returns the amounts of revenue that were redistributed and reinvested.
+/// NOTE: same as with taxes, if the revenue can't be split equally between
+/// players, the remainder is reinvested into the resource stock
+pub fn apply_auction(
+    player_stats: &mut PlayerStats,
+    result: &AuctionResult,
+    params: &AuctionParams,
+    players: &[AgentPubKey],
+) -> (ResourceAmount, ResourceAmount) {
+    for (player, payment) in &result.payments {
+        *player_stats.entry(player.clone()).or_insert(0) -= payment;
+    }
+    let redistributed = auction_revenue_redistributed(params, result.revenue, players.len());
+    if redistributed > 0 {
+        let share = redistributed / players.len() as ResourceAmount;
+        for p in players {
+            *player_stats.entry(p.clone()).or_insert(0) += share;
+        }
+    }
+    (redistributed, result.revenue - redistributed)
+}
+
+/// Calculates how much of the auction revenue is given back to the players
+pub fn auction_revenue_redistributed(
+    params: &AuctionParams,
+    revenue: ResourceAmount,
+    num_players: usize,
+) -> ResourceAmount {
+    match params.revenue {
+        Redistribution::Equal if num_players > 0 => {
+            revenue / num_players as ResourceAmount * num_players as ResourceAmount
+        }
+        _ => 0,
+    }
+}
+
+/// Makes sure that no player takes more resources than they won the right to.
+/// Rights are only known once every player has revealed their bid, so moves
+/// can't be checked against them in validation, and the takes are cut to the
+/// rights when the round is closed instead.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it only operates with input data
+pub fn clamp_takes_to_rights(player_moves: Vec<GameMove>, rights: &PlayerStats) -> Vec<GameMove> {
+    player_moves
+        .into_iter()
+        .map(|m| {
+            let rights = *rights.get(&m.owner).unwrap_or(&0);
+            GameMove {
+                resource_amount: std::cmp::min(m.resource_amount, rights),
+                ..m
+            }
+        })
+        .collect()
+}
+
+/// Validates creation of BidCommitment entries
+pub fn validate_create_entry_bid_commitment(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let commitment: BidCommitment = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &commitment.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "BidCommitment can only be created by it's owner",
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(commitment.round_hash.clone())?;
+    if game_round.session != commitment.session {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "BidCommitment's round doesn't belong to it's GameSession",
+        )));
+    }
+    if game_round.state.game_params.auction.is_none() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "There's no auction in this GameSession",
+        )));
+    }
+    let game_session = must_get_entry_struct::<GameSession>(commitment.session.clone())?;
+    if !active_players(&game_session.players, &game_round.state.eliminated_players)
+        .contains(&commitment.owner)
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only active GameSession players can bid",
+        )));
+    }
+
+    // NOTE: commit_bid checks that the round is still open, and that the player
+    // hasn't committed to another bid in the round
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of BidCommitment entries
+pub fn validate_update_entry_bid_commitment(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update BidCommitment entry",
+    )))
+}
+
+/// Validates delete of BidCommitment entries
+pub fn validate_delete_entry_bid_commitment(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete BidCommitment entry",
+    )))
+}
+
+/// Validates creation of BidReveal entries
+pub fn validate_create_entry_bid_reveal(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let bid: BidReveal = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &bid.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "BidReveal can only be created by it's owner",
+        )));
+    }
+
+    if bid.units < 0 || bid.price_per_unit < 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Bid has to be for >= 0 resources at a price >= 0, but it is for {} at {}",
+            bid.units, bid.price_per_unit
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(bid.round_hash.clone())?;
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session.clone())?;
+    let players = active_players(&game_session.players, &game_round.state.eliminated_players);
+    if !players.contains(&bid.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only active GameSession players can bid",
+        )));
+    }
+
+    // players can only bid with resources they already have and the ones
+    // the rights let them take
+    let balance = *game_round.state.player_stats.get(&bid.owner).unwrap_or(&0);
+    if !can_afford_bid(&bid, bid.units, balance) {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Bid for {} resources at {} per unit costs more than the owner has: {}",
+            bid.units, bid.price_per_unit, balance
+        )));
+    }
+
+    // NOTE: reveal_bid checks that every player has committed to their bid
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of BidReveal entries
+pub fn validate_update_entry_bid_reveal(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update BidReveal entry",
+    )))
+}
+
+/// Validates delete of BidReveal entries
+pub fn validate_delete_entry_bid_reveal(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete BidReveal entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, bid, game_move, stats};
+
+    fn auction(revenue: Redistribution) -> AuctionParams {
+        AuctionParams {
+            rights_per_round: 10,
+            reserve_price: 1,
+            revenue,
+        }
+    }
+
+    #[test]
+    fn auction_has_to_sell_something() {
+        assert!(check_auction(&auction(Redistribution::Equal)).is_ok());
+        let mut params = auction(Redistribution::Equal);
+        params.rights_per_round = 0;
+        assert!(check_auction(&params).is_err());
+        params.rights_per_round = 10;
+        params.reserve_price = -1;
+        assert!(check_auction(&params).is_err());
+        // nobody could pay that much for their rights in the first round
+        params.reserve_price = 2;
+        assert!(check_auction(&params).is_err());
+    }
+
+    #[test]
+    fn highest_bids_win_and_the_last_winner_gets_whats_left() {
+        let result = resolve_auction(
+            &auction(Redistribution::Equal),
+            vec![bid(10, 1, 6, 3), bid(11, 2, 6, 4), bid(12, 3, 6, 5)],
+            &stats(&[(1, 100), (2, 100), (3, 100)]),
+        );
+        assert_eq!(
+            result,
+            AuctionResult {
+                rights: stats(&[(2, 4), (3, 6)]),
+                payments: stats(&[(2, 16), (3, 30)]),
+                revenue: 46,
+            }
+        );
+    }
+
+    #[test]
+    fn bids_with_the_same_price_are_served_by_commitment_hash() {
+        let result = resolve_auction(
+            &auction(Redistribution::Equal),
+            vec![bid(11, 1, 8, 3), bid(10, 2, 8, 3)],
+            &stats(&[(1, 100), (2, 100)]),
+        );
+        assert_eq!(result.rights, stats(&[(1, 2), (2, 8)]));
+    }
+
+    #[test]
+    fn bids_below_reserve_or_unaffordable_are_skipped() {
+        let result = resolve_auction(
+            &auction(Redistribution::Equal),
+            vec![bid(10, 1, 5, 0), bid(11, 2, 5, 4), bid(12, 3, 5, 3)],
+            &stats(&[(1, 100), (2, 14), (3, 10)]),
+        );
+        assert_eq!(
+            result,
+            AuctionResult {
+                rights: stats(&[(3, 5)]),
+                payments: stats(&[(3, 15)]),
+                revenue: 15,
+            }
+        );
+    }
+
+    #[test]
+    fn players_can_pay_for_their_rights_with_what_the_rights_let_them_take() {
+        let result = resolve_auction(
+            &auction(Redistribution::Equal),
+            vec![bid(10, 1, 4, 1), bid(11, 2, 4, 2)],
+            &PlayerStats::new(),
+        );
+        assert_eq!(result.rights, stats(&[(1, 4)]));
+        assert_eq!(result.payments, stats(&[(1, 4)]));
+    }
+
+    #[test]
+    fn takes_are_cut_to_the_rights_players_won() {
+        let moves = vec![game_move(1, 7), game_move(2, 3), game_move(3, 2)];
+        let takes: Vec<ResourceAmount> = clamp_takes_to_rights(moves, &stats(&[(1, 5), (2, 4)]))
+            .into_iter()
+            .map(|m| m.resource_amount)
+            .collect();
+        assert_eq!(takes, vec![5, 3, 0]);
+    }
+
+    #[test]
+    fn revenue_is_split_equally_and_the_remainder_is_reinvested() {
+        let players = vec![agent(1), agent(2), agent(3)];
+        let result = AuctionResult {
+            rights: stats(&[(1, 5)]),
+            payments: stats(&[(1, 10)]),
+            revenue: 10,
+        };
+        let mut player_stats = stats(&[(1, 20), (2, 0), (3, 0)]);
+        let (redistributed, reinvested) = apply_auction(
+            &mut player_stats,
+            &result,
+            &auction(Redistribution::Equal),
+            &players,
+        );
+        assert_eq!((redistributed, reinvested), (9, 1));
+        assert_eq!(player_stats, stats(&[(1, 13), (2, 3), (3, 3)]));
+
+        let mut player_stats = stats(&[(1, 20), (2, 0), (3, 0)]);
+        let (redistributed, reinvested) = apply_auction(
+            &mut player_stats,
+            &result,
+            &auction(Redistribution::Reinvest),
+            &players,
+        );
+        assert_eq!((redistributed, reinvested), (0, 10));
+        assert_eq!(player_stats, stats(&[(1, 10), (2, 0), (3, 0)]));
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{
+    game_move::get_moves_for_round,
+    game_round::{check_round_is_open, GameRound},
+    game_session::GameSession,
+    game_signals::{ChatMessagePayload, GameSignal},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+
+pub const CHAT_MESSAGE_LINK_TAG: &str = "CHAT_MESSAGE";
+// max number of characters in a single message
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 500;
+// max number of messages in a single page of the chat history
+pub const MAX_CHAT_PAGE_SIZE: u32 = 100;
+
+/// Defines in which rounds players can talk to each other
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum CommunicationSchedule {
+    // players can't talk at all
+    Never,
+    // players can only talk before anyone has made a move in the round zero
+    BeforeGame,
+    // players can talk in every round
+    EveryRound,
+    // players can talk in round zero and then in every n-th round
+    EveryNRounds { n: u32 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct CommunicationParams {
+    pub schedule: CommunicationSchedule,
+    // if true, players can only send messages to their neighbours: players that
+    // come right before and after them in the GameSession players list, which
+    // is treated as a circle. Messages to everyone aren't allowed then
+    pub neighbours_only: bool,
+}
+
+/// Message a player sends to the other players of the session
+#[hdk_entry(id = "chat_message", visibility = "public")]
+#[derive(Clone)]
+pub struct ChatMessage {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // round that was going on when the message was sent
+    pub round_hash: EntryHash,
+    pub round_num: u32,
+    // player the message is for. None means it's for everyone.
+    // Messages to one player are still public entries on DHT
+    pub recipient: Option<AgentPubKey>,
+    pub content: String,
+    // time when the message was sent, taken from the author's clock
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatMessageInput {
+    pub round_hash: EntryHash,
+    // None means the message is for everyone
+    pub recipient: Option<AgentPubKey>,
+    pub content: String,
+}
+
+/// Struct to receive the page of the chat history the UI wants to show
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatHistoryInput {
+    pub session_hash: EntryHash,
+    // number of the page, from 0. Page zero has the latest messages
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Makes sure that the communication params make sense for the game
+pub fn check_communication(params: &CommunicationParams) -> ExternResult<()> {
+    match params.schedule {
+        CommunicationSchedule::EveryNRounds { n } if n == 0 => Err(WasmError::Guest(String::from(
+            "Players can't talk every 0 rounds: n has to be > 0",
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Checks if the players are neighbours in the circle of the session players
+pub fn are_neighbours(players: &[AgentPubKey], a: &AgentPubKey, b: &AgentPubKey) -> bool {
+    let (i, j) = match (
+        players.iter().position(|p| p == a),
+        players.iter().position(|p| p == b),
+    ) {
+        (Some(i), Some(j)) => (i, j),
+        _ => return false,
+    };
+    let n = players.len();
+    i != j && ((i + 1) % n == j || (j + 1) % n == i)
+}
+
+/// Creates a new chat message, links it from the game session and lets
+/// the players it's for know about it
+pub fn send_chat_message(input: ChatMessageInput) -> ExternResult<EntryHash> {
+    let game_round: GameRound =
+        try_get_and_convert(input.round_hash.clone(), GetOptions::latest())?;
+    let game_session: GameSession =
+        try_get_and_convert(game_round.session.clone(), GetOptions::latest())?;
+    // NOTE: these checks depend on what the agent can see on DHT, so they are
+    // done here instead of the validation of ChatMessage
+    check_round_is_open(input.round_hash.clone(), "send a ChatMessage")?;
+    if let Some(CommunicationParams {
+        schedule: CommunicationSchedule::BeforeGame,
+        ..
+    }) = game_round.state.game_params.communication
+    {
+        if !get_moves_for_round(input.round_hash.clone())?.is_empty() {
+            return Err(WasmError::Guest(String::from(
+                "Players can't talk anymore: the game has already started",
+            )));
+        }
+    }
+    let me = agent_info()?.agent_latest_pubkey;
+    let chat_message = ChatMessage {
+        owner: me.clone(),
+        session: game_round.session.clone(),
+        round_hash: input.round_hash,
+        round_num: game_round.round_num,
+        recipient: input.recipient.clone(),
+        content: input.content,
+        timestamp: sys_time()?,
+    };
+    create_entry(&chat_message)?;
+    let chat_message_entry_hash = hash_entry(&chat_message)?;
+
+    // Link from the game session so that every player can read the chat history.
+    // Tag has the round number, so that UI can tell in which round messages were sent
+    create_link(
+        game_round.session.clone(),
+        chat_message_entry_hash.clone(),
+        LinkTag::new(format!(
+            "{}_{}",
+            CHAT_MESSAGE_LINK_TAG, chat_message.round_num
+        )),
+    )?;
+
+    // Let the other players show the message right away
+    let signal_payload = ChatMessagePayload {
+        game_session_entry_hash: game_round.session,
+        message_entry_hash: chat_message_entry_hash.clone(),
+        message: chat_message,
+    };
+    let signal = ExternIO::encode(GameSignal::ChatMessage(signal_payload))?;
+    let recipients: Vec<AgentPubKey> = match input.recipient {
+        Some(recipient) => vec![recipient],
+        None => game_session
+            .players
+            .into_iter()
+            .filter(|p| p != &me)
+            .collect(),
+    };
+    remote_signal(signal, recipients)?;
+
+    Ok(chat_message_entry_hash)
+}
+
+/// Get the page of the chat history of the session, from the latest messages
+/// to the earliest ones. Messages sent at the same time are ordered by their hashes.
+/// Messages sent to other players are left out of the history of the agent executing
+/// this fn, but they are public DHT entries, so they aren't hidden from anyone who
+/// looks them up directly
+pub fn get_chat_messages(input: ChatHistoryInput) -> ExternResult<Vec<(EntryHash, ChatMessage)>> {
+    if input.page_size == 0 || input.page_size > MAX_CHAT_PAGE_SIZE {
+        return Err(WasmError::Guest(format!(
+            "Page size has to be between 1 and {}, but it is {}",
+            MAX_CHAT_PAGE_SIZE, input.page_size
+        )));
+    }
+    // every message is linked with it's round number in the tag, so we're
+    // looking for all tags that start with the same prefix
+    let links = get_links(
+        input.session_hash,
+        Some(LinkTag::new(String::from(CHAT_MESSAGE_LINK_TAG))),
+    )?;
+    let mut messages: Vec<(EntryHash, ChatMessage)> = vec![];
+    for link in links.into_inner() {
+        let message: ChatMessage = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        messages.push((link.target, message));
+    }
+    let me = agent_info()?.agent_latest_pubkey;
+    Ok(chat_history_page(
+        messages,
+        &me,
+        input.page,
+        input.page_size,
+    ))
+}
+
+/// Leaves only the messages the agent can read, sorts them from the latest to the
+/// earliest and cuts out the page. Messages sent to other players are only
+/// shown to their owner and recipient, but it doesn't make them private
+fn chat_history_page(
+    messages: Vec<(EntryHash, ChatMessage)>,
+    me: &AgentPubKey,
+    page: u32,
+    page_size: u32,
+) -> Vec<(EntryHash, ChatMessage)> {
+    let mut messages: Vec<(EntryHash, ChatMessage)> = messages
+        .into_iter()
+        .filter(|(_, message)| match &message.recipient {
+            Some(recipient) => recipient == me || &message.owner == me,
+            None => true,
+        })
+        .collect();
+    messages.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp).then(a.0.cmp(&b.0)));
+    messages
+        .into_iter()
+        .skip((page as usize).saturating_mul(page_size as usize))
+        .take(page_size as usize)
+        .collect()
+}
+
+/// Validates creation of ChatMessage entries
+pub fn validate_create_entry_chat_message(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let chat_message: ChatMessage = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &chat_message.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "ChatMessage can only be created by it's owner",
+        )));
+    }
+
+    if chat_message.timestamp > data.element.header().timestamp() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "ChatMessage can't have a timestamp later than the header that creates it",
+        )));
+    }
+
+    let length = chat_message.content.chars().count();
+    if length == 0 || length > MAX_CHAT_MESSAGE_LENGTH {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "ChatMessage has to have between 1 and {} characters, but it has {}",
+            MAX_CHAT_MESSAGE_LENGTH, length
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(chat_message.round_hash.clone())?;
+    if game_round.session != chat_message.session || game_round.round_num != chat_message.round_num
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "ChatMessage has to be sent in a round of it's GameSession",
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(chat_message.session)?;
+    if !game_session.players.contains(&chat_message.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can send chat messages",
+        )));
+    }
+    if let Some(recipient) = &chat_message.recipient {
+        if recipient == &chat_message.owner || !game_session.players.contains(recipient) {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "ChatMessage can only be sent to another GameSession player",
+            )));
+        }
+    }
+
+    // NOTE: whether the round is still open is checked in send_chat_message,
+    // since it depends on what the agent can see on DHT
+
+    let params = match game_round.state.game_params.communication {
+        Some(params) => params,
+        None => return Ok(ValidateCallbackResult::Valid),
+    };
+    if params.neighbours_only {
+        let to_neighbour = match &chat_message.recipient {
+            Some(recipient) => {
+                are_neighbours(&game_session.players, &chat_message.owner, recipient)
+            }
+            None => false,
+        };
+        if !to_neighbour {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "ChatMessage can only be sent to one of the sender's neighbours",
+            )));
+        }
+    }
+    let can_talk = match params.schedule {
+        CommunicationSchedule::Never => false,
+        // NOTE: that no one has made a move yet is checked in send_chat_message
+        CommunicationSchedule::BeforeGame => chat_message.round_num == 0,
+        CommunicationSchedule::EveryRound => true,
+        CommunicationSchedule::EveryNRounds { n } => chat_message.round_num % n == 0,
+    };
+    if !can_talk {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Players can't talk in round {} of this GameSession",
+            chat_message.round_num
+        )));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of ChatMessage entries
+pub fn validate_update_entry_chat_message(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update ChatMessage entry",
+    )))
+}
+
+/// Validates delete of ChatMessage entries
+pub fn validate_delete_entry_chat_message(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete ChatMessage entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, hash, timestamp};
+
+    fn message(n: u8, owner: u8, recipient: Option<u8>, secs: i64) -> (EntryHash, ChatMessage) {
+        (
+            hash(n),
+            ChatMessage {
+                owner: agent(owner),
+                session: hash(0),
+                round_hash: hash(0),
+                round_num: 0,
+                recipient: recipient.map(agent),
+                content: String::from("hi"),
+                timestamp: timestamp(secs),
+            },
+        )
+    }
+
+    fn hashes(messages: &[(EntryHash, ChatMessage)]) -> Vec<EntryHash> {
+        messages.iter().map(|(h, _)| h.clone()).collect()
+    }
+
+    #[test]
+    fn history_goes_from_the_latest_message_and_ties_are_ordered_by_hash() {
+        let messages = vec![
+            message(10, 1, None, 1),
+            message(30, 2, None, 2),
+            message(20, 3, None, 2),
+        ];
+        let page = chat_history_page(messages, &agent(1), 0, 10);
+        assert_eq!(hashes(&page), vec![hash(20), hash(30), hash(10)]);
+    }
+
+    #[test]
+    fn private_messages_are_only_seen_by_their_owner_and_recipient() {
+        let messages = vec![
+            message(10, 1, Some(2), 1),
+            message(20, 2, Some(3), 2),
+            message(30, 3, None, 3),
+        ];
+        let page = chat_history_page(messages.clone(), &agent(1), 0, 10);
+        assert_eq!(hashes(&page), vec![hash(30), hash(10)]);
+        let page = chat_history_page(messages, &agent(2), 0, 10);
+        assert_eq!(hashes(&page), vec![hash(30), hash(20), hash(10)]);
+    }
+
+    #[test]
+    fn pages_are_cut_from_the_sorted_history() {
+        let messages: Vec<(EntryHash, ChatMessage)> =
+            (1..=5).map(|n| message(n, 1, None, n as i64)).collect();
+        let page = chat_history_page(messages.clone(), &agent(1), 1, 2);
+        assert_eq!(hashes(&page), vec![hash(3), hash(2)]);
+        let page = chat_history_page(messages.clone(), &agent(1), 2, 2);
+        assert_eq!(hashes(&page), vec![hash(1)]);
+        assert!(chat_history_page(messages, &agent(1), 3, 2).is_empty());
+    }
+
+    #[test]
+    fn pages_far_past_the_history_are_empty() {
+        let messages = vec![message(10, 1, None, 1)];
+        assert!(chat_history_page(messages, &agent(1), u32::MAX, u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn players_cant_talk_every_zero_rounds() {
+        let params = |schedule| CommunicationParams {
+            schedule,
+            neighbours_only: false,
+        };
+        assert!(check_communication(&params(CommunicationSchedule::Never)).is_ok());
+        assert!(check_communication(&params(CommunicationSchedule::EveryNRounds { n: 2 })).is_ok());
+        assert!(
+            check_communication(&params(CommunicationSchedule::EveryNRounds { n: 0 })).is_err()
+        );
+    }
+
+    #[test]
+    fn neighbours_are_next_to_each_other_in_the_circle_of_players() {
+        let players = vec![agent(1), agent(2), agent(3), agent(4)];
+        assert!(are_neighbours(&players, &agent(1), &agent(2)));
+        assert!(are_neighbours(&players, &agent(2), &agent(1)));
+        assert!(are_neighbours(&players, &agent(4), &agent(1)));
+        assert!(!are_neighbours(&players, &agent(1), &agent(3)));
+        assert!(!are_neighbours(&players, &agent(1), &agent(1)));
+        assert!(!are_neighbours(&players, &agent(1), &agent(5)));
+    }
+
+    #[test]
+    fn single_player_has_no_neighbours() {
+        assert!(!are_neighbours(&[agent(1)], &agent(1), &agent(1)));
+    }
+}
@@ -0,0 +1,538 @@
+use crate::{
+    game_code::{create_game_code_anchor, get_game_code_anchor},
+    game_round::GameRound,
+    game_session::{
+        end_game, start_game_session_with_params, GameParams, GameSession, SessionState,
+        GAME_CODE_TO_SESSION_TAG, SESSION_TO_ROUND_TAG,
+    },
+    player_profile::{get_player_profiles_for_game_code, PlayerProfile},
+    utils::{
+        must_get_entry_struct, try_from_element, try_from_entry, try_get_and_convert,
+        try_get_element,
+    },
+};
+use hdk::prelude::*;
+
+pub const FACILITATOR_LINK_TAG: &str = "FACILITATOR";
+pub const FACILITATED_LOBBY_LINK_TAG: &str = "FACILITATED_LOBBY";
+pub const SESSION_CONTROL_LINK_TAG: &str = "SESSION_CONTROL";
+// max number of lobbies a facilitator can create at once
+pub const MAX_LOBBY_BATCH_SIZE: u32 = 20;
+// number of characters in the generated game codes
+pub const GAME_CODE_LENGTH: usize = 6;
+// characters of the generated game codes: there are no 0/O and 1/I
+// to make the codes easier to read out loud in a classroom
+const GAME_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Record that the facilitator is in charge of a lobby. The game code of the lobby
+/// is made from the entry hash of the record, so nobody can make rights for the
+/// lobby of someone else. It's linked from the game code anchor, so everyone can check it
+#[hdk_entry(id = "facilitator_rights", visibility = "public")]
+#[derive(Clone)]
+pub struct FacilitatorRights {
+    pub facilitator: AgentPubKey,
+    // random bytes that give every record of the facilitator a different game code
+    pub nonce: Vec<u8>,
+}
+
+/// NOTE: pausing and ending the session is enforced by the zome fns: new_move,
+/// try_to_close_round and the other fns that act in a round check that the session
+/// isn't halted. Validation can't check it, since the controls are only found
+/// through the links on DHT, so whatever an agent commits while the session is
+/// halted and they haven't seen the control yet still counts
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SessionControlAction {
+    Pause,
+    Resume,
+    // ends the game with the state of the latest round
+    End,
+}
+
+/// Action the facilitator took to control the game session
+#[hdk_entry(id = "session_control", visibility = "public")]
+#[derive(Clone)]
+pub struct SessionControl {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // FacilitatorRights that put the owner in charge of the session's lobby
+    pub rights: EntryHash,
+    pub action: SessionControlAction,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FacilitatedStartInput {
+    pub game_codes: Vec<String>,
+    pub game_params: GameParams,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionControlInput {
+    pub session_hash: EntryHash,
+    pub action: SessionControlAction,
+}
+
+/// Helper struct to package the live state of a facilitated session for the UI
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FacilitatedSession {
+    pub session_hash: EntryHash,
+    pub session: GameSession,
+    pub paused: bool,
+    pub round_hash: EntryHash,
+    pub round: GameRound,
+}
+
+/// Helper struct to package everything the facilitator can see about a lobby
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FacilitatedLobby {
+    pub game_code: String,
+    pub players: Vec<PlayerProfile>,
+    // None if the facilitator hasn't started the session yet
+    pub session: Option<FacilitatedSession>,
+}
+
+/// Generates `count` new game codes, creates their lobbies and records
+/// the agent executing this fn as the facilitator of all of them
+pub fn create_lobby_batch(count: u32) -> ExternResult<Vec<String>> {
+    if count == 0 || count > MAX_LOBBY_BATCH_SIZE {
+        return Err(WasmError::Guest(format!(
+            "Batch has to have between 1 and {} lobbies, but it has {}",
+            MAX_LOBBY_BATCH_SIZE, count
+        )));
+    }
+    let facilitator = agent_info()?.agent_latest_pubkey;
+    let mut game_codes: Vec<String> = vec![];
+    while game_codes.len() < count as usize {
+        let rights = FacilitatorRights {
+            facilitator: facilitator.clone(),
+            nonce: random_bytes(GAME_CODE_LENGTH as u32)?.to_vec(),
+        };
+        let rights_hash = hash_entry(&rights)?;
+        let game_code = game_code_of_rights(&rights_hash);
+        // codes that someone already uses for their lobby are drawn again,
+        // otherwise we'd claim a lobby that isn't ours
+        if game_codes.contains(&game_code)
+            || get(
+                get_game_code_anchor(game_code.clone())?,
+                GetOptions::latest(),
+            )?
+            .is_some()
+        {
+            continue;
+        }
+        let anchor = create_game_code_anchor(game_code.clone())?;
+        create_entry(&rights)?;
+        // Link from the lobby so that everyone can check who is in charge of it
+        create_link(
+            anchor.clone(),
+            rights_hash,
+            LinkTag::new(String::from(FACILITATOR_LINK_TAG)),
+        )?;
+        // Link from the facilitator so that they can find all their lobbies
+        create_link(
+            facilitator.clone().into(),
+            anchor,
+            LinkTag::new(String::from(FACILITATED_LOBBY_LINK_TAG)),
+        )?;
+        game_codes.push(game_code);
+    }
+    Ok(game_codes)
+}
+
+/// Turns random bytes into a game code, one character per byte
+fn game_code_from_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| GAME_CODE_ALPHABET[*b as usize % GAME_CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Makes the game code of the lobby the rights at rights_hash are for.
+/// Claiming the lobby of a game code someone else uses would take
+/// making rights records until one of them gets the same code
+pub fn game_code_of_rights(rights_hash: &EntryHash) -> String {
+    game_code_from_bytes(&rights_hash.get_raw_32()[..GAME_CODE_LENGTH])
+}
+
+/// Get the facilitator of the lobby at the game code anchor.
+/// Returns None if nobody is in charge of the lobby
+pub fn get_lobby_facilitator(anchor: EntryHash) -> ExternResult<Option<AgentPubKey>> {
+    Ok(get_lobby_rights(anchor)?.map(|(_, rights)| rights.facilitator))
+}
+
+/// Get the rights record of the facilitator in charge of the lobby at the game code
+/// anchor, together with it's entry hash. Returns None if nobody is in charge of the lobby.
+/// Validation makes sure that only the rights for the lobby's game code are linked
+/// from it. If several rights records got the same code, the one with the smallest
+/// entry hash is used
+pub fn get_lobby_rights(anchor: EntryHash) -> ExternResult<Option<(EntryHash, FacilitatorRights)>> {
+    let links = get_links(
+        anchor,
+        Some(LinkTag::new(String::from(FACILITATOR_LINK_TAG))),
+    )?;
+    match links.into_inner().into_iter().map(|l| l.target).min() {
+        Some(rights_hash) => {
+            let rights: FacilitatorRights =
+                try_get_and_convert(rights_hash.clone(), GetOptions::latest())?;
+            Ok(Some((rights_hash, rights)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Starts the sessions of the lobbies with the same params. The agent executing
+/// this fn has to be the facilitator of every lobby. Returns the hashes of the round zero
+/// of every session, in the same order as the game codes
+pub fn start_facilitated_sessions(input: FacilitatedStartInput) -> ExternResult<Vec<EntryHash>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    // check every lobby first so that we don't start only some of them
+    for game_code in &input.game_codes {
+        let anchor = get_game_code_anchor(game_code.clone())?;
+        if get_lobby_facilitator(anchor)? != Some(me.clone()) {
+            return Err(WasmError::Guest(format!(
+                "Can't start the session of the lobby {}: you aren't it's facilitator",
+                game_code
+            )));
+        }
+    }
+    let mut round_zero_hashes: Vec<EntryHash> = vec![];
+    for game_code in input.game_codes {
+        round_zero_hashes.push(start_game_session_with_params(
+            game_code,
+            input.game_params.clone(),
+            None,
+            None,
+        )?);
+    }
+    Ok(round_zero_hashes)
+}
+
+/// Follows the update chain of the game rounds from the round zero to the latest round.
+/// If several agents closed the same round, the update with the smallest entry hash is used
+pub fn get_latest_round(session_hash: EntryHash) -> ExternResult<Option<(EntryHash, GameRound)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(SESSION_TO_ROUND_TAG))),
+    )?;
+    let mut round_hash = match links.into_inner().into_iter().map(|l| l.target).min() {
+        Some(round_hash) => round_hash,
+        None => return Ok(None),
+    };
+    loop {
+        let next_round_hash = match get_details(round_hash.clone(), GetOptions::latest())? {
+            Some(Details::Entry(entry_details)) => entry_details
+                .updates
+                .iter()
+                .filter_map(|update_header| update_header.header().entry_hash().cloned())
+                .min(),
+            _ => None,
+        };
+        match next_round_hash {
+            Some(next_round_hash) => round_hash = next_round_hash,
+            None => break,
+        }
+    }
+    let round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    Ok(Some((round_hash, round)))
+}
+
+/// Get all control actions the facilitator took in the session, in the order they were taken
+pub fn get_session_controls(
+    session_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, SessionControl)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(SESSION_CONTROL_LINK_TAG))),
+    )?;
+    let mut controls: Vec<(EntryHash, SessionControl)> = vec![];
+    for link in links.into_inner() {
+        let control: SessionControl =
+            try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        controls.push((link.target, control));
+    }
+    controls.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then(a.0.cmp(&b.0)));
+    Ok(controls)
+}
+
+/// Checks if the session is paused, going by the latest Pause or Resume action
+pub fn is_session_paused(controls: &[(EntryHash, SessionControl)]) -> bool {
+    controls
+        .iter()
+        .rev()
+        .find(|(_, c)| c.action != SessionControlAction::End)
+        .map_or(false, |(_, c)| c.action == SessionControlAction::Pause)
+}
+
+/// Checks if the facilitator has paused or ended the session, so that
+/// nobody can move or close rounds in it
+pub fn is_session_halted(session_hash: EntryHash) -> ExternResult<bool> {
+    let controls = get_session_controls(session_hash)?;
+    Ok(is_session_paused(&controls)
+        || controls
+            .iter()
+            .any(|(_, c)| c.action == SessionControlAction::End))
+}
+
+/// Pauses, resumes or ends the session. Only the facilitator of the session's lobby
+/// can do that. Ending the session ends the game with the state of the latest round
+pub fn control_session(input: SessionControlInput) -> ExternResult<EntryHash> {
+    let session_element = try_get_element(input.session_hash.clone(), GetOptions::latest())?;
+    let game_session: GameSession = try_from_element(session_element.clone())?;
+    let me = agent_info()?.agent_latest_pubkey;
+    let rights_hash = match get_lobby_rights(game_session.anchor.clone())? {
+        Some((rights_hash, rights)) if rights.facilitator == me => rights_hash,
+        _ => {
+            return Err(WasmError::Guest(
+                "Only the facilitator of the lobby can control it's GameSession".into(),
+            ))
+        }
+    };
+    let control = SessionControl {
+        owner: me,
+        session: input.session_hash.clone(),
+        rights: rights_hash,
+        action: input.action.clone(),
+        timestamp: sys_time()?,
+    };
+    create_entry(&control)?;
+    let control_entry_hash = hash_entry(&control)?;
+    create_link(
+        input.session_hash.clone(),
+        control_entry_hash.clone(),
+        LinkTag::new(String::from(SESSION_CONTROL_LINK_TAG)),
+    )?;
+
+    if input.action == SessionControlAction::End {
+        let (round_hash, round) = get_latest_round(input.session_hash)?.ok_or(WasmError::Guest(
+            "Can't end the GameSession: it doesn't have any rounds".into(),
+        ))?;
+        end_game(
+            &game_session,
+            session_element.header_address(),
+            &round,
+            &round_hash,
+            &round.state,
+        )?;
+    }
+    Ok(control_entry_hash)
+}
+
+/// Get the players, the session and it's latest round of every lobby
+/// the agent executing this fn is in charge of
+pub fn get_facilitated_lobbies() -> ExternResult<Vec<FacilitatedLobby>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let links = get_links(
+        me.into(),
+        Some(LinkTag::new(String::from(FACILITATED_LOBBY_LINK_TAG))),
+    )?;
+    let mut lobbies: Vec<FacilitatedLobby> = vec![];
+    for link in links.into_inner() {
+        let game_code = get_anchor(link.target.clone())?
+            .and_then(|anchor| anchor.anchor_text)
+            .unwrap_or_default();
+        let session_links = get_links(
+            link.target,
+            Some(LinkTag::new(String::from(GAME_CODE_TO_SESSION_TAG))),
+        )?;
+        let session = match session_links
+            .into_inner()
+            .into_iter()
+            .map(|l| l.target)
+            .min()
+        {
+            Some(session_hash) => {
+                let session: GameSession =
+                    try_get_and_convert(session_hash.clone(), GetOptions::latest())?;
+                match get_latest_round(session_hash.clone())? {
+                    Some((round_hash, round)) => Some(FacilitatedSession {
+                        paused: is_session_paused(&get_session_controls(session_hash.clone())?),
+                        session_hash,
+                        session,
+                        round_hash,
+                        round,
+                    }),
+                    None => None,
+                }
+            }
+            None => None,
+        };
+        lobbies.push(FacilitatedLobby {
+            players: get_player_profiles_for_game_code(game_code.clone())?,
+            game_code,
+            session,
+        });
+    }
+    Ok(lobbies)
+}
+
+/// Validates creation of FacilitatorRights entries
+pub fn validate_create_entry_facilitator_rights(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let rights: FacilitatorRights = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &rights.facilitator {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "FacilitatorRights can only be created by the facilitator",
+        )));
+    }
+
+    // NOTE: the game code of the rights is made from their entry hash, and
+    // validate_create_link_facilitator only lets the rights be linked from the
+    // lobby of that code. create_lobby_batch only claims lobbies whose game code
+    // nobody uses yet
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of FacilitatorRights entries
+pub fn validate_update_entry_facilitator_rights(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update FacilitatorRights entry",
+    )))
+}
+
+/// Validates delete of FacilitatorRights entries
+pub fn validate_delete_entry_facilitator_rights(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete FacilitatorRights entry",
+    )))
+}
+
+/// Validates creation of SessionControl entries
+pub fn validate_create_entry_session_control(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let control: SessionControl = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &control.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "SessionControl can only be created by it's owner",
+        )));
+    }
+
+    if control.timestamp > data.element.header().timestamp() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "SessionControl can't have a timestamp later than the header that creates it",
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(control.session)?;
+    if game_session.status != SessionState::InProgress {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "SessionControl has to be made for the GameSession entry that started the game",
+        )));
+    }
+    // the facilitator starts the sessions of their lobbies, so they own them, and
+    // the rights they refer to have to be for the session's lobby
+    let rights = must_get_entry_struct::<FacilitatorRights>(control.rights.clone())?;
+    if rights.facilitator != control.owner
+        || game_session.owner != control.owner
+        || get_game_code_anchor(game_code_of_rights(&control.rights))? != game_session.anchor
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only the facilitator of the lobby can control it's GameSession",
+        )));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates creation of FACILITATOR links: the facilitator can only link their
+/// rights from the lobby of the game code made from the rights' entry hash.
+/// Links with other tags are valid
+pub fn validate_create_link_facilitator(
+    data: ValidateCreateLinkData,
+) -> ExternResult<ValidateLinkCallbackResult> {
+    let link_add = data.link_add;
+    if link_add.tag != LinkTag::new(String::from(FACILITATOR_LINK_TAG)) {
+        return Ok(ValidateLinkCallbackResult::Valid);
+    }
+    let rights: FacilitatorRights = match try_from_entry(data.target) {
+        Ok(rights) => rights,
+        Err(_) => {
+            return Ok(ValidateLinkCallbackResult::Invalid(String::from(
+                "FACILITATOR link has to point to a FacilitatorRights entry",
+            )))
+        }
+    };
+    if rights.facilitator != link_add.author {
+        return Ok(ValidateLinkCallbackResult::Invalid(String::from(
+            "FacilitatorRights can only be linked by the facilitator",
+        )));
+    }
+    if get_game_code_anchor(game_code_of_rights(&link_add.target_address))? != link_add.base_address
+    {
+        return Ok(ValidateLinkCallbackResult::Invalid(String::from(
+            "FacilitatorRights can only be linked from the lobby of their game code",
+        )));
+    }
+    Ok(ValidateLinkCallbackResult::Valid)
+}
+
+/// Validates update of SessionControl entries
+pub fn validate_update_entry_session_control(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update SessionControl entry",
+    )))
+}
+
+/// Validates delete of SessionControl entries
+pub fn validate_delete_entry_session_control(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete SessionControl entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{control, hash};
+
+    #[test]
+    fn session_is_paused_by_the_latest_pause_or_resume() {
+        use SessionControlAction::*;
+        assert!(!is_session_paused(&[]));
+        assert!(is_session_paused(&[control(1, Pause)]));
+        assert!(!is_session_paused(&[control(1, Pause), control(2, Resume)]));
+        assert!(is_session_paused(&[
+            control(1, Pause),
+            control(2, Resume),
+            control(3, Pause),
+        ]));
+    }
+
+    #[test]
+    fn ending_the_session_doesnt_change_if_its_paused() {
+        use SessionControlAction::*;
+        assert!(!is_session_paused(&[control(1, End)]));
+        assert!(is_session_paused(&[control(1, Pause), control(2, End)]));
+    }
+
+    #[test]
+    fn game_codes_are_easy_to_read_out_loud() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let game_code = game_code_from_bytes(&bytes);
+        assert_eq!(game_code.len(), 256);
+        assert!(game_code
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()));
+        assert!(!game_code.contains(|c| c == '0' || c == 'O' || c == '1' || c == 'I'));
+        assert_eq!(game_code_from_bytes(&[0, 31, 32]), "A9A");
+    }
+
+    #[test]
+    fn game_code_is_made_from_the_rights_hash() {
+        assert_eq!(game_code_of_rights(&hash(1)), "BBBBBB");
+        assert_eq!(game_code_of_rights(&hash(33)), "BBBBBB");
+        assert_ne!(game_code_of_rights(&hash(2)), game_code_of_rights(&hash(1)));
+    }
+}
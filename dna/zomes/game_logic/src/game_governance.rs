@@ -0,0 +1,505 @@
+use crate::{
+    game_session::{check_game_params, get_ended_session, GameParams, GameSession, ResourceAmount},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const PROPOSAL_LINK_TAG: &str = "PROPOSAL";
+pub const VOTE_LINK_TAG: &str = "VOTE";
+
+/// Defines how many players have to vote in favor of a proposal for it to be accepted
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum MajorityRule {
+    // more than a half of the players
+    Simple,
+    // at least two thirds of the players
+    TwoThirds,
+    // every player
+    Unanimous,
+}
+
+impl MajorityRule {
+    /// Checks if votes_in_favor out of number_of_players is enough to accept a proposal
+    pub fn is_reached(&self, votes_in_favor: usize, number_of_players: usize) -> bool {
+        match self {
+            MajorityRule::Simple => votes_in_favor * 2 > number_of_players,
+            MajorityRule::TwoThirds => votes_in_favor * 3 >= number_of_players * 2,
+            MajorityRule::Unanimous => votes_in_favor == number_of_players,
+        }
+    }
+}
+
+/// Changes to the GameParams that players can propose during the game
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "change_name", content = "change_value")]
+pub enum ParamsChange {
+    HarvestCap(Option<ResourceAmount>),
+    RegenerationFactor(f32),
+    SanctionFactor(Option<ResourceAmount>),
+    // setting it lower than the original number of rounds ends the game early
+    NumRounds(u32),
+}
+
+impl ParamsChange {
+    /// Applies this change to the game params
+    pub fn apply(&self, params: &mut GameParams) {
+        match self {
+            ParamsChange::HarvestCap(harvest_cap) => params.harvest_cap = *harvest_cap,
+            ParamsChange::RegenerationFactor(factor) => params.regeneration_factor = *factor,
+            ParamsChange::SanctionFactor(factor) => params.sanction_factor = *factor,
+            ParamsChange::NumRounds(num_rounds) => params.num_rounds = *num_rounds,
+        }
+    }
+}
+
+#[hdk_entry(id = "proposal", visibility = "public")]
+#[derive(Clone)]
+pub struct Proposal {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    pub change: ParamsChange,
+}
+
+#[hdk_entry(id = "vote", visibility = "public")]
+#[derive(Clone)]
+pub struct Vote {
+    pub owner: AgentPubKey,
+    pub proposal: EntryHash,
+    pub in_favor: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProposalInput {
+    pub session_hash: EntryHash,
+    pub change: ParamsChange,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VoteInput {
+    pub proposal_hash: EntryHash,
+    pub in_favor: bool,
+}
+
+/// Creates a new proposal entry and links it from the game session
+pub fn new_proposal(session_hash: EntryHash, change: ParamsChange) -> ExternResult<EntryHash> {
+    // the session entry itself is never updated in place, so we look for the update
+    // that ended it.
+    // NOTE: this check depends on what the agent can see on DHT, so it's
+    // done here instead of the validation of Proposal. Proposals made after the
+    // session has ended are never applied, since no round is closed after that
+    if get_ended_session(session_hash.clone())?.is_some() {
+        return Err(WasmError::Guest(String::from(
+            "Can't make a proposal for the GameSession that has ended",
+        )));
+    }
+    let proposal = Proposal {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: session_hash.clone(),
+        change,
+    };
+    create_entry(&proposal)?;
+    let proposal_entry_hash = hash_entry(&proposal)?;
+
+    // Link from the game session so that every player can discover proposals
+    create_link(
+        session_hash,
+        proposal_entry_hash.clone(),
+        LinkTag::new(String::from(PROPOSAL_LINK_TAG)),
+    )?;
+
+    // Return the hash of the proposal because players would need it to vote
+    Ok(proposal_entry_hash)
+}
+
+/// Creates a new vote entry and links it from the game session of the proposal
+pub fn new_vote(proposal_hash: EntryHash, in_favor: bool) -> ExternResult<HeaderHash> {
+    let proposal: Proposal = try_get_and_convert(proposal_hash.clone(), GetOptions::latest())?;
+
+    // Votes are written by the players themselves, so their earlier votes
+    // are already on their source chain
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .header_type(HeaderType::Create)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(Vote)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Public,
+        )));
+    for el in query(filter)? {
+        let prev_vote: Vote = try_from_element(el)?;
+        if prev_vote.proposal == proposal_hash {
+            return Err(WasmError::Guest(String::from(
+                "Players can only vote once per proposal",
+            )));
+        }
+    }
+
+    let vote = Vote {
+        owner: agent_info()?.agent_latest_pubkey,
+        proposal: proposal_hash,
+        in_favor,
+    };
+    create_entry(&vote)?;
+    let vote_entry_hash = hash_entry(&vote)?;
+
+    // Votes are linked from the session as well, so whoever closes the round
+    // can collect all of them in one go
+    create_link(
+        proposal.session,
+        vote_entry_hash,
+        LinkTag::new(String::from(VOTE_LINK_TAG)),
+    )
+}
+
+/// Get all proposals made in the game session, together with their entry hashes
+pub fn get_proposals_for_session(
+    session_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, Proposal)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(PROPOSAL_LINK_TAG))),
+    )?;
+    let mut proposals: Vec<(EntryHash, Proposal)> = vec![];
+    for link in links.into_inner() {
+        let proposal: Proposal = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        proposals.push((link.target, proposal));
+    }
+    Ok(proposals)
+}
+
+/// Get all votes made in the game session, together with their entry hashes
+pub fn get_votes_for_session(session_hash: EntryHash) -> ExternResult<Vec<(EntryHash, Vote)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(VOTE_LINK_TAG))),
+    )?;
+    let mut votes: Vec<(EntryHash, Vote)> = vec![];
+    for link in links.into_inner() {
+        let vote: Vote = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        votes.push((link.target, vote));
+    }
+    Ok(votes)
+}
+
+/// Applies changes from the proposals that reached the majority and weren't applied
+/// yet to the params. Returns updated params together with the hashes of all
+/// proposals applied so far and the hashes of all votes on the proposals that
+/// were applied now, both in favor and against, so they can be counted again.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent:
+/// - proposals are applied in the order of their entry hashes
+/// - every player's vote counts once; if a player somehow has voted both in favor
+///   and against the same proposal, their vote doesn't count as in favor
+/// - votes from agents who aren't players are ignored
+pub fn apply_accepted_proposals(
+    params: &GameParams,
+    applied_proposals: &[EntryHash],
+    mut proposals: Vec<(EntryHash, Proposal)>,
+    votes: Vec<(EntryHash, Vote)>,
+    players: &[AgentPubKey],
+) -> (GameParams, Vec<EntryHash>, Vec<EntryHash>) {
+    // whether every player's votes on the proposal count as in favor,
+    // together with the hashes of all of them
+    let mut votes_per_player: BTreeMap<(EntryHash, AgentPubKey), (bool, Vec<EntryHash>)> =
+        BTreeMap::new();
+    for (vote_hash, v) in votes {
+        if !players.contains(&v.owner) {
+            continue;
+        }
+        let vote = votes_per_player
+            .entry((v.proposal, v.owner))
+            .or_insert((true, vec![]));
+        vote.0 = vote.0 && v.in_favor;
+        if !vote.1.contains(&vote_hash) {
+            vote.1.push(vote_hash);
+        }
+    }
+
+    proposals.sort_by(|a, b| a.0.cmp(&b.0));
+    proposals.dedup_by(|a, b| a.0 == b.0);
+
+    let mut new_params = params.clone();
+    let mut new_applied_proposals = applied_proposals.to_vec();
+    let mut counted_votes: Vec<EntryHash> = vec![];
+    for (proposal_hash, proposal) in proposals {
+        if applied_proposals.contains(&proposal_hash) {
+            continue;
+        }
+        let proposal_votes: Vec<&(bool, Vec<EntryHash>)> = votes_per_player
+            .iter()
+            .filter(|((p, _), _)| *p == proposal_hash)
+            .map(|(_, vote)| vote)
+            .collect();
+        let votes_in_favor = proposal_votes
+            .iter()
+            .filter(|(in_favor, _)| *in_favor)
+            .count();
+        if params
+            .majority_rule
+            .is_reached(votes_in_favor, players.len())
+        {
+            info!("Proposal {:?} was accepted", proposal_hash);
+            proposal.change.apply(&mut new_params);
+            new_applied_proposals.push(proposal_hash);
+            for (_, vote_hashes) in proposal_votes {
+                counted_votes.extend(vote_hashes.iter().cloned());
+            }
+        }
+    }
+    counted_votes.sort();
+    (new_params, new_applied_proposals, counted_votes)
+}
+
+/// Loads the proposals accepted during the round and all votes on them by their
+/// hashes stored in the round, so every validating agent counts the same votes.
+/// Proposals accepted during the round are the ones that follow the proposals
+/// accepted before it. Returns None if the proposals weren't made in the session
+/// at session_hash, or the votes aren't on those proposals.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_accepted_proposals(
+    session_hash: &EntryHash,
+    prev_accepted_proposals: &[EntryHash],
+    accepted_proposals: &[EntryHash],
+    accepted_votes: &[EntryHash],
+) -> ExternResult<Option<(Vec<(EntryHash, Proposal)>, Vec<(EntryHash, Vote)>)>> {
+    // proposals accepted earlier can't disappear from the list
+    if !accepted_proposals.starts_with(prev_accepted_proposals) {
+        return Ok(None);
+    }
+    let mut proposals: Vec<(EntryHash, Proposal)> = vec![];
+    for proposal_hash in &accepted_proposals[prev_accepted_proposals.len()..] {
+        let proposal = must_get_entry_struct::<Proposal>(proposal_hash.clone())?;
+        if &proposal.session != session_hash {
+            return Ok(None);
+        }
+        proposals.push((proposal_hash.clone(), proposal));
+    }
+    let mut votes: Vec<(EntryHash, Vote)> = vec![];
+    for vote_hash in accepted_votes {
+        let vote = must_get_entry_struct::<Vote>(vote_hash.clone())?;
+        if !proposals.iter().any(|(h, _)| h == &vote.proposal) {
+            return Ok(None);
+        }
+        votes.push((vote_hash.clone(), vote));
+    }
+    Ok(Some((proposals, votes)))
+}
+
+/// Validates creation of Proposal entries
+pub fn validate_create_entry_proposal(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let proposal: Proposal = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &proposal.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Proposal can only be created by it's owner",
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(proposal.session.clone())?;
+    if !game_session.players.contains(&proposal.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can make proposals",
+        )));
+    }
+    // NOTE: new_proposal checks that the GameSession hasn't ended yet
+
+    // GameRound validation relies on the original number of rounds,
+    // so players can only agree to end the game earlier
+    if let ParamsChange::NumRounds(num_rounds) = proposal.change {
+        if num_rounds > game_session.game_params.num_rounds {
+            return Ok(ValidateCallbackResult::Invalid(format!(
+                "Number of rounds can't be greater than {}, but it is {}",
+                game_session.game_params.num_rounds, num_rounds
+            )));
+        }
+    }
+    let mut params = game_session.game_params.clone();
+    proposal.change.apply(&mut params);
+    match check_game_params(&params) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(WasmError::Guest(e)) => Ok(ValidateCallbackResult::Invalid(e)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Validates update of Proposal entries
+pub fn validate_update_entry_proposal(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update Proposal entry",
+    )))
+}
+
+/// Validates delete of Proposal entries
+pub fn validate_delete_entry_proposal(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete Proposal entry",
+    )))
+}
+
+/// Validates creation of Vote entries
+pub fn validate_create_entry_vote(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let vote: Vote = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &vote.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Vote can only be created by it's owner",
+        )));
+    }
+
+    let proposal = must_get_entry_struct::<Proposal>(vote.proposal.clone())?;
+    let game_session = must_get_entry_struct::<GameSession>(proposal.session.clone())?;
+    if !game_session.players.contains(&vote.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can vote",
+        )));
+    }
+
+    // NOTE: new_vote checks that the player hasn't voted for this proposal yet.
+    // Validation can't check it without looking at what's on DHT, so
+    // apply_accepted_proposals is careful about counting player's vote once
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of Vote entries
+pub fn validate_update_entry_vote(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update Vote entry",
+    )))
+}
+
+/// Validates delete of Vote entries
+pub fn validate_delete_entry_vote(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete Vote entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, hash};
+
+    fn proposal(n: u8, change: ParamsChange) -> (EntryHash, Proposal) {
+        (
+            hash(n),
+            Proposal {
+                owner: agent(1),
+                session: hash(0),
+                change,
+            },
+        )
+    }
+
+    fn vote(n: u8, owner: u8, proposal: u8, in_favor: bool) -> (EntryHash, Vote) {
+        (
+            hash(n),
+            Vote {
+                owner: agent(owner),
+                proposal: hash(proposal),
+                in_favor,
+            },
+        )
+    }
+
+    #[test]
+    fn majority_rules_need_enough_votes_in_favor() {
+        assert!(!MajorityRule::Simple.is_reached(2, 4));
+        assert!(MajorityRule::Simple.is_reached(3, 4));
+        assert!(!MajorityRule::TwoThirds.is_reached(1, 3));
+        assert!(MajorityRule::TwoThirds.is_reached(2, 3));
+        assert!(!MajorityRule::Unanimous.is_reached(2, 3));
+        assert!(MajorityRule::Unanimous.is_reached(3, 3));
+    }
+
+    #[test]
+    fn accepted_proposal_changes_params_once() {
+        let players = vec![agent(1), agent(2), agent(3)];
+        let proposals = vec![proposal(10, ParamsChange::HarvestCap(Some(5)))];
+        let votes = vec![
+            vote(20, 1, 10, true),
+            vote(21, 2, 10, true),
+            vote(22, 3, 10, false),
+        ];
+        let (params, applied, counted_votes) = apply_accepted_proposals(
+            &GameParams::default(),
+            &[],
+            proposals.clone(),
+            votes.clone(),
+            &players,
+        );
+        assert_eq!(params.harvest_cap, Some(5));
+        assert_eq!(applied, vec![hash(10)]);
+        // votes against are stored too, so the majority can be counted again
+        assert_eq!(counted_votes, vec![hash(20), hash(21), hash(22)]);
+
+        // the same proposal isn't applied again in the next round
+        let (next_params, next_applied, next_votes) =
+            apply_accepted_proposals(&params, &applied, proposals, votes, &players);
+        assert_eq!(next_params, params);
+        assert_eq!(next_applied, applied);
+        assert!(next_votes.is_empty());
+    }
+
+    #[test]
+    fn repeated_and_outsider_votes_dont_count() {
+        let players = vec![agent(1), agent(2), agent(3)];
+        let proposals = vec![proposal(10, ParamsChange::NumRounds(2))];
+        let votes = vec![
+            vote(20, 1, 10, true),
+            vote(21, 1, 10, true),
+            vote(22, 4, 10, true),
+            vote(23, 5, 10, true),
+        ];
+        let (params, applied, counted_votes) =
+            apply_accepted_proposals(&GameParams::default(), &[], proposals, votes, &players);
+        assert_eq!(params, GameParams::default());
+        assert!(applied.is_empty());
+        assert!(counted_votes.is_empty());
+    }
+
+    #[test]
+    fn voting_both_ways_isnt_a_vote_in_favor() {
+        let players = vec![agent(1), agent(2)];
+        let proposals = vec![proposal(10, ParamsChange::SanctionFactor(Some(2)))];
+        let votes = vec![
+            vote(20, 1, 10, true),
+            vote(21, 2, 10, true),
+            vote(22, 2, 10, false),
+        ];
+        let (params, _, _) =
+            apply_accepted_proposals(&GameParams::default(), &[], proposals, votes, &players);
+        assert_eq!(params.sanction_factor, None);
+    }
+
+    #[test]
+    fn every_vote_on_the_accepted_proposal_is_counted() {
+        let players = vec![agent(1), agent(2), agent(3)];
+        let proposals = vec![proposal(10, ParamsChange::SanctionFactor(Some(2)))];
+        let votes = vec![
+            vote(20, 1, 10, true),
+            vote(21, 2, 10, true),
+            vote(22, 3, 10, true),
+            vote(23, 3, 10, false),
+        ];
+        let (params, _, counted_votes) =
+            apply_accepted_proposals(&GameParams::default(), &[], proposals, votes, &players);
+        assert_eq!(params.sanction_factor, Some(2));
+        assert_eq!(counted_votes, vec![hash(20), hash(21), hash(22), hash(23)]);
+    }
+
+    #[test]
+    fn proposals_are_applied_in_the_order_of_their_hashes() {
+        let players = vec![agent(1)];
+        let proposals = vec![
+            proposal(11, ParamsChange::HarvestCap(Some(7))),
+            proposal(10, ParamsChange::HarvestCap(Some(5))),
+        ];
+        let votes = vec![vote(20, 1, 10, true), vote(21, 1, 11, true)];
+        let (params, applied, _) =
+            apply_accepted_proposals(&GameParams::default(), &[], proposals, votes, &players);
+        assert_eq!(params.harvest_cap, Some(7));
+        assert_eq!(applied, vec![hash(10), hash(11)]);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    game_move::GameMove,
+    game_round::GameRound,
+    game_session::{PlayerStats, ResourceAmount},
+};
+use hdk::prelude::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct InvestmentParams {
+    // if true, players can put some of their resources back into the stock
+    pub restocking: bool,
+    // interest the bank pays on savings every round, e.g. 0.05 is 5%.
+    // None means there's no bank
+    pub interest_rate: Option<f32>,
+}
+
+/// Amounts of resources players reinvested and saved during the round
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InvestmentResult {
+    // resources every player put back into the stock
+    pub restocked: PlayerStats,
+    // savings of every player in the bank, including interest and new deposits
+    pub bank_balances: PlayerStats,
+}
+
+/// Makes sure that the investment params make sense for the game
+pub fn check_investment(params: &InvestmentParams) -> ExternResult<()> {
+    match params.interest_rate {
+        Some(interest_rate) if !(0.0..=f32::MAX).contains(&interest_rate) => Err(WasmError::Guest(
+            format!("Interest rate has to be >= 0, but it is {}", interest_rate),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Pays interest on the savings from the previous rounds, then takes resources
+/// players reinvest and save with their moves from the player_stats.
+/// NOTE: this fn is used when calculating the round state, so it has to give the
+/// same result for every agent. Moves are already sorted by the owner, and
+/// validation can only check reinvestments against what the player could have at
+/// most, so if a player can't afford everything anymore (e.g. because of taxes),
+/// they reinvest and save as much as they have, reinvestments first.
+/// Interest is rounded down for every player.
+pub fn apply_investments(
+    player_stats: &mut PlayerStats,
+    prev_bank_balances: &PlayerStats,
+    player_moves: &[GameMove],
+    params: &InvestmentParams,
+) -> InvestmentResult {
+    let mut bank_balances = PlayerStats::new();
+    if let Some(interest_rate) = params.interest_rate {
+        for (player, saved) in prev_bank_balances {
+            let with_interest = (*saved as f32 * (1.0 + interest_rate)) as ResourceAmount;
+            bank_balances.insert(player.clone(), with_interest);
+        }
+    }
+
+    let mut restocked = PlayerStats::new();
+    for m in player_moves {
+        let balance = std::cmp::max(*player_stats.get(&m.owner).unwrap_or(&0), 0);
+        let reinvest = if params.restocking {
+            std::cmp::min(m.reinvest_amount, balance)
+        } else {
+            0
+        };
+        let save = match params.interest_rate {
+            Some(_) => std::cmp::min(m.save_amount, balance - reinvest),
+            None => 0,
+        };
+        *player_stats.entry(m.owner.clone()).or_insert(0) -= reinvest + save;
+        if reinvest > 0 {
+            restocked.insert(m.owner.clone(), reinvest);
+        }
+        if save > 0 {
+            *bank_balances.entry(m.owner.clone()).or_insert(0) += save;
+        }
+    }
+    InvestmentResult {
+        restocked,
+        bank_balances,
+    }
+}
+
+/// Checks that the move only reinvests and saves resources if the game allows it,
+/// and that the player can afford it with what they have and what they take.
+/// Returns None if the move is valid
+pub fn validate_investment(
+    game_move: &GameMove,
+    game_round: &GameRound,
+) -> Option<ValidateCallbackResult> {
+    if game_move.reinvest_amount < 0 || game_move.save_amount < 0 {
+        return Some(ValidateCallbackResult::Invalid(format!(
+            "GameMove has to reinvest and save >= 0 resources, but it reinvests {} and saves {}",
+            game_move.reinvest_amount, game_move.save_amount
+        )));
+    }
+    let params = game_round.state.game_params.investment;
+    let can_restock = params.map_or(false, |p| p.restocking);
+    let can_save = params.map_or(false, |p| p.interest_rate.is_some());
+    if (game_move.reinvest_amount > 0 && !can_restock) || (game_move.save_amount > 0 && !can_save) {
+        return Some(ValidateCallbackResult::Invalid(String::from(
+            "GameMove can't reinvest or save resources: it's disabled for this GameSession",
+        )));
+    }
+    // players can only reinvest what they had before the round and what they take now
+    let balance = *game_round
+        .state
+        .player_stats
+        .get(&game_move.owner)
+        .unwrap_or(&0);
+    if game_move.reinvest_amount + game_move.save_amount > balance + game_move.resource_amount {
+        return Some(ValidateCallbackResult::Invalid(format!(
+            "GameMove reinvests and saves {} resources but the player only has {}",
+            game_move.reinvest_amount + game_move.save_amount,
+            balance + game_move.resource_amount
+        )));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{investing_move, stats};
+
+    fn investment(restocking: bool, interest_rate: Option<f32>) -> InvestmentParams {
+        InvestmentParams {
+            restocking,
+            interest_rate,
+        }
+    }
+
+    #[test]
+    fn interest_rate_cant_be_negative() {
+        assert!(check_investment(&investment(true, None)).is_ok());
+        assert!(check_investment(&investment(false, Some(0.0))).is_ok());
+        assert!(check_investment(&investment(false, Some(-0.1))).is_err());
+        assert!(check_investment(&investment(false, Some(f32::NAN))).is_err());
+    }
+
+    #[test]
+    fn savings_earn_interest_and_new_deposits_are_added() {
+        let mut player_stats = stats(&[(1, 10), (2, 10)]);
+        let result = apply_investments(
+            &mut player_stats,
+            &stats(&[(1, 10), (2, 3)]),
+            &[investing_move(1, 2, 3), investing_move(2, 0, 0)],
+            &investment(true, Some(0.5)),
+        );
+        assert_eq!(
+            result,
+            InvestmentResult {
+                restocked: stats(&[(1, 2)]),
+                // interest is rounded down: 3 * 1.5 is 4
+                bank_balances: stats(&[(1, 18), (2, 4)]),
+            }
+        );
+        assert_eq!(player_stats, stats(&[(1, 5), (2, 10)]));
+    }
+
+    #[test]
+    fn players_invest_only_what_they_have_reinvestments_first() {
+        let mut player_stats = stats(&[(1, 4), (2, -1)]);
+        let result = apply_investments(
+            &mut player_stats,
+            &PlayerStats::new(),
+            &[investing_move(1, 3, 3), investing_move(2, 1, 1)],
+            &investment(true, Some(0.0)),
+        );
+        assert_eq!(
+            result,
+            InvestmentResult {
+                restocked: stats(&[(1, 3)]),
+                bank_balances: stats(&[(1, 1)]),
+            }
+        );
+        assert_eq!(player_stats, stats(&[(1, 0), (2, -1)]));
+    }
+
+    #[test]
+    fn nothing_is_invested_when_the_game_doesnt_allow_it() {
+        let mut player_stats = stats(&[(1, 10)]);
+        let result = apply_investments(
+            &mut player_stats,
+            &stats(&[(1, 10)]),
+            &[investing_move(1, 2, 3)],
+            &investment(false, None),
+        );
+        assert_eq!(result, InvestmentResult::default());
+        assert_eq!(player_stats, stats(&[(1, 10)]));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    game_round::GameRound,
+    game_session::{get_ended_session, verify_ended_session, GameSession},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+
+pub const PUBLISHED_NOTE_LINK_TAG: &str = "PUBLISHED_NOTE";
+// max number of characters in a single note
+pub const MAX_JOURNAL_NOTE_LENGTH: usize = 5000;
+
+/// Note the player writes to record their reasoning during the round.
+/// It stays private on their source chain, so only they can read it
+#[hdk_entry(id = "journal_note", visibility = "private")]
+#[derive(Clone)]
+pub struct JournalNote {
+    pub session: EntryHash,
+    // round that was going on when the note was written
+    pub round_hash: EntryHash,
+    pub round_num: u32,
+    pub content: String,
+    pub timestamp: Timestamp,
+}
+
+/// Copy of a journal note that the player chose to publish after the game ended
+#[hdk_entry(id = "published_note", visibility = "public")]
+#[derive(Clone)]
+pub struct PublishedNote {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // header of the game session update that ended the game
+    pub ended_session: HeaderHash,
+    pub round_num: u32,
+    pub content: String,
+    // time when the original note was written
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalNoteInput {
+    pub round_hash: EntryHash,
+    pub content: String,
+}
+
+/// Checks that the note isn't empty and isn't too long. Length is counted
+/// in characters, so that notes in any language have the same limit
+fn note_length_is_valid(content: &str) -> bool {
+    let length = content.chars().count();
+    length > 0 && length <= MAX_JOURNAL_NOTE_LENGTH
+}
+
+/// Writes a new note to the private journal of the agent executing this fn
+pub fn write_journal_note(input: JournalNoteInput) -> ExternResult<EntryHash> {
+    if !note_length_is_valid(&input.content) {
+        return Err(WasmError::Guest(format!(
+            "Note has to have between 1 and {} characters, but it has {}",
+            MAX_JOURNAL_NOTE_LENGTH,
+            input.content.chars().count()
+        )));
+    }
+    let game_round: GameRound =
+        try_get_and_convert(input.round_hash.clone(), GetOptions::latest())?;
+    let note = JournalNote {
+        session: game_round.session,
+        round_hash: input.round_hash,
+        round_num: game_round.round_num,
+        content: input.content,
+        timestamp: sys_time()?,
+    };
+    // private entries are only stored on the source chain, so there's nothing to link
+    create_entry(&note)?;
+    hash_entry(&note)
+}
+
+/// Get all notes the agent executing this fn wrote during the session,
+/// in the order they were written
+pub fn get_journal_notes(session_hash: EntryHash) -> ExternResult<Vec<(EntryHash, JournalNote)>> {
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(JournalNote)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Private,
+        )));
+    let mut notes: Vec<(EntryHash, JournalNote)> = vec![];
+    for el in query(filter)? {
+        let note_hash = el.header().entry_hash().cloned().ok_or(WasmError::Guest(
+            "JournalNote element doesn't have an entry hash".into(),
+        ))?;
+        let note: JournalNote = try_from_element(el)?;
+        if note.session == session_hash {
+            notes.push((note_hash, note));
+        }
+    }
+    Ok(notes)
+}
+
+/// Publishes every note the agent executing this fn wrote during the session
+/// and hasn't published yet, so that the other players and researchers can read them.
+/// Notes can only be published once the game has ended
+pub fn publish_journal(session_hash: EntryHash) -> ExternResult<Vec<EntryHash>> {
+    let (ended_session_header, _) = get_ended_session(session_hash.clone())?.ok_or(
+        WasmError::Guest("Can't publish the journal until the game has ended".into()),
+    )?;
+    let owner = agent_info()?.agent_latest_pubkey;
+    // the same note always makes the same PublishedNote entry, so we can tell
+    // which notes were published already by their hashes
+    let already_published: Vec<EntryHash> = get_published_notes(session_hash.clone())?
+        .into_iter()
+        .map(|(hash, _)| hash)
+        .collect();
+    let mut published: Vec<EntryHash> = vec![];
+    for (_, note) in get_journal_notes(session_hash.clone())? {
+        let published_note = PublishedNote {
+            owner: owner.clone(),
+            session: session_hash.clone(),
+            ended_session: ended_session_header.clone(),
+            round_num: note.round_num,
+            content: note.content,
+            timestamp: note.timestamp,
+        };
+        let published_note_entry_hash = hash_entry(&published_note)?;
+        if already_published.contains(&published_note_entry_hash) {
+            continue;
+        }
+        create_entry(&published_note)?;
+        // Link from the game session so that anyone can find the published notes
+        create_link(
+            session_hash.clone(),
+            published_note_entry_hash.clone(),
+            LinkTag::new(String::from(PUBLISHED_NOTE_LINK_TAG)),
+        )?;
+        published.push(published_note_entry_hash);
+    }
+    Ok(published)
+}
+
+/// Get all notes the players published after the session ended,
+/// ordered by the time they were written
+pub fn get_published_notes(
+    session_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, PublishedNote)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(PUBLISHED_NOTE_LINK_TAG))),
+    )?;
+    let mut notes: Vec<(EntryHash, PublishedNote)> = vec![];
+    for link in links.into_inner() {
+        let note: PublishedNote = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        notes.push((link.target, note));
+    }
+    notes.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then(a.0.cmp(&b.0)));
+    Ok(notes)
+}
+
+/// Validates creation of PublishedNote entries
+pub fn validate_create_entry_published_note(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let note: PublishedNote = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &note.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "PublishedNote can only be created by it's owner",
+        )));
+    }
+
+    if !note_length_is_valid(&note.content) {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "PublishedNote has to have between 1 and {} characters, but it has {}",
+            MAX_JOURNAL_NOTE_LENGTH,
+            note.content.chars().count()
+        )));
+    }
+
+    if !verify_ended_session(&note.session, &note.ended_session)? {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Notes can only be published after their GameSession has ended",
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(note.session)?;
+    if !game_session.players.contains(&note.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can publish notes",
+        )));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of PublishedNote entries
+pub fn validate_update_entry_published_note(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update PublishedNote entry",
+    )))
+}
+
+/// Validates delete of PublishedNote entries
+pub fn validate_delete_entry_published_note(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete PublishedNote entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_cant_be_empty_or_too_long() {
+        assert!(!note_length_is_valid(""));
+        assert!(note_length_is_valid("a"));
+        assert!(note_length_is_valid(&"a".repeat(MAX_JOURNAL_NOTE_LENGTH)));
+        assert!(!note_length_is_valid(
+            &"a".repeat(MAX_JOURNAL_NOTE_LENGTH + 1)
+        ));
+    }
+
+    #[test]
+    fn note_length_is_counted_in_characters() {
+        // every character takes several bytes, but the note is still within the limit
+        let note = "ž".repeat(MAX_JOURNAL_NOTE_LENGTH);
+        assert!(note.len() > MAX_JOURNAL_NOTE_LENGTH);
+        assert!(note_length_is_valid(&note));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    game_move::{get_moves_for_round, latest_moves_per_player, must_get_counted_moves, GameMove},
+    game_round::{closed_round_info, GameRound, GameRoundInfo},
+    game_seed::{random_from_seed, SharedSeed, MONITORING_NOISE_DRAW},
+    game_session::{GameSession, ResourceAmount},
+    game_signals::{GameSignal, RoundObservationPayload},
+    game_subsistence::active_players,
+    utils::{try_from_element, try_get_and_convert, try_get_element},
+};
+use hdk::prelude::*;
+
+/// What players can learn about the resource stock
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum StockMonitoring {
+    // players see exactly how many resources are left
+    Exact,
+    // players see the stock with a random error of up to max_noise of it,
+    // e.g. 0.2 means that the stock they see can be 20% less or more than it is
+    Noisy { max_noise: f32 },
+}
+
+/// Whose takes players can learn about
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum TakesMonitoring {
+    // players only see what they took themselves
+    Own,
+    // players see what everyone took
+    Everyone,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct MonitoringParams {
+    pub stock: StockMonitoring,
+    pub takes: TakesMonitoring,
+}
+
+impl MonitoringParams {
+    /// Checks if the noise has to be drawn from the shared seed
+    pub fn is_noisy(&self) -> bool {
+        matches!(self.stock, StockMonitoring::Noisy { .. })
+    }
+}
+
+/// Makes sure that the monitoring params make sense for the game
+pub fn check_monitoring(params: &MonitoringParams) -> ExternResult<()> {
+    if let StockMonitoring::Noisy { max_noise } = params.stock {
+        if !(0.0..=1.0).contains(&max_noise) {
+            return Err(WasmError::Guest(format!(
+                "Max noise of the stock has to be between 0.0 and 1.0, but it is {}",
+                max_noise
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Calculates the factor that the stock players see in the given round is multiplied by.
+/// NOTE: the noise is drawn from the shared seed, so every player sees the same noisy
+/// stock. Round entries always keep the exact stock, it's only the output for the UI
+/// that gets the noise
+pub fn stock_noise_factor(max_noise: f32, shared_seed: &SharedSeed, round_num: u32) -> f32 {
+    let draw = random_from_seed(shared_seed, round_num, MONITORING_NOISE_DRAW);
+    1.0 + (draw * 2.0 - 1.0) * max_noise
+}
+
+/// Checks if the player is allowed to observe what the owner of a move took
+pub fn can_observe_take(
+    params: &Option<MonitoringParams>,
+    player: &AgentPubKey,
+    owner: &AgentPubKey,
+) -> bool {
+    match params {
+        Some(params) if params.takes == TakesMonitoring::Own => player == owner,
+        _ => true,
+    }
+}
+
+/// Filters the round info down to what the player is allowed to observe.
+/// Params are the ones that were in effect during the round that was closed
+pub fn observe_round_info(
+    info: GameRoundInfo,
+    params: &Option<MonitoringParams>,
+    shared_seed: &Option<SharedSeed>,
+    player: &AgentPubKey,
+) -> GameRoundInfo {
+    let params = match params {
+        Some(params) => params,
+        None => return info,
+    };
+    let mut info = info;
+    if let StockMonitoring::Noisy { max_noise } = params.stock {
+        match shared_seed {
+            Some(shared_seed) => {
+                let factor = stock_noise_factor(max_noise, shared_seed, info.round_num);
+                let noisy = |amount: ResourceAmount| (amount as f32 * factor) as ResourceAmount;
+                info.resources_left = info.resources_left.map(noisy);
+                info.patch_stocks = info.patch_stocks.into_iter().map(noisy).collect();
+            }
+            // we can't draw the noise without the seed, so we show nothing
+            None => {
+                info.resources_left = None;
+                info.patch_stocks = vec![];
+            }
+        }
+        // exact growth would let players calculate the exact stock
+        info.resources_grown_round = None;
+    }
+    if params.takes == TakesMonitoring::Own {
+        info.moves.retain(|(_, _, owner)| owner == player);
+        info.pact_violations.retain(|v| &v.player == player);
+        // totals of the round and of the teams would tell the player how much others took
+        info.resources_taken_round = None;
+        info.resources_taxed_round = None;
+        info.resources_redistributed_round = None;
+        info.resources_reinvested_round = None;
+        info.team_leaderboard = vec![];
+    }
+    info
+}
+
+/// Get the latest moves made so far in the round at round_hash whose takes the agent
+/// executing this fn is allowed to observe
+pub fn get_observable_moves(round_hash: EntryHash) -> ExternResult<Vec<GameMove>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let moves = latest_moves_per_player(get_moves_for_round(round_hash)?)?;
+    Ok(moves
+        .into_iter()
+        .map(|(_, m)| m)
+        .filter(|m| can_observe_take(&game_round.state.game_params.monitoring, &me, &m.owner))
+        .collect())
+}
+
+/// Sends every player of the session what they can observe of the round that was just closed.
+/// Each player gets their own signal, since they might be allowed to see different things
+pub fn signal_round_observations(
+    game_session: &GameSession,
+    game_session_hash: EntryHash,
+    info: &GameRoundInfo,
+    params: &Option<MonitoringParams>,
+    shared_seed: &Option<SharedSeed>,
+) -> ExternResult<()> {
+    for player in &game_session.players {
+        let signal_payload = RoundObservationPayload {
+            game_session_entry_hash: game_session_hash.clone(),
+            round_info: observe_round_info(info.clone(), params, shared_seed, player),
+        };
+        let signal = ExternIO::encode(GameSignal::RoundObserved(signal_payload))?;
+        remote_signal(signal, vec![player.clone()])?;
+    }
+    Ok(())
+}
+
+/// Get every closed round of the game up to the round at round_hash, from the earliest
+/// to the latest, as the agent executing this fn can observe them
+pub fn get_round_history(round_hash: EntryHash) -> ExternResult<Vec<GameRoundInfo>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let mut history: Vec<GameRoundInfo> = vec![];
+    let mut round_hash = round_hash;
+    let mut round_element = try_get_element(round_hash.clone(), GetOptions::latest())?;
+    let mut round: GameRound = try_from_element(round_element.clone())?;
+    let game_session: GameSession =
+        try_get_and_convert(round.session.clone(), GetOptions::latest())?;
+    // every round except the round zero is an update of the previous one
+    while let Header::Update(update) = round_element.header().clone() {
+        let prev_hash = update.original_entry_address;
+        let prev_element = try_get_element(prev_hash.clone(), GetOptions::latest())?;
+        let prev_round: GameRound = try_from_element(prev_element.clone())?;
+        // moves of the previous round are the ones that were counted when closing it
+        let players = active_players(&game_session.players, &prev_round.state.eliminated_players);
+        let moves = match must_get_counted_moves(&round.state.moves, &prev_hash, &players)? {
+            Some(moves) => moves,
+            None => {
+                return Err(WasmError::Guest(format!(
+                    "Can't load the moves counted in round {}",
+                    prev_round.round_num
+                )))
+            }
+        };
+        let info = closed_round_info(
+            &round.state,
+            round.round_num,
+            Some(round_hash),
+            prev_hash.clone(),
+            None,
+            "ROUND_HISTORY",
+            &moves,
+        );
+        history.push(observe_round_info(
+            info,
+            &prev_round.state.game_params.monitoring,
+            &round.state.shared_seed,
+            &me,
+        ));
+        round_hash = prev_hash;
+        round_element = prev_element;
+        round = prev_round;
+    }
+    history.reverse();
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, shared_seed, violation};
+
+    fn monitoring(stock: StockMonitoring, takes: TakesMonitoring) -> Option<MonitoringParams> {
+        Some(MonitoringParams { stock, takes })
+    }
+
+    fn round_info() -> GameRoundInfo {
+        GameRoundInfo {
+            round_num: 2,
+            resources_left: Some(100),
+            resources_taken_round: Some(12),
+            resources_grown_round: Some(6),
+            resources_taxed_round: Some(1),
+            resources_redistributed_round: Some(1),
+            resources_reinvested_round: Some(0),
+            current_round_entry_hash: None,
+            prev_round_entry_hash: None,
+            game_session_hash: None,
+            next_action: String::from("SHOW_ROUND_RESULTS"),
+            moves: vec![
+                (5, String::from("a"), agent(1)),
+                (7, String::from("b"), agent(2)),
+            ],
+            team_leaderboard: vec![(0, 12)],
+            patch_stocks: vec![40, 60],
+            pact_violations: vec![violation(0, 1, 5, 0), violation(0, 2, 5, 0)],
+        }
+    }
+
+    #[test]
+    fn max_noise_has_to_be_a_share_of_the_stock() {
+        let params = |max_noise| MonitoringParams {
+            stock: StockMonitoring::Noisy { max_noise },
+            takes: TakesMonitoring::Everyone,
+        };
+        assert!(check_monitoring(&params(0.0)).is_ok());
+        assert!(check_monitoring(&params(1.0)).is_ok());
+        assert!(check_monitoring(&params(-0.5)).is_err());
+        assert!(check_monitoring(&params(1.5)).is_err());
+    }
+
+    #[test]
+    fn noise_stays_within_max_noise_and_is_the_same_for_everyone() {
+        for round_num in 0..20 {
+            let factor = stock_noise_factor(0.25, &shared_seed(7), round_num);
+            assert!((0.75..=1.25).contains(&factor));
+            assert_eq!(factor, stock_noise_factor(0.25, &shared_seed(7), round_num));
+        }
+        assert_eq!(stock_noise_factor(0.0, &shared_seed(7), 3), 1.0);
+    }
+
+    #[test]
+    fn players_only_observe_their_own_takes_when_monitoring_is_own() {
+        let own = monitoring(StockMonitoring::Exact, TakesMonitoring::Own);
+        let everyone = monitoring(StockMonitoring::Exact, TakesMonitoring::Everyone);
+        assert!(can_observe_take(&own, &agent(1), &agent(1)));
+        assert!(!can_observe_take(&own, &agent(1), &agent(2)));
+        assert!(can_observe_take(&everyone, &agent(1), &agent(2)));
+        assert!(can_observe_take(&None, &agent(1), &agent(2)));
+    }
+
+    #[test]
+    fn own_monitoring_hides_the_takes_and_totals_of_others() {
+        let params = monitoring(StockMonitoring::Exact, TakesMonitoring::Own);
+        let info = observe_round_info(round_info(), &params, &None, &agent(1));
+        assert_eq!(info.moves, vec![(5, String::from("a"), agent(1))]);
+        assert_eq!(info.pact_violations, vec![violation(0, 1, 5, 0)]);
+        assert_eq!(info.resources_taken_round, None);
+        assert_eq!(info.resources_taxed_round, None);
+        assert_eq!(info.resources_redistributed_round, None);
+        assert_eq!(info.resources_reinvested_round, None);
+        assert!(info.team_leaderboard.is_empty());
+        assert_eq!(info.resources_left, Some(100));
+        assert_eq!(info.resources_grown_round, Some(6));
+    }
+
+    #[test]
+    fn noisy_monitoring_hides_the_exact_stock() {
+        let params = monitoring(
+            StockMonitoring::Noisy { max_noise: 0.5 },
+            TakesMonitoring::Everyone,
+        );
+        let seed = shared_seed(3);
+        let factor = stock_noise_factor(0.5, &seed, 2);
+        let info = observe_round_info(round_info(), &params, &Some(seed), &agent(1));
+        assert_eq!(
+            info.resources_left,
+            Some((100.0 * factor) as ResourceAmount)
+        );
+        assert_eq!(
+            info.patch_stocks,
+            vec![
+                (40.0 * factor) as ResourceAmount,
+                (60.0 * factor) as ResourceAmount
+            ]
+        );
+        assert_eq!(info.resources_grown_round, None);
+        assert_eq!(info.moves.len(), 2);
+
+        let info = observe_round_info(round_info(), &params, &None, &agent(1));
+        assert_eq!(info.resources_left, None);
+        assert!(info.patch_stocks.is_empty());
+    }
+
+    #[test]
+    fn round_info_is_unchanged_without_monitoring() {
+        let info = observe_round_info(round_info(), &None, &None, &agent(1));
+        assert_eq!(info.resources_left, Some(100));
+        assert_eq!(info.moves.len(), 2);
+        assert_eq!(info.team_leaderboard, vec![(0, 12)]);
+    }
+}
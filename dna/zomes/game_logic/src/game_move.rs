@@ -1,43 +1,102 @@
 use crate::{
-    game_round::GameRound,
+    game_attributes::attributes_of,
+    game_facilitator::is_session_halted,
+    game_investment::validate_investment,
+    game_pact::validate_pacts,
+    game_patches::validate_patch,
+    game_quota::validate_quota,
+    game_round::{check_round_is_open, GameRound},
     game_session::{GameSession, ResourceAmount},
-    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+    game_team::validate_team_cap,
+    game_turns::{
+        moves_in_turn_order, prev_move_in_turn, signal_next_turn, validate_single_move_in_turn,
+        validate_turn,
+    },
+    utils::{must_get_entry_struct, try_from_element, try_from_entry, try_get_and_convert},
 };
 use hdk::prelude::*;
 use std::collections::BTreeMap;
 
 pub const GAME_MOVE_LINK_TAG: &str = "GAME_MOVE";
 
-#[hdk_entry(id = "game_move", visibility = "public")]
-#[derive(Clone)]
+// validators get the author's earlier moves with every move, so they can check
+// that players who move one after another only move once in a round
+#[hdk_entry(
+    id = "game_move",
+    visibility = "public",
+    required_validation_type = "sub_chain"
+)]
+#[derive(Clone, PartialEq)]
 pub struct GameMove {
     pub owner: AgentPubKey,
     pub round_hash: EntryHash,
     pub resource_amount: ResourceAmount,
+    // index of the patch the resources are taken from. None if the commons
+    // isn't split into patches
+    pub patch: Option<u32>,
+    // part of the player's resources put back into the stock before it regrows
+    pub reinvest_amount: ResourceAmount,
+    // part of the player's resources saved in the bank, where they earn interest
+    pub save_amount: ResourceAmount,
+    // time when the move was made, taken from the author's clock.
+    // Validation makes sure it's never later than the timestamp of the header
+    // that commits the move, and that every revision is later than the original move
+    pub timestamp: Timestamp,
+    // hash of the move of the player who moves just before the owner in the
+    // turn order. None if the owner moves first or if players move simultaneously
+    pub prev_move: Option<EntryHash>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GameMoveInput {
     pub resource_amount: ResourceAmount,
     pub round_hash: EntryHash,
+    pub patch: Option<u32>,
+    // None means the player doesn't reinvest anything
+    pub reinvest_amount: Option<ResourceAmount>,
+    // None means the player doesn't save anything
+    pub save_amount: Option<ResourceAmount>,
+}
+
+impl GameMove {
+    /// Creates a move the owner makes right now with the data from the UI,
+    /// after the move at prev_move
+    pub fn from_input(
+        owner: AgentPubKey,
+        input: GameMoveInput,
+        prev_move: Option<EntryHash>,
+    ) -> ExternResult<GameMove> {
+        Ok(GameMove {
+            owner,
+            round_hash: input.round_hash,
+            resource_amount: input.resource_amount,
+            patch: input.patch,
+            reinvest_amount: input.reinvest_amount.unwrap_or(0),
+            save_amount: input.save_amount.unwrap_or(0),
+            timestamp: sys_time()?,
+            prev_move,
+        })
+    }
 }
 
 /// Create a new move entry, and link it from its round
-pub fn new_move(
-    resource_amount: ResourceAmount,
-    round_hash: EntryHash,
-) -> ExternResult<HeaderHash> {
+pub fn new_move(input: GameMoveInput) -> ExternResult<HeaderHash> {
     // We don't have to pass as parameter the author of the move, because
     // the agent that's executing this code will always be the author of the move
     // So just their public key from the local conductor
     let agent_info = agent_info()?;
 
+    // Nobody can move once the round is closed, or while the facilitator
+    // has the session paused or ended
+    check_round_accepts_moves(&input.round_hash)?;
+
+    // If players move one after another, the move comes after the move of the
+    // player before the agent, and the agent can only move once
+    let prev_move = prev_move_in_turn(&input.round_hash, &agent_info.agent_latest_pubkey)?;
+
     // Construct the contents of the entry
-    let game_move = GameMove {
-        owner: agent_info.agent_latest_pubkey,
-        resource_amount,
-        round_hash: round_hash.clone(),
-    };
+    let round_hash = input.round_hash.clone();
+    let game_move = GameMove::from_input(agent_info.agent_latest_pubkey, input, prev_move)?;
 
     // Create the entry
     create_entry(game_move.clone())?;
@@ -47,15 +106,80 @@ pub fn new_move(
 
     // Link from the round entry to the newly created move so that other agents can discover it
     let create_link_header_hash = create_link(
-        round_hash,
+        round_hash.clone(),
         move_entry_hash,
         LinkTag::new(String::from(GAME_MOVE_LINK_TAG)),
     )?;
 
+    // If players move one after another, it's the next player's turn now
+    signal_next_turn(round_hash)?;
+
     Ok(create_link_header_hash)
 }
 
-/// Get all moves attached to the round that we have so far
+/// Replaces the move that the agent executing this fn made for the given round
+/// with a new one. The new revision is committed as an update of the
+/// original move, so the link from the round keeps pointing to the original entry
+/// and get_moves_for_round would find the revision through it's details.
+pub fn update_move(input: GameMoveInput) -> ExternResult<HeaderHash> {
+    let agent_info = agent_info()?;
+
+    // Nobody can change their move once the round is closed, or while the
+    // facilitator has the session paused or ended
+    check_round_accepts_moves(&input.round_hash)?;
+
+    let original_move_header_hash = get_my_original_move(&input.round_hash)?.ok_or(
+        WasmError::Guest("Can't update the move: there's no move made for this round yet".into()),
+    )?;
+
+    // moves can't be updated when players move one after another,
+    // so there's no previous move to refer to
+    let game_move = GameMove::from_input(agent_info.agent_latest_pubkey, input, None)?;
+    // NOTE: we're always updating the original move and not the latest revision,
+    // so all revisions can be found from the original entry
+    update_entry(original_move_header_hash, &game_move)
+}
+
+/// Get the header hash of the first move the agent executing this fn made for
+/// the given round. Moves are written by the player themselves, so it's
+/// already on their source chain if they've moved
+pub fn get_my_original_move(round_hash: &EntryHash) -> ExternResult<Option<HeaderHash>> {
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .header_type(HeaderType::Create)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(GameMove)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Public,
+        )));
+    for el in query(filter)? {
+        let game_move: GameMove = try_from_element(el.clone())?;
+        if &game_move.round_hash == round_hash {
+            // query returns elements in the source chain order, so the first one
+            // we find is the original move
+            return Ok(Some(el.header_address().clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Makes sure that the round is still open and that the facilitator hasn't paused
+/// or ended it's session.
+/// NOTE: these checks depend on what the agent can see on DHT, so they are
+/// done here instead of the validation of GameMove
+fn check_round_accepts_moves(round_hash: &EntryHash) -> ExternResult<()> {
+    check_round_is_open(round_hash.clone(), "make a move")?;
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    if is_session_halted(game_round.session)? {
+        return Err(WasmError::Guest(String::from(
+            "Can't make a move: the facilitator has paused or ended the GameSession",
+        )));
+    }
+    Ok(())
+}
+
+/// Get all moves attached to the round that we have so far, including every
+/// revision made to them
 pub fn get_moves_for_round(last_round_hash: EntryHash) -> ExternResult<Vec<GameMove>> {
     let links = get_links(
         last_round_hash,
@@ -63,20 +187,49 @@ pub fn get_moves_for_round(last_round_hash: EntryHash) -> ExternResult<Vec<GameM
     )?;
     let mut moves: Vec<GameMove> = vec![];
     for link in links.into_inner() {
-        let game_move: GameMove = try_get_and_convert(link.target, GetOptions::latest())?;
-        moves.push(game_move);
+        // get_details gives us both the original move and the headers that updated it
+        let entry_details = match get_details(link.target.clone(), GetOptions::latest())? {
+            Some(Details::Entry(entry_details)) => entry_details,
+            _ => {
+                return Err(WasmError::Guest(format!(
+                    "There is no GameMove entry at the hash {}",
+                    link.target
+                )))
+            }
+        };
+        moves.push(try_from_entry(entry_details.entry)?);
+        for update_header in entry_details.updates {
+            // every update header points to the entry with the revised move
+            let revision_hash = update_header.header().entry_hash().ok_or(WasmError::Guest(
+                "GameMove update header doesn't have an entry".into(),
+            ))?;
+            let revised_move: GameMove =
+                try_get_and_convert(revision_hash.clone(), GetOptions::latest())?;
+            moves.push(revised_move);
+        }
     }
     Ok(moves)
 }
 
 /// Consumes list of moves passed to it to finalize them.
 /// If every player made at least one move, it returns list of moves which is guaranteed
-/// to have a single move for every player.
+/// to have a single move for every player, ordered by player's pub key.
+/// If players move one after another, turn_order has the order of players in this
+/// round, and every player's move has to refer to the move of the player before them.
 /// If there are missing moves, it returns None, since we can't finalize the moves and
 /// have to wait for other players instead.
+///
+/// When a player has several moves (e.g. they've updated their move), we select one
+/// using the following rule:
+/// 1) the move with the latest timestamp wins
+/// 2) if timestamps are equal, the move with the smallest resource_amount wins
+/// 3) if resource amounts are equal too, the move with the smallest entry hash wins
+/// The rule only depends on the contents of the moves, so every agent who closes
+/// the round would select the same moves no matter in which order they got them.
 pub fn finalize_moves(
     moves: Vec<GameMove>,
     number_of_players: usize,
+    turn_order: Option<&[AgentPubKey]>,
 ) -> ExternResult<Option<Vec<GameMove>>> {
     // Check that at least we have as many moves
     // as there are players in the game
@@ -88,65 +241,309 @@ pub fn finalize_moves(
         );
         return Ok(None);
     } else {
+        if let Some(turn_order) = turn_order {
+            // moves can't be updated when players move one after another, so
+            // we follow the chain of moves every player made after the previous one
+            let mut hashed_moves: Vec<(EntryHash, GameMove)> = vec![];
+            for m in moves {
+                hashed_moves.push((hash_entry(&m)?, m));
+            }
+            let mut ordered_moves: Vec<GameMove> = moves_in_turn_order(hashed_moves, turn_order)
+                .into_iter()
+                .map(|(_, m)| m)
+                .collect();
+            if ordered_moves.len() < number_of_players {
+                info!("Cannot close the round: only {} players made their moves in turn, waiting for total {} players", ordered_moves.len(), number_of_players);
+                return Ok(None);
+            }
+            ordered_moves.sort_by(|a, b| a.owner.cmp(&b.owner));
+            return Ok(Some(ordered_moves));
+        }
         // Now that we know we have moves >= num of players, we need
         // to make sure that every player made at least one move, so
         // we're not closing the round without someone's move
-        let mut moves_per_player: BTreeMap<AgentPubKey, Vec<GameMove>> = BTreeMap::new();
-        for m in moves {
-            match moves_per_player.get_mut(&m.owner) {
-                Some(moves) => moves.push(m),
-                // TODO(e-nastasia): cloning owner value seems like a waste, but I think
-                // that alternative would be to use lifetimes. Not sure it's worth the
-                // readability penalty that we'll incur.
-                None => {
-                    moves_per_player.insert(m.owner.clone(), vec![m]);
+        let latest_per_player = latest_moves_per_player(moves)?;
+        if latest_per_player.keys().len() < number_of_players {
+            info!("Cannot close the round: only {} players made their moves, waiting for total {} players", latest_per_player.keys().len(), number_of_players);
+            return Ok(None);
+        }
+        Ok(Some(
+            latest_per_player.into_iter().map(|(_, m)| m).collect(),
+        ))
+    }
+}
+
+/// Selects one move for every player who made at least one of the given moves,
+/// using the rule described in finalize_moves
+pub fn latest_moves_per_player(
+    moves: Vec<GameMove>,
+) -> ExternResult<BTreeMap<AgentPubKey, GameMove>> {
+    let mut latest_per_player: BTreeMap<AgentPubKey, GameMove> = BTreeMap::new();
+    for m in moves {
+        // keep the move only if it wins over the one we already have
+        let is_latest = match latest_per_player.get(&m.owner) {
+            Some(latest) => {
+                if m.timestamp != latest.timestamp {
+                    m.timestamp > latest.timestamp
+                } else if m.resource_amount != latest.resource_amount {
+                    m.resource_amount < latest.resource_amount
+                } else if m != *latest {
+                    // revisions can still differ in other fields, so we break
+                    // the tie by their entry hashes
+                    hash_entry(&m)? < hash_entry(latest)?
+                } else {
+                    false
                 }
             }
+            None => true,
+        };
+        if is_latest {
+            // TODO(e-nastasia): cloning owner value seems like a waste, but I think
+            // that alternative would be to use lifetimes. Not sure it's worth the
+            // readability penalty that we'll incur.
+            latest_per_player.insert(m.owner.clone(), m);
         }
-        if moves_per_player.keys().len() < number_of_players {
-            info!("Cannot close the round: only {} players made their moves, waiting for total {} players", moves_per_player.keys().len(), number_of_players);
+    }
+    Ok(latest_per_player)
+}
+
+/// Loads the moves counted in the round by their hashes, and checks that there's
+/// exactly one move of every active player, made for the round at round_hash.
+/// Returns None if there isn't.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation.
+/// Hashes have to be sorted, so the moves are in the same order as they were when
+/// the round state was calculated
+pub fn must_get_counted_moves(
+    move_hashes: &[EntryHash],
+    round_hash: &EntryHash,
+    players: &[AgentPubKey],
+) -> ExternResult<Option<Vec<GameMove>>> {
+    if move_hashes.len() != players.len() || move_hashes.windows(2).any(|h| h[0] >= h[1]) {
+        return Ok(None);
+    }
+    let mut moves: Vec<GameMove> = vec![];
+    for move_hash in move_hashes {
+        let game_move = must_get_entry_struct::<GameMove>(move_hash.clone())?;
+        if &game_move.round_hash != round_hash
+            || !players.contains(&game_move.owner)
+            || moves.iter().any(|m| m.owner == game_move.owner)
+        {
             return Ok(None);
         }
-        let mut new_moves = vec![];
-        for (_, move_vec) in moves_per_player {
-            // NOTE(e-nastasia): if we add a timestamp to the game move, we'll be able to
-            // filter moves here, but for now we'll do with just taking some move
-            new_moves.push(move_vec[0].clone());
-        }
-        Ok(Some(new_moves))
+        moves.push(game_move);
     }
+    Ok(Some(moves))
 }
 
 /// Validates creation of GameMove entries
 pub fn validate_create_entry_game_move(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
-    let game_move: GameMove = try_from_element(data.element)?;
+    let game_move: GameMove = try_from_element(data.element.clone())?;
 
-    // validate that resources consumed during the move are always positive
-    if game_move.resource_amount <= 0 {
-        return Ok(ValidateCallbackResult::Invalid(format!(
-            "GameMove has to have resources >= 0, but it has {}",
-            game_move.resource_amount
+    // validate that the move isn't post-dated to win over player's other moves
+    if game_move.timestamp > data.element.header().timestamp() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "GameMove can't have a timestamp later than the header that creates it",
         )));
     }
 
     // now we need to retrieve game session via the round header hash saved
     // in the game move entry to verify that player is making a move for the
     // game session they're actually playing
-    let game_round = must_get_entry_struct::<GameRound>(game_move.round_hash)?;
-    let game_session = must_get_entry_struct::<GameSession>(game_round.session)?;
+    let game_round = must_get_entry_struct::<GameRound>(game_move.round_hash.clone())?;
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session.clone())?;
+
+    // validate that the move takes a valid amount of resources
+    if let Some(result) = validate_resource_amount(&game_move, &game_round) {
+        return Ok(result);
+    }
 
     if !game_session.players.contains(&game_move.owner) {
         return Ok(ValidateCallbackResult::Invalid(String::from("Can't make a GameMove for this GameSession because move owner isn't in the list of GameSession players")));
     }
 
+    if game_round
+        .state
+        .eliminated_players
+        .contains(&game_move.owner)
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Can't make a GameMove: move owner was eliminated from the game",
+        )));
+    }
+
+    if let Some(result) = validate_harvest_cap(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_patch(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_investment(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_quota(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_pacts(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_capacity(&game_move, &game_session) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_team_cap(&game_move, &game_round, &game_session) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_turn(&game_move, &game_round, &game_session)? {
+        return Ok(result);
+    }
+    if let Some(result) = validate_single_move_in_turn(&game_move, &game_round, &data)? {
+        return Ok(result);
+    }
+
+    // NOTE: new_move checks that the round is still open and that the
+    // facilitator hasn't paused or ended the GameSession.
+    // If there's an auction, takes are cut to the rights the player won when
+    // the round is closed, since the rights are only known then
+
     Ok(ValidateCallbackResult::Valid)
 }
 
-/// Validates update of GameMove entries
-pub fn validate_update_entry_game_move(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
-    Ok(ValidateCallbackResult::Invalid(String::from(
-        "Can't update GameMove entry",
-    )))
+/// Checks that the move takes some resources. The only exception are the moves
+/// of players who didn't win any rights in the auction: they can only take nothing.
+/// Returns None if the amount is valid
+fn validate_resource_amount(
+    game_move: &GameMove,
+    game_round: &GameRound,
+) -> Option<ValidateCallbackResult> {
+    let min_amount = match game_round.state.game_params.auction {
+        Some(_) => 0,
+        None => 1,
+    };
+    if game_move.resource_amount < min_amount {
+        return Some(ValidateCallbackResult::Invalid(format!(
+            "GameMove has to have resources >= {}, but it has {}",
+            min_amount, game_move.resource_amount
+        )));
+    }
+    None
+}
+
+/// Checks the move against the harvest cap that is in effect for it's round.
+/// Returns None if the move is within the cap
+fn validate_harvest_cap(
+    game_move: &GameMove,
+    game_round: &GameRound,
+) -> Option<ValidateCallbackResult> {
+    match game_round.state.game_params.harvest_cap {
+        Some(harvest_cap) if game_move.resource_amount > harvest_cap => {
+            Some(ValidateCallbackResult::Invalid(format!(
+                "GameMove can't take more than {} resources, but it takes {}",
+                harvest_cap, game_move.resource_amount
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Checks the move against the capacity of the player who made it.
+/// Returns None if the move is within the capacity
+fn validate_capacity(
+    game_move: &GameMove,
+    game_session: &GameSession,
+) -> Option<ValidateCallbackResult> {
+    match attributes_of(&game_session.player_attributes, &game_move.owner).capacity {
+        Some(capacity) if game_move.resource_amount > capacity => {
+            Some(ValidateCallbackResult::Invalid(format!(
+                "GameMove can't take more than player's capacity of {} resources, but it takes {}",
+                capacity, game_move.resource_amount
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Validates update of GameMove entries: players can change their own move
+/// for as long as the round it belongs to is still open. Revisions made after
+/// that aren't counted, since the round state refers to the counted moves by hash
+pub fn validate_update_entry_game_move(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let game_move: GameMove = try_from_element(data.element.clone())?;
+
+    let update_header = match data.element.header() {
+        Header::Update(update_data) => update_data.clone(),
+        _ => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "GameMove's element has the wrong header: expected Update",
+            )));
+        }
+    };
+
+    let original_move =
+        must_get_entry_struct::<GameMove>(update_header.original_entry_address.clone())?;
+
+    // only the player who made the move can change it
+    if update_header.author != original_move.owner || game_move.owner != original_move.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Can't update GameMove entry: only the owner of the move can update it",
+        )));
+    }
+
+    // the revised move still has to be a move for the same round
+    if game_move.round_hash != original_move.round_hash {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Can't update GameMove entry to belong to a different round",
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(game_move.round_hash.clone())?;
+    if let Some(result) = validate_resource_amount(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_harvest_cap(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_patch(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_investment(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_quota(&game_move, &game_round) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_pacts(&game_move, &game_round) {
+        return Ok(result);
+    }
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session.clone())?;
+    if let Some(result) = validate_capacity(&game_move, &game_session) {
+        return Ok(result);
+    }
+    if let Some(result) = validate_team_cap(&game_move, &game_round, &game_session) {
+        return Ok(result);
+    }
+    // when players move one after another, the next players have already
+    // seen the move, so it can't be changed
+    if game_round.state.game_params.turn_order.is_some() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Can't update GameMove entry when players move one after another",
+        )));
+    }
+    if let Some(result) = validate_turn(&game_move, &game_round, &game_session)? {
+        return Ok(result);
+    }
+
+    // revisions have to be ordered after the original move, and can't be
+    // post-dated to win over player's other revisions
+    if game_move.timestamp <= original_move.timestamp
+        || game_move.timestamp > update_header.timestamp
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "GameMove revision has to have a timestamp between the original move and the header that updates it",
+        )));
+    }
+
+    // NOTE: update_move checks that the round is still open and that the
+    // facilitator hasn't paused or ended the GameSession
+
+    Ok(ValidateCallbackResult::Valid)
 }
 
 /// Validates delete of GameMove entries
@@ -155,3 +552,74 @@ pub fn validate_delete_entry_game_move(_: ValidateData) -> ExternResult<Validate
         "Can't delete GameMove entry",
     )))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move, timestamp};
+
+    fn move_at(owner: u8, resource_amount: ResourceAmount, secs: i64) -> GameMove {
+        GameMove {
+            timestamp: timestamp(secs),
+            ..game_move(owner, resource_amount)
+        }
+    }
+
+    #[test]
+    fn latest_revision_of_the_move_is_counted() {
+        let moves = vec![move_at(1, 5, 10), move_at(1, 7, 20), move_at(2, 3, 10)];
+        let latest = latest_moves_per_player(moves).unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[&agent(1)].resource_amount, 7);
+        assert_eq!(latest[&agent(2)].resource_amount, 3);
+    }
+
+    #[test]
+    fn revisions_dont_count_as_moves_of_other_players() {
+        // two players, but only one of them moved (twice)
+        let moves = vec![move_at(1, 5, 10), move_at(1, 7, 20)];
+        assert!(finalize_moves(moves, 2, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn round_closes_with_one_move_per_player() {
+        let moves = vec![move_at(2, 3, 10), move_at(1, 5, 10), move_at(1, 7, 20)];
+        let finalized = finalize_moves(moves, 2, None).unwrap().unwrap();
+        let takes: Vec<(AgentPubKey, ResourceAmount)> = finalized
+            .into_iter()
+            .map(|m| (m.owner, m.resource_amount))
+            .collect();
+        assert_eq!(takes, vec![(agent(1), 7), (agent(2), 3)]);
+    }
+
+    #[test]
+    fn smaller_take_wins_when_revisions_have_the_same_timestamp() {
+        let moves = vec![move_at(1, 7, 10), move_at(1, 5, 10)];
+        assert_eq!(
+            latest_moves_per_player(moves).unwrap()[&agent(1)].resource_amount,
+            5
+        );
+    }
+
+    #[test]
+    fn selected_moves_dont_depend_on_the_order_we_got_them_in() {
+        let moves = vec![
+            move_at(1, 5, 10),
+            move_at(1, 9, 30),
+            move_at(1, 8, 30),
+            move_at(2, 4, 20),
+        ];
+        let mut reversed = moves.clone();
+        reversed.reverse();
+        let takes = |moves: Vec<GameMove>| -> Vec<ResourceAmount> {
+            finalize_moves(moves, 2, None)
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|m| m.resource_amount)
+                .collect()
+        };
+        assert_eq!(takes(moves), vec![8, 4]);
+        assert_eq!(takes(reversed), vec![8, 4]);
+    }
+}
@@ -0,0 +1,534 @@
+use crate::{
+    game_move::GameMove,
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const PACT_LINK_TAG: &str = "PACT";
+pub const PACT_SIGNATURE_LINK_TAG: &str = "PACT_SIGNATURE";
+
+/// Defines what happens with the moves that break the pact
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum PactEnforcement {
+    // moves that take more than the cap are invalid
+    Reject,
+    // moves that take more than the cap are valid, but the player loses
+    // penalty_factor resources for every resource they took over the cap
+    Penalty { penalty_factor: ResourceAmount },
+}
+
+/// Pact is an agreement between some players of the session to take no more
+/// than take_cap resources per move for num_rounds rounds. It's binding once
+/// every signatory has signed it, starting from the round after the one it was made in
+#[hdk_entry(id = "pact", visibility = "public")]
+#[derive(Clone)]
+pub struct Pact {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // round during which the pact has to be signed
+    pub round_hash: EntryHash,
+    pub signatories: Vec<AgentPubKey>,
+    pub take_cap: ResourceAmount,
+    pub num_rounds: u32,
+    pub enforcement: PactEnforcement,
+}
+
+/// Signature of the owner under the pact
+#[hdk_entry(id = "pact_signature", visibility = "public")]
+#[derive(Clone)]
+pub struct PactSignature {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    pub pact: EntryHash,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PactInput {
+    pub round_hash: EntryHash,
+    pub signatories: Vec<AgentPubKey>,
+    pub take_cap: ResourceAmount,
+    pub num_rounds: u32,
+    pub enforcement: PactEnforcement,
+}
+
+/// Pact that every signatory has signed, as it's stored in the round state
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ActivePact {
+    pub pact_hash: EntryHash,
+    // hashes of the signatures of every signatory, sorted
+    pub signatures: Vec<EntryHash>,
+    pub signatories: Vec<AgentPubKey>,
+    pub take_cap: ResourceAmount,
+    pub enforcement: PactEnforcement,
+    // number of rounds, including the current one, for which the pact is binding
+    pub rounds_left: u32,
+}
+
+/// Move that took more resources than a pact the player signed allows
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PactViolation {
+    pub pact_hash: EntryHash,
+    pub player: AgentPubKey,
+    pub resource_amount: ResourceAmount,
+    pub take_cap: ResourceAmount,
+    // resources the player lost for breaking the pact
+    pub penalty: ResourceAmount,
+}
+
+/// Creates a new pact for the given round, links it from the game session
+/// and signs it on behalf of the agent who made it
+pub fn new_pact(input: PactInput) -> ExternResult<EntryHash> {
+    let game_round: GameRound =
+        try_get_and_convert(input.round_hash.clone(), GetOptions::latest())?;
+    let mut signatories = input.signatories;
+    signatories.sort();
+    signatories.dedup();
+    let pact = Pact {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: game_round.session.clone(),
+        round_hash: input.round_hash,
+        signatories,
+        take_cap: input.take_cap,
+        num_rounds: input.num_rounds,
+        enforcement: input.enforcement,
+    };
+    create_entry(&pact)?;
+    let pact_entry_hash = hash_entry(&pact)?;
+
+    // Link from the game session so that every player can discover the pact
+    create_link(
+        game_round.session,
+        pact_entry_hash.clone(),
+        LinkTag::new(String::from(PACT_LINK_TAG)),
+    )?;
+    sign_pact(pact_entry_hash.clone())?;
+
+    // Return the hash of the pact because other signatories would need it to sign it
+    Ok(pact_entry_hash)
+}
+
+/// Signs the pact and links the signature from it
+pub fn sign_pact(pact_hash: EntryHash) -> ExternResult<HeaderHash> {
+    let pact: Pact = try_get_and_convert(pact_hash.clone(), GetOptions::latest())?;
+    check_round_is_open(pact.round_hash.clone(), "sign a Pact")?;
+    let pact_signature = PactSignature {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: pact.session,
+        pact: pact_hash.clone(),
+    };
+    create_entry(&pact_signature)?;
+    let pact_signature_entry_hash = hash_entry(&pact_signature)?;
+
+    create_link(
+        pact_hash,
+        pact_signature_entry_hash,
+        LinkTag::new(String::from(PACT_SIGNATURE_LINK_TAG)),
+    )
+}
+
+/// Get all pacts made in the game session, together with their entry hashes
+pub fn get_pacts_for_session(session_hash: EntryHash) -> ExternResult<Vec<(EntryHash, Pact)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(PACT_LINK_TAG))),
+    )?;
+    let mut pacts: Vec<(EntryHash, Pact)> = vec![];
+    for link in links.into_inner() {
+        let pact: Pact = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        pacts.push((link.target, pact));
+    }
+    Ok(pacts)
+}
+
+/// Get all pacts made during the round that every signatory has signed so far.
+/// If a player has signed a pact several times, the signature with the smallest
+/// entry hash is used
+pub fn get_signed_pacts_for_round(
+    session_hash: EntryHash,
+    round_hash: EntryHash,
+) -> ExternResult<Vec<ActivePact>> {
+    let mut signed_pacts: Vec<ActivePact> = vec![];
+    for (pact_hash, pact) in get_pacts_for_session(session_hash)? {
+        // pacts of all rounds are linked from the session, so we filter out the others
+        if pact.round_hash != round_hash {
+            continue;
+        }
+        let links = get_links(
+            pact_hash.clone(),
+            Some(LinkTag::new(String::from(PACT_SIGNATURE_LINK_TAG))),
+        )?;
+        let mut signature_per_player: BTreeMap<AgentPubKey, EntryHash> = BTreeMap::new();
+        for link in links.into_inner() {
+            let signature: PactSignature =
+                try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+            if !pact.signatories.contains(&signature.owner) {
+                continue;
+            }
+            let is_first = match signature_per_player.get(&signature.owner) {
+                Some(prev_hash) => link.target < *prev_hash,
+                None => true,
+            };
+            if is_first {
+                signature_per_player.insert(signature.owner, link.target);
+            }
+        }
+        if signature_per_player.len() < pact.signatories.len() {
+            info!("Pact {:?} isn't signed by every signatory yet", pact_hash);
+            continue;
+        }
+        let mut signatures: Vec<EntryHash> =
+            signature_per_player.into_iter().map(|(_, h)| h).collect();
+        signatures.sort();
+        signed_pacts.push(ActivePact {
+            pact_hash,
+            signatures,
+            signatories: pact.signatories,
+            take_cap: pact.take_cap,
+            enforcement: pact.enforcement,
+            rounds_left: pact.num_rounds,
+        });
+    }
+    Ok(signed_pacts)
+}
+
+/// Checks that the active pact is made of the pact made during the round at
+/// round_hash and of the signatures of every one of it's signatories.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn verify_active_pact(active_pact: &ActivePact, round_hash: &EntryHash) -> ExternResult<bool> {
+    let pact = must_get_entry_struct::<Pact>(active_pact.pact_hash.clone())?;
+    if &pact.round_hash != round_hash
+        || pact.signatories != active_pact.signatories
+        || pact.take_cap != active_pact.take_cap
+        || pact.enforcement != active_pact.enforcement
+        || pact.num_rounds != active_pact.rounds_left
+        || active_pact.signatures.len() != pact.signatories.len()
+    {
+        return Ok(false);
+    }
+    let mut owners: Vec<AgentPubKey> = vec![];
+    for signature_hash in &active_pact.signatures {
+        let signature = must_get_entry_struct::<PactSignature>(signature_hash.clone())?;
+        if signature.pact != active_pact.pact_hash
+            || !pact.signatories.contains(&signature.owner)
+            || owners.contains(&signature.owner)
+        {
+            return Ok(false);
+        }
+        owners.push(signature.owner);
+    }
+    Ok(true)
+}
+
+/// Picks the pacts that were signed during the round at round_hash out of the pacts
+/// that are binding in the next round, and checks every one of them with
+/// verify_active_pact. The others have to be binding since an earlier round.
+/// Returns None if some of the new pacts weren't made and signed during the round.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_signed_pacts(
+    active_pacts: &[ActivePact],
+    prev_active_pacts: &[ActivePact],
+    round_hash: &EntryHash,
+) -> ExternResult<Option<Vec<ActivePact>>> {
+    let mut signed_pacts: Vec<ActivePact> = vec![];
+    for pact in active_pacts {
+        if prev_active_pacts
+            .iter()
+            .any(|p| p.pact_hash == pact.pact_hash)
+        {
+            continue;
+        }
+        if !verify_active_pact(pact, round_hash)? {
+            return Ok(None);
+        }
+        signed_pacts.push(pact.clone());
+    }
+    Ok(Some(signed_pacts))
+}
+
+/// Calculates which pacts are binding in the next round: the ones that were
+/// binding in this round and have rounds left, and the ones signed during this round.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so the result is sorted by the pact hash and every pact is there once
+pub fn next_active_pacts(
+    prev_pacts: &[ActivePact],
+    signed_pacts: Vec<ActivePact>,
+) -> Vec<ActivePact> {
+    let mut pacts: Vec<ActivePact> = prev_pacts
+        .iter()
+        .filter(|p| p.rounds_left > 1)
+        .map(|p| ActivePact {
+            rounds_left: p.rounds_left - 1,
+            ..p.clone()
+        })
+        .collect();
+    for pact in signed_pacts {
+        if pact.rounds_left > 0 && !pacts.iter().any(|p| p.pact_hash == pact.pact_hash) {
+            pacts.push(pact);
+        }
+    }
+    pacts.sort_by(|a, b| a.pact_hash.cmp(&b.pact_hash));
+    pacts
+}
+
+/// Finds the moves that break the pacts binding in the round, and takes the
+/// penalty from the players who broke the pacts that are enforced with one.
+/// Returns the violations together with the resources every player lost.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent. Pacts are
+/// already sorted, and same as with sanctions, players can't lose more resources
+/// than they have.
+pub fn apply_pacts(
+    player_stats: &mut PlayerStats,
+    pacts: &[ActivePact],
+    player_moves: &[GameMove],
+) -> (Vec<PactViolation>, PlayerStats) {
+    let mut violations: Vec<PactViolation> = vec![];
+    let mut penalties = PlayerStats::new();
+    for pact in pacts {
+        for m in player_moves {
+            if !pact.signatories.contains(&m.owner) || m.resource_amount <= pact.take_cap {
+                continue;
+            }
+            let penalty = match pact.enforcement {
+                PactEnforcement::Penalty { penalty_factor } => {
+                    let balance = std::cmp::max(*player_stats.get(&m.owner).unwrap_or(&0), 0);
+                    std::cmp::min(
+                        (m.resource_amount - pact.take_cap) * penalty_factor,
+                        balance,
+                    )
+                }
+                PactEnforcement::Reject => 0,
+            };
+            if penalty > 0 {
+                *player_stats.entry(m.owner.clone()).or_insert(0) -= penalty;
+                *penalties.entry(m.owner.clone()).or_insert(0) += penalty;
+            }
+            violations.push(PactViolation {
+                pact_hash: pact.pact_hash.clone(),
+                player: m.owner.clone(),
+                resource_amount: m.resource_amount,
+                take_cap: pact.take_cap,
+                penalty,
+            });
+        }
+    }
+    (violations, penalties)
+}
+
+/// Checks that the move doesn't break the pacts that reject violating moves.
+/// Returns None if the move keeps to the pacts
+pub fn validate_pacts(
+    game_move: &GameMove,
+    game_round: &GameRound,
+) -> Option<ValidateCallbackResult> {
+    for pact in &game_round.state.active_pacts {
+        if pact.enforcement == PactEnforcement::Reject
+            && pact.signatories.contains(&game_move.owner)
+            && game_move.resource_amount > pact.take_cap
+        {
+            return Some(ValidateCallbackResult::Invalid(format!(
+                "GameMove can't take more than {} resources because of the pact the player signed, but it takes {}",
+                pact.take_cap, game_move.resource_amount
+            )));
+        }
+    }
+    None
+}
+
+/// Validates creation of Pact entries
+pub fn validate_create_entry_pact(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let pact: Pact = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &pact.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Pact can only be created by it's owner",
+        )));
+    }
+
+    if pact.take_cap < 0 || pact.num_rounds == 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Pact has to cap takes at >= 0 resources for > 0 rounds, but it caps them at {} for {}",
+            pact.take_cap, pact.num_rounds
+        )));
+    }
+    if let PactEnforcement::Penalty { penalty_factor } = pact.enforcement {
+        if penalty_factor <= 0 {
+            return Ok(ValidateCallbackResult::Invalid(format!(
+                "Pact has to have penalty factor > 0, but it has {}",
+                penalty_factor
+            )));
+        }
+    }
+
+    // signatories are sorted and unique, so the pact has the same hash
+    // no matter in which order the owner listed them
+    let mut signatories = pact.signatories.clone();
+    signatories.sort();
+    signatories.dedup();
+    if signatories != pact.signatories
+        || signatories.len() < 2
+        || !signatories.contains(&pact.owner)
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Pact has to have at least two sorted unique signatories, including it's owner",
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(pact.round_hash.clone())?;
+    if game_round.session != pact.session {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Pact's round doesn't belong to it's GameSession",
+        )));
+    }
+    let game_session = must_get_entry_struct::<GameSession>(pact.session)?;
+    if !signatories.iter().all(|s| game_session.players.contains(s)) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can sign a pact",
+        )));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of Pact entries
+pub fn validate_update_entry_pact(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update Pact entry",
+    )))
+}
+
+/// Validates delete of Pact entries
+pub fn validate_delete_entry_pact(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete Pact entry",
+    )))
+}
+
+/// Validates creation of PactSignature entries
+pub fn validate_create_entry_pact_signature(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let signature: PactSignature = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &signature.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "PactSignature can only be created by it's owner",
+        )));
+    }
+
+    let pact = must_get_entry_struct::<Pact>(signature.pact)?;
+    if pact.session != signature.session {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "PactSignature has to belong to the GameSession of it's pact",
+        )));
+    }
+    if !pact.signatories.contains(&signature.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only signatories of the pact can sign it",
+        )));
+    }
+
+    // NOTE: sign_pact checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of PactSignature entries
+pub fn validate_update_entry_pact_signature(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update PactSignature entry",
+    )))
+}
+
+/// Validates delete of PactSignature entries
+pub fn validate_delete_entry_pact_signature(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete PactSignature entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move, hash, stats, violation};
+
+    fn pact(
+        n: u8,
+        signatories: &[u8],
+        enforcement: PactEnforcement,
+        rounds_left: u32,
+    ) -> ActivePact {
+        ActivePact {
+            pact_hash: hash(n),
+            signatures: vec![],
+            signatories: signatories.iter().map(|s| agent(*s)).collect(),
+            take_cap: 3,
+            enforcement,
+            rounds_left,
+        }
+    }
+
+    const PENALTY: PactEnforcement = PactEnforcement::Penalty { penalty_factor: 2 };
+
+    #[test]
+    fn pacts_run_out_and_new_ones_are_added_once_in_hash_order() {
+        let prev_pacts = vec![pact(20, &[1], PENALTY, 1), pact(30, &[1], PENALTY, 2)];
+        let signed_pacts = vec![
+            pact(40, &[2], PENALTY, 3),
+            pact(10, &[2], PENALTY, 3),
+            pact(30, &[1], PENALTY, 5),
+            pact(50, &[2], PENALTY, 0),
+        ];
+        let pacts = next_active_pacts(&prev_pacts, signed_pacts);
+        assert_eq!(
+            pacts,
+            vec![
+                pact(10, &[2], PENALTY, 3),
+                pact(30, &[1], PENALTY, 1),
+                pact(40, &[2], PENALTY, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn signatories_taking_over_the_cap_are_penalised() {
+        let mut player_stats = stats(&[(1, 10), (2, 10), (3, 10)]);
+        let moves = vec![game_move(1, 5), game_move(2, 3), game_move(3, 9)];
+        let (violations, penalties) =
+            apply_pacts(&mut player_stats, &[pact(10, &[1, 2], PENALTY, 1)], &moves);
+        assert_eq!(violations, vec![violation(10, 1, 5, 4)]);
+        assert_eq!(penalties, stats(&[(1, 4)]));
+        assert_eq!(player_stats, stats(&[(1, 6), (2, 10), (3, 10)]));
+    }
+
+    #[test]
+    fn penalty_cant_take_more_than_the_player_has() {
+        let mut player_stats = stats(&[(1, 3)]);
+        let (violations, penalties) = apply_pacts(
+            &mut player_stats,
+            &[pact(10, &[1], PENALTY, 1)],
+            &[game_move(1, 8)],
+        );
+        assert_eq!(violations[0].penalty, 3);
+        assert_eq!(penalties, stats(&[(1, 3)]));
+        assert_eq!(player_stats, stats(&[(1, 0)]));
+    }
+
+    #[test]
+    fn rejecting_pacts_record_violations_without_penalty() {
+        let mut player_stats = stats(&[(1, 10)]);
+        let pacts = [pact(10, &[1], PactEnforcement::Reject, 1)];
+        let (violations, penalties) = apply_pacts(&mut player_stats, &pacts, &[game_move(1, 8)]);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].penalty, 0);
+        assert!(penalties.is_empty());
+        assert_eq!(player_stats, stats(&[(1, 10)]));
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{
+    game_move::GameMove,
+    game_round::{regenerate, GameRound},
+    game_session::ResourceAmount,
+};
+use hdk::prelude::*;
+
+/// Params of a single patch of the commons, e.g. a lake or a forest plot
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct PatchParams {
+    // how many resources are in the patch when the game starts
+    pub start_amount: ResourceAmount,
+    // how would resources of this patch re-grow every round
+    pub regeneration_factor: f32,
+}
+
+/// Params of the commons split into several patches. Patches lie in a row,
+/// so every patch neighbours the ones right before and after it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PatchesParams {
+    pub patches: Vec<PatchParams>,
+    // share of the difference in stocks of two neighbouring patches that
+    // migrates to the poorer one every round, from 0.0 to 1.0. 0.0 means
+    // that resources don't migrate
+    pub migration_rate: f32,
+}
+
+/// Makes sure that the patches make sense for the game
+pub fn check_patches(params: &PatchesParams) -> ExternResult<()> {
+    if params.patches.is_empty() {
+        return Err(WasmError::Guest(String::from(
+            "Commons has to have at least one patch",
+        )));
+    }
+    if !(0.0..=1.0).contains(&params.migration_rate) {
+        return Err(WasmError::Guest(format!(
+            "Migration rate has to be between 0.0 and 1.0, but it is {}",
+            params.migration_rate
+        )));
+    }
+    for patch in &params.patches {
+        if patch.start_amount <= 0 || !(0.0..=f32::MAX).contains(&patch.regeneration_factor) {
+            return Err(WasmError::Guest(format!(
+                "Patch has to have start amount > 0 and regeneration factor >= 0, but it has {} and {}",
+                patch.start_amount, patch.regeneration_factor
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Stocks of the patches when the game starts
+pub fn initial_patch_stocks(params: &PatchesParams) -> Vec<ResourceAmount> {
+    params.patches.iter().map(|p| p.start_amount).collect()
+}
+
+/// Sums up how many resources were taken from every patch
+pub fn takes_per_patch(player_moves: &[GameMove], num_patches: usize) -> Vec<ResourceAmount> {
+    let mut taken = vec![0; num_patches];
+    for m in player_moves {
+        if let Some(patch_taken) = m.patch.and_then(|p| taken.get_mut(p as usize)) {
+            *patch_taken += m.resource_amount;
+        }
+    }
+    taken
+}
+
+/// Reduces the takes from every patch that players together asked more resources
+/// from than it has, so that they take exactly what is left in the patch.
+/// NOTE: this fn is used when calculating the round state, so it has to give the
+/// same result for every agent:
+/// - the stock of the patch is split in proportion to what every player asked for,
+///   rounded down
+/// - what is left after the rounding goes one by one to the players who asked for
+///   more than they got, in the order of their pub keys
+pub fn clamp_takes_to_patches(
+    mut player_moves: Vec<GameMove>,
+    stocks: &[ResourceAmount],
+) -> Vec<GameMove> {
+    let asked = takes_per_patch(&player_moves, stocks.len());
+    let mut order: Vec<usize> = (0..player_moves.len()).collect();
+    order.sort_by(|a, b| player_moves[*a].owner.cmp(&player_moves[*b].owner));
+    for (patch, stock) in stocks.iter().enumerate() {
+        let stock = std::cmp::max(*stock, 0);
+        if asked[patch] <= stock {
+            continue;
+        }
+        let in_patch: Vec<usize> = order
+            .iter()
+            .cloned()
+            .filter(|i| player_moves[*i].patch == Some(patch as u32))
+            .collect();
+        split_in_proportion(&mut player_moves, &in_patch, asked[patch], stock);
+    }
+    player_moves
+}
+
+/// Reduces the takes of the moves at the given indexes, which asked for more
+/// resources together than there are available, so that they take exactly
+/// what is available: it's split in proportion to what every move asked for,
+/// rounded down, and what is left after the rounding goes one by one to the
+/// moves that asked for more than they got, in the order of the indexes
+pub fn split_in_proportion(
+    player_moves: &mut [GameMove],
+    indexes: &[usize],
+    asked: ResourceAmount,
+    available: ResourceAmount,
+) {
+    let mut left = available;
+    let mut shortfalls: Vec<usize> = vec![];
+    for i in indexes {
+        let wanted = player_moves[*i].resource_amount;
+        let share = (wanted as i64 * available as i64 / asked as i64) as ResourceAmount;
+        player_moves[*i].resource_amount = share;
+        left -= share;
+        if share < wanted {
+            shortfalls.push(*i);
+        }
+    }
+    for i in shortfalls.into_iter().take(left as usize) {
+        player_moves[i].resource_amount += 1;
+    }
+}
+
+/// Calculates stocks of the patches at the end of the round: resources are
+/// taken from every patch, reinvested resources are spread equally between
+/// patches, then every patch regrows and some resources migrate between
+/// neighbouring patches.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent:
+/// - if reinvested resources can't be spread equally, the remainder goes one by
+///   one to the patches in their order
+/// - migration between all neighbouring patches is calculated from the stocks
+///   after the regrowth, so it doesn't depend on the order of patches
+pub fn next_patch_stocks(
+    params: &PatchesParams,
+    prev_stocks: &[ResourceAmount],
+    taken: &[ResourceAmount],
+    reinvested: ResourceAmount,
+    regeneration_change: f32,
+) -> Vec<ResourceAmount> {
+    let num_patches = params.patches.len() as ResourceAmount;
+    let mut stocks: Vec<ResourceAmount> = vec![];
+    for (i, patch) in params.patches.iter().enumerate() {
+        let extra = if (i as ResourceAmount) < reinvested % num_patches {
+            1
+        } else {
+            0
+        };
+        let left = prev_stocks.get(i).unwrap_or(&0) - taken.get(i).unwrap_or(&0)
+            + reinvested / num_patches
+            + extra;
+        stocks.push(regenerate(
+            left,
+            patch.regeneration_factor,
+            regeneration_change,
+        ));
+    }
+    migrate(&mut stocks, params.migration_rate);
+    stocks
+}
+
+/// Moves resources from every patch to it's poorer neighbours
+fn migrate(stocks: &mut [ResourceAmount], migration_rate: f32) {
+    let regrown = stocks.to_vec();
+    for (i, neighbours) in regrown.windows(2).enumerate() {
+        let flow =
+            ((neighbours[0] - neighbours[1]) as f32 * migration_rate / 2.0) as ResourceAmount;
+        stocks[i] -= flow;
+        stocks[i + 1] += flow;
+    }
+}
+
+/// Checks that the move takes resources from an existing patch, and not more
+/// than there are in this patch. Returns None if the move is valid
+pub fn validate_patch(
+    game_move: &GameMove,
+    game_round: &GameRound,
+) -> Option<ValidateCallbackResult> {
+    match (&game_round.state.game_params.patches, game_move.patch) {
+        (None, None) => None,
+        (None, Some(_)) => Some(ValidateCallbackResult::Invalid(String::from(
+            "GameMove can't choose a patch: this commons doesn't have patches",
+        ))),
+        (Some(_), None) => Some(ValidateCallbackResult::Invalid(String::from(
+            "GameMove has to choose a patch to take resources from",
+        ))),
+        (Some(_), Some(patch)) => match game_round.state.patch_stocks.get(patch as usize) {
+            None => Some(ValidateCallbackResult::Invalid(format!(
+                "GameMove can't take resources from patch {}: there's no such patch",
+                patch
+            ))),
+            Some(stock) if game_move.resource_amount > *stock => {
+                Some(ValidateCallbackResult::Invalid(format!(
+                    "GameMove can't take {} resources from patch {}: it only has {}",
+                    game_move.resource_amount, patch, stock
+                )))
+            }
+            Some(_) => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::game_move;
+
+    fn patch_move(owner: u8, patch: Option<u32>, resource_amount: ResourceAmount) -> GameMove {
+        GameMove {
+            patch,
+            ..game_move(owner, resource_amount)
+        }
+    }
+
+    fn patches(regeneration_factors: &[f32], migration_rate: f32) -> PatchesParams {
+        PatchesParams {
+            patches: regeneration_factors
+                .iter()
+                .map(|regeneration_factor| PatchParams {
+                    start_amount: 10,
+                    regeneration_factor: *regeneration_factor,
+                })
+                .collect(),
+            migration_rate,
+        }
+    }
+
+    fn takes(moves: &[GameMove]) -> Vec<ResourceAmount> {
+        moves.iter().map(|m| m.resource_amount).collect()
+    }
+
+    #[test]
+    fn patches_have_to_make_sense() {
+        assert!(check_patches(&patches(&[1.1, 1.2], 0.5)).is_ok());
+        assert!(check_patches(&patches(&[], 0.5)).is_err());
+        assert!(check_patches(&patches(&[1.1], 1.5)).is_err());
+        assert!(check_patches(&patches(&[-1.0], 0.5)).is_err());
+        assert!(check_patches(&patches(&[f32::NAN], 0.5)).is_err());
+        assert!(check_patches(&patches(&[1.1], f32::NAN)).is_err());
+    }
+
+    #[test]
+    fn takes_are_summed_up_per_patch() {
+        let moves = vec![
+            patch_move(1, Some(0), 3),
+            patch_move(2, Some(1), 4),
+            patch_move(3, Some(0), 5),
+            patch_move(4, Some(7), 6),
+            patch_move(5, None, 7),
+        ];
+        assert_eq!(takes_per_patch(&moves, 2), vec![8, 4]);
+    }
+
+    #[test]
+    fn takes_within_the_stock_arent_clamped() {
+        let moves = vec![patch_move(1, Some(0), 3), patch_move(2, Some(1), 4)];
+        assert_eq!(takes(&clamp_takes_to_patches(moves, &[3, 10])), vec![3, 4]);
+    }
+
+    #[test]
+    fn overtaken_stock_is_split_in_proportion_to_the_takes() {
+        let moves = vec![
+            patch_move(1, Some(0), 6),
+            patch_move(2, Some(0), 2),
+            patch_move(3, Some(1), 4),
+        ];
+        assert_eq!(
+            takes(&clamp_takes_to_patches(moves, &[4, 10])),
+            vec![3, 1, 4]
+        );
+    }
+
+    #[test]
+    fn rounding_remainder_goes_in_pub_key_order() {
+        // 10 split between three players who asked for 4 each is 3 each,
+        // and the remaining 1 goes to the player with the smallest pub key
+        let moves = vec![
+            patch_move(3, Some(0), 4),
+            patch_move(1, Some(0), 4),
+            patch_move(2, Some(0), 4),
+        ];
+        assert_eq!(takes(&clamp_takes_to_patches(moves, &[10])), vec![3, 4, 3]);
+    }
+
+    #[test]
+    fn nothing_can_be_taken_from_an_empty_patch() {
+        let moves = vec![patch_move(1, Some(0), 4), patch_move(2, Some(0), 4)];
+        assert_eq!(takes(&clamp_takes_to_patches(moves, &[-2])), vec![0, 0]);
+    }
+
+    #[test]
+    fn reinvested_resources_are_spread_and_patches_regrow() {
+        let params = patches(&[1.5, 1.0], 0.0);
+        // 3 reinvested resources: 2 go to the first patch and 1 to the second
+        assert_eq!(
+            next_patch_stocks(&params, &[10, 20], &[4, 5], 3, 0.0),
+            vec![12, 16]
+        );
+    }
+
+    #[test]
+    fn resources_migrate_to_poorer_neighbours() {
+        let params = patches(&[1.0, 1.0, 1.0], 0.5);
+        let stocks = next_patch_stocks(&params, &[20, 12, 4], &[0, 0, 0], 0, 0.0);
+        assert_eq!(stocks, vec![18, 12, 6]);
+        assert_eq!(stocks.iter().sum::<ResourceAmount>(), 36);
+    }
+}
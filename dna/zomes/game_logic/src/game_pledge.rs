@@ -0,0 +1,307 @@
+use crate::{
+    game_move::GameMove,
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    game_subsistence::active_players,
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const PLEDGE_LINK_TAG: &str = "PLEDGE";
+
+// Alias to avoid writing the generic type specification every time
+// Every pledge a player made since the start of the game and how they kept it
+pub type TrustHistory = BTreeMap<AgentPubKey, Vec<PledgeRecord>>;
+
+/// Pledge is a non-binding promise of the owner to take no more than
+/// resource_amount in the round that follows the one at round_hash
+#[hdk_entry(id = "pledge", visibility = "public")]
+#[derive(Clone)]
+pub struct Pledge {
+    pub owner: AgentPubKey,
+    pub round_hash: EntryHash,
+    pub resource_amount: ResourceAmount,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PledgeInput {
+    pub round_hash: EntryHash,
+    pub resource_amount: ResourceAmount,
+}
+
+/// What the player pledged to take in the round and what they actually took
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PledgeRecord {
+    pub round_num: u32,
+    pub pledged: ResourceAmount,
+    pub taken: ResourceAmount,
+}
+
+/// Helper struct to package the trust history of a player for the UI
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerTrust {
+    pub player: AgentPubKey,
+    pub records: Vec<PledgeRecord>,
+    // number of pledges the player kept by taking no more than they pledged
+    pub pledges_kept: u32,
+    // share of pledges the player kept, from 0.0 to 1.0. None if they never pledged
+    pub credibility: Option<f32>,
+}
+
+/// Create a new pledge entry, and link it from its round
+pub fn new_pledge(input: PledgeInput) -> ExternResult<HeaderHash> {
+    check_round_is_open(input.round_hash.clone(), "make a Pledge")?;
+    let pledge = Pledge {
+        owner: agent_info()?.agent_latest_pubkey,
+        round_hash: input.round_hash.clone(),
+        resource_amount: input.resource_amount,
+    };
+    create_entry(&pledge)?;
+    let pledge_entry_hash = hash_entry(&pledge)?;
+
+    // Link from the round entry to the pledge so that whoever closes the round can find it
+    create_link(
+        input.round_hash,
+        pledge_entry_hash,
+        LinkTag::new(String::from(PLEDGE_LINK_TAG)),
+    )
+}
+
+/// Get all pledges attached to the round that we have so far, together with their entry hashes
+pub fn get_pledges_for_round(round_hash: EntryHash) -> ExternResult<Vec<(EntryHash, Pledge)>> {
+    let links = get_links(
+        round_hash,
+        Some(LinkTag::new(String::from(PLEDGE_LINK_TAG))),
+    )?;
+    let mut pledges: Vec<(EntryHash, Pledge)> = vec![];
+    for link in links.into_inner() {
+        let pledge: Pledge = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        pledges.push((link.target, pledge));
+    }
+    Ok(pledges)
+}
+
+/// Collects what every active player pledged to take in the next round, together
+/// with the sorted hashes of the pledges that were used.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent: if a player made
+/// several pledges, the one with the smallest entry hash is used
+pub fn pledges_for_next_round(
+    pledges: Vec<(EntryHash, Pledge)>,
+    players: &[AgentPubKey],
+) -> (PlayerStats, Vec<EntryHash>) {
+    let mut pledge_per_player: BTreeMap<AgentPubKey, (EntryHash, ResourceAmount)> = BTreeMap::new();
+    for (pledge_hash, pledge) in pledges {
+        if !players.contains(&pledge.owner) {
+            continue;
+        }
+        let is_first = match pledge_per_player.get(&pledge.owner) {
+            Some((prev_hash, _)) => pledge_hash < *prev_hash,
+            None => true,
+        };
+        if is_first {
+            pledge_per_player.insert(pledge.owner, (pledge_hash, pledge.resource_amount));
+        }
+    }
+    let mut pledge_hashes: Vec<EntryHash> = pledge_per_player
+        .values()
+        .map(|(pledge_hash, _)| pledge_hash.clone())
+        .collect();
+    pledge_hashes.sort();
+    let pledges = pledge_per_player
+        .into_iter()
+        .map(|(player, (_, amount))| (player, amount))
+        .collect();
+    (pledges, pledge_hashes)
+}
+
+/// Loads the pledges counted for the next round by their hashes, and checks that
+/// all of them were made during the round at round_hash.
+/// Returns None if some of them were made in another round.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_pledges(
+    pledge_hashes: &[EntryHash],
+    round_hash: &EntryHash,
+) -> ExternResult<Option<Vec<(EntryHash, Pledge)>>> {
+    let mut pledges: Vec<(EntryHash, Pledge)> = vec![];
+    for pledge_hash in pledge_hashes {
+        let pledge = must_get_entry_struct::<Pledge>(pledge_hash.clone())?;
+        if &pledge.round_hash != round_hash {
+            return Ok(None);
+        }
+        pledges.push((pledge_hash.clone(), pledge));
+    }
+    Ok(Some(pledges))
+}
+
+/// Compares what players pledged to take in the round with what they took,
+/// and adds it to their trust history. Returns the gap for every player who
+/// pledged and moved: positive if they took more than they pledged.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it only operates with input data
+pub fn pledge_gaps(
+    pledges: &PlayerStats,
+    player_moves: &[GameMove],
+    trust_history: &mut TrustHistory,
+    round_num: u32,
+) -> PlayerStats {
+    let mut gaps = PlayerStats::new();
+    for m in player_moves {
+        if let Some(pledged) = pledges.get(&m.owner) {
+            gaps.insert(m.owner.clone(), m.resource_amount - pledged);
+            trust_history
+                .entry(m.owner.clone())
+                .or_default()
+                .push(PledgeRecord {
+                    round_num,
+                    pledged: *pledged,
+                    taken: m.resource_amount,
+                });
+        }
+    }
+    gaps
+}
+
+/// Summarizes the trust history of every player
+pub fn trust_of_players(trust_history: &TrustHistory) -> Vec<PlayerTrust> {
+    trust_history
+        .iter()
+        .map(|(player, records)| {
+            let pledges_kept = records.iter().filter(|r| r.taken <= r.pledged).count() as u32;
+            PlayerTrust {
+                player: player.clone(),
+                records: records.clone(),
+                pledges_kept,
+                credibility: match records.len() {
+                    0 => None,
+                    n => Some(pledges_kept as f32 / n as f32),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Get the trust history of every player up to the round at round_hash
+pub fn get_trust_history(round_hash: EntryHash) -> ExternResult<Vec<PlayerTrust>> {
+    let game_round: GameRound = try_get_and_convert(round_hash, GetOptions::latest())?;
+    Ok(trust_of_players(&game_round.state.trust_history))
+}
+
+/// Validates creation of Pledge entries
+pub fn validate_create_entry_pledge(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let pledge: Pledge = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &pledge.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Pledge can only be created by it's owner",
+        )));
+    }
+
+    if pledge.resource_amount < 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Pledge has to be for >= 0 resources, but it is for {}",
+            pledge.resource_amount
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(pledge.round_hash.clone())?;
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session.clone())?;
+    if !active_players(&game_session.players, &game_round.state.eliminated_players)
+        .contains(&pledge.owner)
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only active GameSession players can make a pledge",
+        )));
+    }
+
+    // NOTE: new_pledge checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of Pledge entries
+pub fn validate_update_entry_pledge(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update Pledge entry",
+    )))
+}
+
+/// Validates delete of Pledge entries
+pub fn validate_delete_entry_pledge(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete Pledge entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move, hash, stats};
+
+    fn pledge(n: u8, owner: u8, resource_amount: ResourceAmount) -> (EntryHash, Pledge) {
+        (
+            hash(n),
+            Pledge {
+                owner: agent(owner),
+                round_hash: hash(0),
+                resource_amount,
+            },
+        )
+    }
+
+    fn record(round_num: u32, pledged: ResourceAmount, taken: ResourceAmount) -> PledgeRecord {
+        PledgeRecord {
+            round_num,
+            pledged,
+            taken,
+        }
+    }
+
+    #[test]
+    fn pledge_with_the_smallest_hash_is_used_for_every_active_player() {
+        let players = vec![agent(1), agent(2)];
+        let pledges = vec![
+            pledge(30, 1, 5),
+            pledge(20, 2, 4),
+            pledge(10, 1, 7),
+            pledge(40, 3, 1),
+        ];
+        let (pledges, pledge_hashes) = pledges_for_next_round(pledges, &players);
+        assert_eq!(pledges, stats(&[(1, 7), (2, 4)]));
+        assert_eq!(pledge_hashes, vec![hash(10), hash(20)]);
+    }
+
+    #[test]
+    fn gaps_are_recorded_for_players_who_pledged() {
+        let mut trust_history = TrustHistory::new();
+        let moves = vec![game_move(1, 6), game_move(2, 2), game_move(3, 9)];
+        let gaps = pledge_gaps(&stats(&[(1, 4), (2, 4)]), &moves, &mut trust_history, 3);
+        assert_eq!(gaps, stats(&[(1, 2), (2, -2)]));
+        assert_eq!(trust_history.get(&agent(1)), Some(&vec![record(3, 4, 6)]));
+        assert_eq!(trust_history.get(&agent(2)), Some(&vec![record(3, 4, 2)]));
+        assert_eq!(trust_history.get(&agent(3)), None);
+    }
+
+    #[test]
+    fn credibility_is_the_share_of_pledges_kept() {
+        let mut trust_history = TrustHistory::new();
+        trust_history.insert(
+            agent(1),
+            vec![
+                record(1, 4, 4),
+                record(2, 4, 6),
+                record(3, 4, 3),
+                record(4, 4, 5),
+            ],
+        );
+        trust_history.insert(agent(2), vec![]);
+        let trust = trust_of_players(&trust_history);
+        assert_eq!(trust.len(), 2);
+        assert_eq!(trust[0].player, agent(1));
+        assert_eq!(trust[0].pledges_kept, 2);
+        assert_eq!(trust[0].credibility, Some(0.5));
+        assert_eq!(trust[1].pledges_kept, 0);
+        assert_eq!(trust[1].credibility, None);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+
+pub const ABATEMENT_LINK_TAG: &str = "ABATEMENT";
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct PollutionParams {
+    // how much pollution every resource taken from the stock produces
+    pub pollution_per_unit: f32,
+    // share of the pollution that disappears on it's own every round, from 0.0 to 1.0
+    pub decay_rate: f32,
+    // how much every unit of pollution reduces the regeneration,
+    // e.g. 0.01 means that 10 units of pollution make resources regrow 10% less
+    pub regeneration_penalty: f32,
+    // how much pollution players clean up for every resource they spend on it.
+    // None means that players can't clean up
+    pub abatement_efficiency: Option<f32>,
+}
+
+/// Abatement is a move where the owner spends `cost` of their own resources
+/// to clean up some of the pollution when the round is closed
+#[hdk_entry(id = "abatement", visibility = "public")]
+#[derive(Clone, PartialEq)]
+pub struct Abatement {
+    pub owner: AgentPubKey,
+    pub round_hash: EntryHash,
+    pub cost: ResourceAmount,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AbatementInput {
+    pub cost: ResourceAmount,
+    pub round_hash: EntryHash,
+}
+
+/// Makes sure that the pollution params make sense for the game
+pub fn check_pollution(params: &PollutionParams) -> ExternResult<()> {
+    if !(0.0..=f32::MAX).contains(&params.pollution_per_unit)
+        || !(0.0..=f32::MAX).contains(&params.regeneration_penalty)
+        || !(0.0..=1.0).contains(&params.decay_rate)
+    {
+        return Err(WasmError::Guest(String::from(
+            "Pollution per unit and regeneration penalty have to be >= 0, and decay rate has to be between 0.0 and 1.0",
+        )));
+    }
+    match params.abatement_efficiency {
+        Some(efficiency) if !(efficiency > 0.0 && efficiency.is_finite()) => {
+            Err(WasmError::Guest(format!(
+                "Abatement efficiency has to be > 0, but it is {}",
+                efficiency
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Create a new abatement entry, and link it from its round
+pub fn new_abatement(cost: ResourceAmount, round_hash: EntryHash) -> ExternResult<HeaderHash> {
+    check_round_is_open(round_hash.clone(), "make an Abatement")?;
+    let abatement = Abatement {
+        owner: agent_info()?.agent_latest_pubkey,
+        round_hash: round_hash.clone(),
+        cost,
+    };
+    create_entry(abatement.clone())?;
+    let abatement_entry_hash = hash_entry(abatement)?;
+
+    // Link from the round entry to the abatement so that whoever closes the round can find it
+    create_link(
+        round_hash,
+        abatement_entry_hash,
+        LinkTag::new(String::from(ABATEMENT_LINK_TAG)),
+    )
+}
+
+/// Get all abatements attached to the round that we have so far, together with
+/// their entry hashes
+pub fn get_abatements_for_round(
+    last_round_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, Abatement)>> {
+    let links = get_links(
+        last_round_hash,
+        Some(LinkTag::new(String::from(ABATEMENT_LINK_TAG))),
+    )?;
+    let mut abatements: Vec<(EntryHash, Abatement)> = vec![];
+    for link in links.into_inner() {
+        let abatement: Abatement = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        abatements.push((link.target, abatement));
+    }
+    Ok(abatements)
+}
+
+/// Loads the abatements counted in the round by their hashes, and checks that all
+/// of them were made during the round at round_hash.
+/// Returns None if some of them were made in another round.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_abatements(
+    abatement_hashes: &[EntryHash],
+    round_hash: &EntryHash,
+) -> ExternResult<Option<Vec<(EntryHash, Abatement)>>> {
+    let mut abatements: Vec<(EntryHash, Abatement)> = vec![];
+    for abatement_hash in abatement_hashes {
+        let abatement = must_get_entry_struct::<Abatement>(abatement_hash.clone())?;
+        if &abatement.round_hash != round_hash {
+            return Ok(None);
+        }
+        abatements.push((abatement_hash.clone(), abatement));
+    }
+    Ok(Some(abatements))
+}
+
+/// Takes the cost of abatements from the player_stats and returns a tuple of
+/// (resources every player spent on cleaning up, hashes of the abatements counted).
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent. Same as with
+/// sanctions, abatements are sorted by their hashes first, the same abatement
+/// is counted once, and the ones the owner can't afford are skipped.
+pub fn apply_abatements(
+    player_stats: &mut PlayerStats,
+    mut abatements: Vec<(EntryHash, Abatement)>,
+) -> (PlayerStats, Vec<EntryHash>) {
+    abatements.sort_by(|a, b| a.0.cmp(&b.0));
+    abatements.dedup_by(|a, b| a.0 == b.0);
+
+    let mut costs = PlayerStats::new();
+    let mut counted: Vec<EntryHash> = vec![];
+    for (abatement_hash, a) in abatements {
+        let balance = *player_stats.get(&a.owner).unwrap_or(&0);
+        if a.cost > balance {
+            info!("Skipping abatement: {:?} can't afford it anymore", a.owner);
+            continue;
+        }
+        player_stats.insert(a.owner.clone(), balance - a.cost);
+        *costs.entry(a.owner).or_insert(0) += a.cost;
+        counted.push(abatement_hash);
+    }
+    (costs, counted)
+}
+
+/// Calculates how much pollution is cleaned up with the resources players spent on it
+pub fn pollution_abated(params: &PollutionParams, abatement_costs: &PlayerStats) -> f32 {
+    let spent: ResourceAmount = abatement_costs.values().sum();
+    match params.abatement_efficiency {
+        Some(efficiency) => spent as f32 * efficiency,
+        None => 0.0,
+    }
+}
+
+/// Calculates the pollution at the end of the round: some of the old pollution
+/// decays, resources taken during the round add to it and abatements clean it up.
+/// Pollution never goes below zero. It isn't rounded, so the pollution of small
+/// takes and the decay of small amounts add up over the rounds
+pub fn next_pollution(
+    params: &PollutionParams,
+    prev_pollution: f32,
+    resources_taken: ResourceAmount,
+    abated: f32,
+) -> f32 {
+    let decayed = prev_pollution * params.decay_rate;
+    let produced = resources_taken as f32 * params.pollution_per_unit;
+    (prev_pollution - decayed + produced - abated).max(0.0)
+}
+
+/// Combines the change of the regeneration caused by a random event with the
+/// one caused by the pollution, so that resources regrow by
+/// regeneration_factor * (1.0 + combined change)
+pub fn polluted_regeneration_change(
+    params: &Option<PollutionParams>,
+    pollution: f32,
+    regeneration_change: f32,
+) -> f32 {
+    match params {
+        Some(pollution_params) => {
+            let penalty = (pollution * pollution_params.regeneration_penalty).min(1.0);
+            (1.0 + regeneration_change) * (1.0 - penalty) - 1.0
+        }
+        None => regeneration_change,
+    }
+}
+
+/// Validates creation of Abatement entries
+pub fn validate_create_entry_abatement(data: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    let abatement: Abatement = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &abatement.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Abatement can only be created by it's owner",
+        )));
+    }
+
+    if abatement.cost <= 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Abatement has to have cost > 0, but it has {}",
+            abatement.cost
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(abatement.round_hash.clone())?;
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session.clone())?;
+
+    match game_round.state.game_params.pollution {
+        Some(PollutionParams {
+            abatement_efficiency: Some(_),
+            ..
+        }) => {}
+        _ => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "Abatements are disabled for this GameSession",
+            )));
+        }
+    }
+
+    if !game_session.players.contains(&abatement.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can clean up the pollution",
+        )));
+    }
+
+    // players can only spend resources they already have
+    let balance = *game_round
+        .state
+        .player_stats
+        .get(&abatement.owner)
+        .unwrap_or(&0);
+    if abatement.cost > balance {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Abatement costs {} but the owner only has {}",
+            abatement.cost, balance
+        )));
+    }
+
+    // NOTE: new_abatement checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of Abatement entries
+pub fn validate_update_entry_abatement(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update Abatement entry",
+    )))
+}
+
+/// Validates delete of Abatement entries
+pub fn validate_delete_entry_abatement(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete Abatement entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, hash, stats};
+
+    fn abatement(n: u8, owner: u8, cost: ResourceAmount) -> (EntryHash, Abatement) {
+        (
+            hash(n),
+            Abatement {
+                owner: agent(owner),
+                round_hash: hash(0),
+                cost,
+            },
+        )
+    }
+
+    fn pollution(abatement_efficiency: Option<f32>) -> PollutionParams {
+        PollutionParams {
+            pollution_per_unit: 0.5,
+            decay_rate: 0.25,
+            regeneration_penalty: 0.125,
+            abatement_efficiency,
+        }
+    }
+
+    #[test]
+    fn pollution_params_have_to_make_sense() {
+        assert!(check_pollution(&pollution(Some(2.0))).is_ok());
+        assert!(check_pollution(&pollution(None)).is_ok());
+        assert!(check_pollution(&pollution(Some(0.0))).is_err());
+        let mut params = pollution(None);
+        params.decay_rate = 1.5;
+        assert!(check_pollution(&params).is_err());
+        params.decay_rate = f32::NAN;
+        assert!(check_pollution(&params).is_err());
+        assert!(check_pollution(&pollution(Some(f32::NAN))).is_err());
+    }
+
+    #[test]
+    fn unaffordable_and_duplicate_abatements_are_skipped() {
+        let mut player_stats = stats(&[(1, 5), (2, 1)]);
+        let abatements = vec![
+            abatement(10, 1, 3),
+            abatement(10, 1, 3),
+            abatement(11, 1, 4),
+            abatement(12, 2, 2),
+        ];
+        let (costs, counted) = apply_abatements(&mut player_stats, abatements);
+        assert_eq!(costs, stats(&[(1, 3)]));
+        assert_eq!(counted, vec![hash(10)]);
+        assert_eq!(player_stats, stats(&[(1, 2), (2, 1)]));
+    }
+
+    #[test]
+    fn abatements_clean_up_only_when_players_can_clean_up() {
+        let costs = stats(&[(1, 3), (2, 1)]);
+        assert_eq!(pollution_abated(&pollution(Some(2.0)), &costs), 8.0);
+        assert_eq!(pollution_abated(&pollution(None), &costs), 0.0);
+    }
+
+    #[test]
+    fn pollution_decays_grows_with_takes_and_never_goes_negative() {
+        let params = pollution(Some(2.0));
+        // 8 - 2 decayed + 5 produced - 3 abated
+        assert_eq!(next_pollution(&params, 8.0, 10, 3.0), 8.0);
+        assert_eq!(next_pollution(&params, 8.0, 10, 20.0), 0.0);
+    }
+
+    #[test]
+    fn pollution_of_small_takes_adds_up_over_the_rounds() {
+        let params = pollution(None);
+        // every take of 1 produces only half a unit of pollution, and a quarter
+        // of it decays every round, so nothing would be left if it was rounded
+        let mut pollution = 0.0;
+        let mut history: Vec<f32> = vec![];
+        for _ in 0..4 {
+            pollution = next_pollution(&params, pollution, 1, 0.0);
+            history.push(pollution);
+        }
+        assert_eq!(history, vec![0.5, 0.875, 1.15625, 1.3671875]);
+        // and it slows the regeneration down once it has added up
+        assert_eq!(
+            polluted_regeneration_change(&Some(params), pollution, 0.0),
+            -(1.3671875 * 0.125)
+        );
+    }
+
+    #[test]
+    fn pollution_slows_the_regeneration_down() {
+        let params = Some(pollution(None));
+        assert_eq!(polluted_regeneration_change(&None, 4.0, 0.5), 0.5);
+        assert_eq!(polluted_regeneration_change(&params, 0.0, 0.5), 0.5);
+        assert_eq!(polluted_regeneration_change(&params, 4.0, 0.0), -0.5);
+        assert_eq!(polluted_regeneration_change(&params, 4.0, 1.0), 0.0);
+        // resources can't regrow less than not at all
+        assert_eq!(polluted_regeneration_change(&params, 16.0, 0.0), -1.0);
+    }
+}
@@ -0,0 +1,553 @@
+use crate::{
+    game_move::GameMove,
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const QUOTA_ORDER_LINK_TAG: &str = "QUOTA_ORDER";
+pub const QUOTA_FILL_LINK_TAG: &str = "QUOTA_FILL";
+
+// Alias to avoid writing the generic type specification every time
+// Number of quota units every player holds
+pub type QuotaHoldings = BTreeMap<AgentPubKey, u32>;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct QuotaParams {
+    // max amount of resources all players can take in a single round
+    pub total_allowable_catch: ResourceAmount,
+    // number of units the total allowable catch is split into. Every unit
+    // lets it's holder take total_allowable_catch / quota_units resources per round
+    pub quota_units: u32,
+}
+
+impl QuotaParams {
+    /// How many resources a player can take per round for every unit they hold
+    pub fn unit_size(&self) -> ResourceAmount {
+        self.total_allowable_catch / self.quota_units as ResourceAmount
+    }
+}
+
+/// Order to sell some of the owner's quota units for a price per unit.
+/// Orders are made during a round and the units change hands when it's closed
+#[hdk_entry(id = "quota_order", visibility = "public")]
+#[derive(Clone)]
+pub struct QuotaOrder {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // round during which the order can be filled
+    pub round_hash: EntryHash,
+    pub units: u32,
+    pub price_per_unit: ResourceAmount,
+}
+
+/// Fill of a quota order: the owner buys all units of the order
+#[hdk_entry(id = "quota_fill", visibility = "public")]
+#[derive(Clone)]
+pub struct QuotaFill {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    pub order: EntryHash,
+}
+
+/// QuotaOrder together with all the fills it got
+#[derive(Clone, Debug)]
+pub struct FilledQuotaOrder {
+    pub order_hash: EntryHash,
+    pub order: QuotaOrder,
+    pub fills: Vec<(EntryHash, QuotaFill)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuotaOrderInput {
+    pub round_hash: EntryHash,
+    pub units: u32,
+    pub price_per_unit: ResourceAmount,
+}
+
+/// Makes sure that the quota params make sense for the game
+pub fn check_quota(params: &QuotaParams) -> ExternResult<()> {
+    if params.quota_units == 0
+        || params.total_allowable_catch < params.quota_units as ResourceAmount
+    {
+        return Err(WasmError::Guest(format!(
+            "Total allowable catch {} has to be split into at least one unit, and every unit has to be worth at least 1 resource",
+            params.total_allowable_catch
+        )));
+    }
+    Ok(())
+}
+
+/// Splits quota units equally between players when the game starts.
+/// If they can't be split equally, the remainder goes one by one to the players
+/// in the order of their pub keys
+pub fn initial_quota_holdings(params: &QuotaParams, players: &[AgentPubKey]) -> QuotaHoldings {
+    let mut holdings = QuotaHoldings::new();
+    if players.is_empty() {
+        return holdings;
+    }
+    let mut sorted_players = players.to_vec();
+    sorted_players.sort();
+    let share = params.quota_units / players.len() as u32;
+    let remainder = params.quota_units % players.len() as u32;
+    for (i, player) in sorted_players.into_iter().enumerate() {
+        let extra = if (i as u32) < remainder { 1 } else { 0 };
+        holdings.insert(player, share + extra);
+    }
+    holdings
+}
+
+/// Creates a new quota order for the given round and links it from the game session
+pub fn new_quota_order(input: QuotaOrderInput) -> ExternResult<EntryHash> {
+    let game_round: GameRound =
+        try_get_and_convert(input.round_hash.clone(), GetOptions::latest())?;
+    check_round_is_open(input.round_hash.clone(), "make a QuotaOrder")?;
+    let quota_order = QuotaOrder {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: game_round.session.clone(),
+        round_hash: input.round_hash,
+        units: input.units,
+        price_per_unit: input.price_per_unit,
+    };
+    create_entry(&quota_order)?;
+    let quota_order_entry_hash = hash_entry(&quota_order)?;
+
+    // Link from the game session so that every player can discover the order
+    create_link(
+        game_round.session,
+        quota_order_entry_hash.clone(),
+        LinkTag::new(String::from(QUOTA_ORDER_LINK_TAG)),
+    )?;
+
+    // Return the hash of the order because players would need it to fill it
+    Ok(quota_order_entry_hash)
+}
+
+/// Fills the quota order and links the fill from the game session
+pub fn fill_quota_order(order_hash: EntryHash) -> ExternResult<HeaderHash> {
+    let quota_order: QuotaOrder = try_get_and_convert(order_hash.clone(), GetOptions::latest())?;
+    check_round_is_open(quota_order.round_hash.clone(), "fill a QuotaOrder")?;
+    let quota_fill = QuotaFill {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: quota_order.session.clone(),
+        order: order_hash,
+    };
+    create_entry(&quota_fill)?;
+    let quota_fill_entry_hash = hash_entry(&quota_fill)?;
+
+    create_link(
+        quota_order.session,
+        quota_fill_entry_hash,
+        LinkTag::new(String::from(QUOTA_FILL_LINK_TAG)),
+    )
+}
+
+/// Get all quota orders made during the round, together with their entry hashes
+pub fn get_quota_orders_for_round(
+    round_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, QuotaOrder)>> {
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let links = get_links(
+        game_round.session,
+        Some(LinkTag::new(String::from(QUOTA_ORDER_LINK_TAG))),
+    )?;
+    let mut orders: Vec<(EntryHash, QuotaOrder)> = vec![];
+    for link in links.into_inner() {
+        let order: QuotaOrder = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        // orders of all rounds are linked from the session, so we filter out the others
+        if order.round_hash == round_hash {
+            orders.push((link.target, order));
+        }
+    }
+    Ok(orders)
+}
+
+/// Get all quota orders made during the round together with their fills
+pub fn get_filled_quota_orders_for_round(
+    round_hash: EntryHash,
+) -> ExternResult<Vec<FilledQuotaOrder>> {
+    let orders = get_quota_orders_for_round(round_hash)?;
+    let session = match orders.first() {
+        Some((_, order)) => order.session.clone(),
+        None => return Ok(vec![]),
+    };
+    let links = get_links(
+        session,
+        Some(LinkTag::new(String::from(QUOTA_FILL_LINK_TAG))),
+    )?;
+    let mut fills: Vec<(EntryHash, QuotaFill)> = vec![];
+    for link in links.into_inner() {
+        let fill: QuotaFill = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        fills.push((link.target, fill));
+    }
+    Ok(orders
+        .into_iter()
+        .map(|(order_hash, order)| FilledQuotaOrder {
+            fills: fills
+                .iter()
+                .filter(|(_, f)| f.order == order_hash)
+                .cloned()
+                .collect(),
+            order_hash,
+            order,
+        })
+        .collect())
+}
+
+/// Moves quota units from sellers to buyers of the filled orders, and the price
+/// of the units from buyers to sellers. Buyers pay with what they had before the
+/// round, so that validation can settle the orders again. Returns new quota holdings,
+/// the net amount of resources every player received by selling quota, and the
+/// orders that were settled together with the fills that won them.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent:
+/// - orders are settled in the order of their entry hashes
+/// - if an order got several fills, the one with the smallest entry hash wins
+/// - orders the seller doesn't have enough units for anymore, and fills the buyer
+///   can't afford, are skipped, since validation can only check every order and
+///   fill on it's own
+pub fn settle_quota_orders(
+    prev_player_stats: &PlayerStats,
+    holdings: &QuotaHoldings,
+    mut orders: Vec<FilledQuotaOrder>,
+) -> (QuotaHoldings, PlayerStats, Vec<(EntryHash, EntryHash)>) {
+    let mut new_holdings = holdings.clone();
+    let mut balances = prev_player_stats.clone();
+    let mut payments = PlayerStats::new();
+    let mut settled: Vec<(EntryHash, EntryHash)> = vec![];
+
+    orders.sort_by(|a, b| a.order_hash.cmp(&b.order_hash));
+    orders.dedup_by(|a, b| a.order_hash == b.order_hash);
+    for mut filled_order in orders {
+        let order = filled_order.order;
+        filled_order.fills.sort_by(|a, b| a.0.cmp(&b.0));
+        let (fill_hash, buyer) = match filled_order.fills.into_iter().next() {
+            Some((fill_hash, fill)) => (fill_hash, fill.owner),
+            None => continue,
+        };
+        let units_held = *new_holdings.get(&order.owner).unwrap_or(&0);
+        let price = order.price_per_unit * order.units as ResourceAmount;
+        if order.units > units_held || price > *balances.get(&buyer).unwrap_or(&0) {
+            info!(
+                "Skipping quota order: {:?} can't sell or {:?} can't buy it anymore",
+                order.owner, buyer
+            );
+            continue;
+        }
+        *new_holdings.entry(order.owner.clone()).or_insert(0) -= order.units;
+        *new_holdings.entry(buyer.clone()).or_insert(0) += order.units;
+        *balances.entry(buyer.clone()).or_insert(0) -= price;
+        *balances.entry(order.owner.clone()).or_insert(0) += price;
+        *payments.entry(buyer).or_insert(0) -= price;
+        *payments.entry(order.owner).or_insert(0) += price;
+        settled.push((filled_order.order_hash, fill_hash));
+    }
+    (new_holdings, payments, settled)
+}
+
+/// Loads the quota orders settled in the round by the hashes of the orders and
+/// their fills, and checks that every order was made during the round at round_hash
+/// and every fill fills it's order. Returns None if they weren't.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_settled_quota_orders(
+    quota_trades: &[(EntryHash, EntryHash)],
+    round_hash: &EntryHash,
+) -> ExternResult<Option<Vec<FilledQuotaOrder>>> {
+    let mut orders: Vec<FilledQuotaOrder> = vec![];
+    for (order_hash, fill_hash) in quota_trades {
+        let order = must_get_entry_struct::<QuotaOrder>(order_hash.clone())?;
+        let fill = must_get_entry_struct::<QuotaFill>(fill_hash.clone())?;
+        if &order.round_hash != round_hash || &fill.order != order_hash {
+            return Ok(None);
+        }
+        orders.push(FilledQuotaOrder {
+            order_hash: order_hash.clone(),
+            order,
+            fills: vec![(fill_hash.clone(), fill)],
+        });
+    }
+    Ok(Some(orders))
+}
+
+/// Checks that the player doesn't take more resources than their quota lets them.
+/// Returns None if the move is within the quota
+pub fn validate_quota(
+    game_move: &GameMove,
+    game_round: &GameRound,
+) -> Option<ValidateCallbackResult> {
+    let quota = game_round.state.game_params.quota?;
+    let units = *game_round
+        .state
+        .quota_holdings
+        .get(&game_move.owner)
+        .unwrap_or(&0);
+    let max_take = quota.unit_size() * units as ResourceAmount;
+    if game_move.resource_amount > max_take {
+        return Some(ValidateCallbackResult::Invalid(format!(
+            "GameMove can't take more than {} resources with {} quota units, but it takes {}",
+            max_take, units, game_move.resource_amount
+        )));
+    }
+    None
+}
+
+/// Validates creation of QuotaOrder entries
+pub fn validate_create_entry_quota_order(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let quota_order: QuotaOrder = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &quota_order.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "QuotaOrder can only be created by it's owner",
+        )));
+    }
+
+    if quota_order.units == 0 || quota_order.price_per_unit < 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "QuotaOrder has to sell > 0 units for >= 0 resources, but it sells {} for {}",
+            quota_order.units, quota_order.price_per_unit
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(quota_order.round_hash.clone())?;
+    if game_round.session != quota_order.session {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "QuotaOrder's round doesn't belong to it's GameSession",
+        )));
+    }
+    if game_round.state.game_params.quota.is_none() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "There are no quotas in this GameSession",
+        )));
+    }
+    let game_session = must_get_entry_struct::<GameSession>(quota_order.session.clone())?;
+    if !game_session.players.contains(&quota_order.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can sell quota",
+        )));
+    }
+
+    // player can only sell units they already hold
+    let units_held = *game_round
+        .state
+        .quota_holdings
+        .get(&quota_order.owner)
+        .unwrap_or(&0);
+    if quota_order.units > units_held {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "QuotaOrder sells {} units but the owner only holds {}",
+            quota_order.units, units_held
+        )));
+    }
+
+    // NOTE: new_quota_order checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of QuotaOrder entries
+pub fn validate_update_entry_quota_order(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update QuotaOrder entry",
+    )))
+}
+
+/// Validates delete of QuotaOrder entries
+pub fn validate_delete_entry_quota_order(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete QuotaOrder entry",
+    )))
+}
+
+/// Validates creation of QuotaFill entries
+pub fn validate_create_entry_quota_fill(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let quota_fill: QuotaFill = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &quota_fill.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "QuotaFill can only be created by it's owner",
+        )));
+    }
+
+    let quota_order = must_get_entry_struct::<QuotaOrder>(quota_fill.order)?;
+    if quota_order.session != quota_fill.session {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "QuotaFill has to belong to the GameSession of it's order",
+        )));
+    }
+    if quota_order.owner == quota_fill.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Players can't buy their own quota",
+        )));
+    }
+    let game_session = must_get_entry_struct::<GameSession>(quota_order.session)?;
+    if !game_session.players.contains(&quota_fill.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can buy quota",
+        )));
+    }
+
+    // buyer can only pay with resources they already have
+    let game_round = must_get_entry_struct::<GameRound>(quota_order.round_hash.clone())?;
+    let price = quota_order.price_per_unit * quota_order.units as ResourceAmount;
+    let balance = *game_round
+        .state
+        .player_stats
+        .get(&quota_fill.owner)
+        .unwrap_or(&0);
+    if price > balance {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "QuotaOrder costs {} but the buyer only has {}",
+            price, balance
+        )));
+    }
+
+    // NOTE: fill_quota_order checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of QuotaFill entries
+pub fn validate_update_entry_quota_fill(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update QuotaFill entry",
+    )))
+}
+
+/// Validates delete of QuotaFill entries
+pub fn validate_delete_entry_quota_fill(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete QuotaFill entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, hash, stats};
+
+    fn holdings(units: &[(u8, u32)]) -> QuotaHoldings {
+        units.iter().map(|(n, u)| (agent(*n), *u)).collect()
+    }
+
+    fn order(
+        n: u8,
+        owner: u8,
+        units: u32,
+        price_per_unit: ResourceAmount,
+        fills: &[(u8, u8)],
+    ) -> FilledQuotaOrder {
+        FilledQuotaOrder {
+            order_hash: hash(n),
+            order: QuotaOrder {
+                owner: agent(owner),
+                session: hash(0),
+                round_hash: hash(1),
+                units,
+                price_per_unit,
+            },
+            fills: fills
+                .iter()
+                .map(|(h, buyer)| {
+                    (
+                        hash(*h),
+                        QuotaFill {
+                            owner: agent(*buyer),
+                            session: hash(0),
+                            order: hash(n),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn quota_has_to_be_split_into_units_worth_at_least_one_resource() {
+        let quota = |total_allowable_catch, quota_units| QuotaParams {
+            total_allowable_catch,
+            quota_units,
+        };
+        assert!(check_quota(&quota(10, 5)).is_ok());
+        assert_eq!(quota(10, 4).unit_size(), 2);
+        assert!(check_quota(&quota(10, 0)).is_err());
+        assert!(check_quota(&quota(3, 5)).is_err());
+    }
+
+    #[test]
+    fn units_are_split_equally_with_the_remainder_in_pub_key_order() {
+        let params = QuotaParams {
+            total_allowable_catch: 70,
+            quota_units: 7,
+        };
+        assert_eq!(
+            initial_quota_holdings(&params, &[agent(3), agent(1), agent(2)]),
+            holdings(&[(1, 3), (2, 2), (3, 2)])
+        );
+    }
+
+    #[test]
+    fn filled_order_moves_units_and_price() {
+        let (new_holdings, payments, settled) = settle_quota_orders(
+            &stats(&[(1, 0), (2, 10)]),
+            &holdings(&[(1, 3), (2, 3)]),
+            vec![order(10, 1, 2, 3, &[(20, 2)])],
+        );
+        assert_eq!(new_holdings, holdings(&[(1, 1), (2, 5)]));
+        assert_eq!(payments, stats(&[(1, 6), (2, -6)]));
+        assert_eq!(settled, vec![(hash(10), hash(20))]);
+    }
+
+    #[test]
+    fn fill_with_the_smallest_hash_wins() {
+        let (new_holdings, _, settled) = settle_quota_orders(
+            &stats(&[(2, 10), (3, 10)]),
+            &holdings(&[(1, 3)]),
+            vec![order(10, 1, 2, 3, &[(21, 2), (20, 3)])],
+        );
+        assert_eq!(new_holdings, holdings(&[(1, 1), (3, 2)]));
+        assert_eq!(settled, vec![(hash(10), hash(20))]);
+    }
+
+    #[test]
+    fn orders_that_cant_be_sold_or_bought_anymore_are_skipped() {
+        // player 1 only has units for one of their orders, and player 3
+        // can't pay for the order they filled
+        let (new_holdings, payments, settled) = settle_quota_orders(
+            &stats(&[(2, 10), (3, 1)]),
+            &holdings(&[(1, 2), (4, 1)]),
+            vec![
+                order(11, 1, 2, 1, &[(21, 2)]),
+                order(10, 1, 1, 1, &[(20, 2)]),
+                order(12, 4, 1, 2, &[(22, 3)]),
+                order(13, 4, 1, 2, &[]),
+            ],
+        );
+        assert_eq!(new_holdings, holdings(&[(1, 1), (2, 1), (4, 1)]));
+        assert_eq!(payments, stats(&[(1, 1), (2, -1)]));
+        assert_eq!(settled, vec![(hash(10), hash(20))]);
+    }
+
+    #[test]
+    fn orders_are_settled_in_the_order_of_their_hashes() {
+        // player 2 can only pay for the units of player 3 with what they get
+        // for their own units, which are sold first
+        let orders = vec![
+            order(11, 3, 1, 3, &[(21, 2)]),
+            order(10, 2, 1, 3, &[(20, 1)]),
+        ];
+        let (new_holdings, payments, settled) = settle_quota_orders(
+            &stats(&[(1, 3), (2, 0)]),
+            &holdings(&[(1, 0), (2, 1), (3, 1)]),
+            orders,
+        );
+        assert_eq!(new_holdings, holdings(&[(1, 1), (2, 1), (3, 0)]));
+        assert_eq!(payments, stats(&[(1, -3), (2, 0), (3, 3)]));
+        assert_eq!(settled, vec![(hash(10), hash(20)), (hash(11), hash(21))]);
+    }
+}
@@ -1,27 +1,174 @@
 use crate::{
-    game_move::{finalize_moves, get_moves_for_round, GameMove},
+    game_attributes::apply_player_attributes,
+    game_auction::{
+        apply_auction, clamp_takes_to_rights, get_revealed_bids_for_round, resolve_auction,
+        verify_auction_bids, AuctionResult, BidReveal,
+    },
+    game_facilitator::is_session_halted,
+    game_governance::{
+        apply_accepted_proposals, get_proposals_for_session, get_votes_for_session,
+        must_get_accepted_proposals, Proposal, Vote,
+    },
+    game_investment::{apply_investments, InvestmentResult},
+    game_monitoring::{observe_round_info, signal_round_observations},
+    game_move::{finalize_moves, get_moves_for_round, must_get_counted_moves, GameMove},
+    game_pact::{
+        apply_pacts, get_signed_pacts_for_round, must_get_signed_pacts, next_active_pacts,
+        ActivePact, PactViolation,
+    },
+    game_patches::{
+        clamp_takes_to_patches, initial_patch_stocks, next_patch_stocks, takes_per_patch,
+    },
+    game_pledge::{
+        get_pledges_for_round, must_get_pledges, pledge_gaps, pledges_for_next_round, Pledge,
+        TrustHistory,
+    },
+    game_pollution::{
+        apply_abatements, get_abatements_for_round, must_get_abatements, next_pollution,
+        polluted_regeneration_change, pollution_abated, Abatement,
+    },
+    game_quota::{
+        get_filled_quota_orders_for_round, initial_quota_holdings, must_get_settled_quota_orders,
+        settle_quota_orders, FilledQuotaOrder, QuotaHoldings,
+    },
+    game_sanction::{apply_sanctions, get_sanctions_for_round, must_get_sanctions, GameSanction},
+    game_seed::{
+        game_ends_by_chance, get_shared_seed, random_regeneration_change, reveal_deadline_passed,
+        verify_shared_seed, SharedSeed,
+    },
     game_session::{end_game, GameParams, GameSession, PlayerStats, ResourceAmount},
     game_signals::{GameSignal, SignalPayload},
+    game_subsistence::{active_players, eliminate_players, signal_eliminations},
+    game_taxation::{apply_taxation, TaxationResult},
+    game_team::{
+        clamp_takes_to_team_cap, redistribute_within_teams, team_leaderboard, team_stats, TeamId,
+        TeamStats,
+    },
+    game_trade::{
+        get_offered_trades_for_round, must_get_settled_trades, settle_trades, OfferedTrade,
+        TradeDebt,
+    },
+    game_turns::{signal_first_turn, turn_order_for_round},
     utils::{must_get_entry_struct, player_stats_from_moves, try_from_element, try_get_element},
 };
 use hdk::prelude::*;
 
 // Having a separate struct for the round state would come in
 // handy later in development
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RoundState {
     // amount of resources at the beginning of the round
     pub resources_left: ResourceAmount,
     // total amount of resources consumed during the round
     pub resources_taken: ResourceAmount,
+    // hashes of the moves counted in the round, one per active player, sorted
+    pub moves: Vec<EntryHash>,
     // amount of resources that regrew at the end of the round
     pub resources_grown: ResourceAmount,
-    // resource statistics for every player
+    // amount of resources in every patch at the beginning of the round.
+    // Empty if the commons isn't split into patches
+    pub patch_stocks: Vec<ResourceAmount>,
+    // amount of resources taken from every patch during the round
+    pub patch_taken: Vec<ResourceAmount>,
+    // change of the regeneration caused by a random event during the round,
+    // e.g. -0.1 means that resources regrew 10% less than usual
+    pub regeneration_change: f32,
+    // total amount of taxes collected from the players during the round
+    pub resources_taxed: ResourceAmount,
+    // amount of taxes given back to the players
+    pub resources_redistributed: ResourceAmount,
+    // amount of taxes put back into the resource stock before it regrew
+    pub resources_reinvested: ResourceAmount,
+    // total amount of resources players put back into the stock before it regrew
+    pub resources_restocked: ResourceAmount,
+    // resources every player put back into the stock during the round
+    pub restocks: PlayerStats,
+    // savings of every player in the bank, which are paid out when the game ends
+    pub bank_balances: PlayerStats,
+    // resource statistics for every player: how many resources each of
+    // them has collected since the start of the game
     pub player_stats: PlayerStats,
+    // resource statistics for every team: sum of the stats of it's players
+    pub team_stats: TeamStats,
+    // resources every player paid for what they took during the round
+    pub harvest_costs: PlayerStats,
+    // hashes of the bid commitments the auction of the round was resolved with,
+    // sorted. Empty if there's no auction
+    pub auction_bids: Vec<EntryHash>,
+    // resources every player won the right to take during the round
+    pub extraction_rights: PlayerStats,
+    // resources every player paid for their rights during the round
+    pub auction_payments: PlayerStats,
+    // amount of auction revenue given back to the players
+    pub auction_redistributed: ResourceAmount,
+    // amount of auction revenue put back into the resource stock before it regrew
+    pub auction_reinvested: ResourceAmount,
+    // net amount of resources every player received through trades during the round
+    pub trade_transfers: PlayerStats,
+    // resources players owe each other for the trades accepted during the round
+    pub trade_debts: Vec<TradeDebt>,
+    // trade offers settled during the round together with the acceptances that won them,
+    // sorted by the offer hashes
+    pub trades: Vec<(EntryHash, EntryHash)>,
+    // quota units every player holds for the next round
+    pub quota_holdings: QuotaHoldings,
+    // net amount of resources every player received by selling quota during the round
+    pub quota_payments: PlayerStats,
+    // quota orders settled during the round together with the fills that won them,
+    // sorted by the order hashes
+    pub quota_trades: Vec<(EntryHash, EntryHash)>,
+    // resources every player spent on sanctioning others during the round
+    pub sanction_costs: PlayerStats,
+    // resources every player lost during the round because of the sanctions
+    // others made in the previous round
+    pub sanction_penalties: PlayerStats,
+    // resources every player is going to lose in the next round because of
+    // the sanctions others made during the round
+    pub sanction_penalties_due: PlayerStats,
+    // hashes of the sanctions counted in the round, sorted
+    pub sanctions: Vec<EntryHash>,
+    // pacts that are binding in the next round, sorted by their hashes
+    pub active_pacts: Vec<ActivePact>,
+    // moves that broke the pacts binding during the round
+    pub pact_violations: Vec<PactViolation>,
+    // resources every player lost for breaking the pacts during the round
+    pub pact_penalties: PlayerStats,
+    // takes players pledged for the next round
+    pub pledges: PlayerStats,
+    // hashes of the pledges that were counted for the next round, sorted
+    pub pledge_hashes: Vec<EntryHash>,
+    // gap between what every player took during the round and what they pledged:
+    // positive if they took more
+    pub pledge_gaps: PlayerStats,
+    // every pledge players made since the start of the game and how they kept it
+    pub trust_history: TrustHistory,
+    // amount of pollution at the end of the round. It reduces the regeneration
+    // in the next round. It's fractional, so the pollution of small takes adds up
+    pub pollution: f32,
+    // amount of pollution players cleaned up during the round
+    pub pollution_abated: f32,
+    // resources every player spent on cleaning up the pollution during the round
+    pub abatement_costs: PlayerStats,
+    // hashes of the abatements counted in the round, sorted
+    pub abatements: Vec<EntryHash>,
+    // params that are in effect for the next round: those are the GameSession
+    // params with all proposals accepted by the players applied to them
+    pub game_params: GameParams,
+    // hashes of all the proposals that were accepted since the start of the game
+    pub accepted_proposals: Vec<EntryHash>,
+    // hashes of all votes on the proposals accepted during the round, both in favor
+    // and against, sorted
+    pub accepted_votes: Vec<EntryHash>,
+    // seed that all players agreed on to make random draws, made from the seeds
+    // they revealed for the round after everyone has moved
+    pub shared_seed: Option<SharedSeed>,
+    // players who fell below the subsistence line since the start of the game,
+    // sorted by their pub keys. They can't make moves anymore
+    pub eliminated_players: Vec<AgentPubKey>,
 }
 
 #[hdk_entry(id = "game_round", visibility = "public")]
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq)]
 pub struct GameRound {
     // number of current round, from 0
     pub round_num: u32,
@@ -38,11 +185,20 @@ pub struct GameRoundInfo {
     pub resources_left: Option<ResourceAmount>,
     pub resources_taken_round: Option<ResourceAmount>,
     pub resources_grown_round: Option<ResourceAmount>,
+    pub resources_taxed_round: Option<ResourceAmount>,
+    pub resources_redistributed_round: Option<ResourceAmount>,
+    pub resources_reinvested_round: Option<ResourceAmount>,
     pub current_round_entry_hash: Option<EntryHash>,
     pub prev_round_entry_hash: Option<EntryHash>,
     pub game_session_hash: Option<EntryHash>,
     pub next_action: String,
     pub moves: Vec<(ResourceAmount, String, AgentPubKey)>,
+    // teams ordered from the one with most resources. Empty if there are no teams
+    pub team_leaderboard: Vec<(TeamId, ResourceAmount)>,
+    // resources left in every patch. Empty if there are no patches
+    pub patch_stocks: Vec<ResourceAmount>,
+    // moves that broke the pacts players signed during the round
+    pub pact_violations: Vec<PactViolation>,
 }
 
 // That's a Rust way of providing methods that would be called on specific
@@ -50,22 +206,7 @@ pub struct GameRoundInfo {
 // Learn more here: https://doc.rust-lang.org/book/ch05-03-method-syntax.html
 impl GameRound {
     /// Creates a new GameRound instance with the provided input
-    /// We're writing this method to encapsulate creating RoundState instance
-    /// to initialize the state field of GameRound
-    pub fn new(
-        round_num: u32,
-        session: EntryHash,
-        resources_left: ResourceAmount,
-        resources_taken: ResourceAmount,
-        resources_grown: ResourceAmount,
-        player_stats: PlayerStats,
-    ) -> GameRound {
-        let state = RoundState {
-            resources_left,
-            resources_taken,
-            resources_grown,
-            player_stats,
-        };
+    pub fn new(round_num: u32, session: EntryHash, state: RoundState) -> GameRound {
         GameRound {
             round_num,
             session,
@@ -74,71 +215,483 @@ impl GameRound {
     }
 }
 
-/// Calculate state of the round using provided game params and player moves
+impl RoundState {
+    /// Creates the state of the round zero: nothing has happened yet
+    /// and there's start_amount of resources available
+    pub fn initial(game_params: &GameParams, players: &[AgentPubKey]) -> RoundState {
+        // if there are patches, the stock is the sum of their stocks
+        let (resources_left, patch_stocks) = match &game_params.patches {
+            Some(patches) => {
+                let patch_stocks = initial_patch_stocks(patches);
+                (patch_stocks.iter().sum(), patch_stocks)
+            }
+            None => (game_params.start_amount, vec![]),
+        };
+        RoundState {
+            resources_left,
+            patch_stocks,
+            patch_taken: vec![],
+            resources_taken: 0,
+            moves: vec![],
+            resources_grown: 0,
+            regeneration_change: 0.0,
+            resources_taxed: 0,
+            resources_redistributed: 0,
+            resources_reinvested: 0,
+            resources_restocked: 0,
+            restocks: PlayerStats::new(),
+            bank_balances: PlayerStats::new(),
+            player_stats: PlayerStats::new(),
+            team_stats: TeamStats::new(),
+            harvest_costs: PlayerStats::new(),
+            auction_bids: vec![],
+            extraction_rights: PlayerStats::new(),
+            auction_payments: PlayerStats::new(),
+            auction_redistributed: 0,
+            auction_reinvested: 0,
+            trade_transfers: PlayerStats::new(),
+            trade_debts: vec![],
+            trades: vec![],
+            quota_holdings: match &game_params.quota {
+                Some(quota) => initial_quota_holdings(quota, players),
+                None => QuotaHoldings::new(),
+            },
+            quota_payments: PlayerStats::new(),
+            quota_trades: vec![],
+            sanction_costs: PlayerStats::new(),
+            sanction_penalties: PlayerStats::new(),
+            sanction_penalties_due: PlayerStats::new(),
+            sanctions: vec![],
+            active_pacts: vec![],
+            pact_violations: vec![],
+            pact_penalties: PlayerStats::new(),
+            pledges: PlayerStats::new(),
+            pledge_hashes: vec![],
+            pledge_gaps: PlayerStats::new(),
+            trust_history: TrustHistory::new(),
+            pollution: 0.0,
+            pollution_abated: 0.0,
+            abatement_costs: PlayerStats::new(),
+            abatements: vec![],
+            game_params: game_params.clone(),
+            accepted_proposals: vec![],
+            accepted_votes: vec![],
+            shared_seed: None,
+            eliminated_players: vec![],
+        }
+    }
+}
+
+/// Everything players did during the round that affects the round state
+pub struct RoundActions {
+    // moves together with their hashes
+    pub moves: Vec<(EntryHash, GameMove)>,
+    // revealed bids together with the hashes of their commitments
+    pub bids: Vec<(EntryHash, BidReveal)>,
+    // sanctions together with their hashes
+    pub sanctions: Vec<(EntryHash, GameSanction)>,
+    // abatements together with their hashes
+    pub abatements: Vec<(EntryHash, Abatement)>,
+    pub trades: Vec<OfferedTrade>,
+    pub quota_orders: Vec<FilledQuotaOrder>,
+    // pacts made during the round that every signatory has signed
+    pub pacts: Vec<ActivePact>,
+    pub pledges: Vec<(EntryHash, Pledge)>,
+    pub proposals: Vec<(EntryHash, Proposal)>,
+    pub votes: Vec<(EntryHash, Vote)>,
+}
+
+/// Calculates how many resources there are after the stock regrows
+pub fn regenerate(
+    resources_left: ResourceAmount,
+    regeneration_factor: f32,
+    regeneration_change: f32,
+) -> ResourceAmount {
+    (resources_left as f32 * regeneration_factor * (1.0 + regeneration_change)) as ResourceAmount
+}
+
+/// Calculates the regeneration change caused by a random event in the given round.
+/// Returns None if the game has random events, but there's no shared seed to draw them from
+fn regeneration_change_for_round(
+    params: &GameParams,
+    shared_seed: &Option<SharedSeed>,
+    round_num: u32,
+) -> Option<f32> {
+    match (&params.random_events, shared_seed) {
+        (Some(random_events), Some(seed)) => {
+            Some(random_regeneration_change(random_events, seed, round_num))
+        }
+        (Some(_), None) => None,
+        (None, _) => Some(0.0),
+    }
+}
+
+/// Draws whether the game ends by chance after the given number of rounds.
+/// Returns None if the game has an unknown horizon, but there's no shared seed
+/// to draw from
+fn game_ends_for_round(
+    params: &GameParams,
+    shared_seed: &Option<SharedSeed>,
+    round_num: u32,
+) -> Option<bool> {
+    match (&params.unknown_horizon, shared_seed) {
+        (Some(unknown_horizon), Some(seed)) => {
+            Some(game_ends_by_chance(unknown_horizon, seed, round_num))
+        }
+        (Some(_), None) => None,
+        (None, _) => Some(false),
+    }
+}
+
+/// Calculates which players are eliminated once the round with round_state is over
+fn eliminated_players_for_round(
+    params: &GameParams,
+    round_state: &RoundState,
+    round_num: u32,
+    players: &[AgentPubKey],
+    prev_eliminated: &[AgentPubKey],
+) -> Vec<AgentPubKey> {
+    match params.subsistence_need {
+        Some(subsistence_need) => eliminate_players(
+            &round_state.player_stats,
+            subsistence_need,
+            round_num,
+            players,
+            prev_eliminated,
+        ),
+        None => prev_eliminated.to_vec(),
+    }
+}
+
+/// Calculate state of the round using game params of the last round,
+/// the shared seed and actions players made during the round.
+/// Players are the ones who are still active in the game
 /// NOTE: this fn would be used both in validation and when creating game round entries
 /// so it doesn't make any DHT queries and only operates with input data
 fn calculate_round_state(
     last_round: &GameRound,
-    params: &GameParams,
-    player_moves: Vec<GameMove>,
+    players: &[AgentPubKey],
+    game_session: &GameSession,
+    shared_seed: Option<SharedSeed>,
+    actions: RoundActions,
 ) -> RoundState {
+    let params = &last_round.state.game_params;
+    // moves are sorted by their hashes, so validation would load them in the same order
+    let mut hashed_moves = actions.moves;
+    hashed_moves.sort_by(|a, b| a.0.cmp(&b.0));
+    let moves: Vec<EntryHash> = hashed_moves.iter().map(|(h, _)| h.clone()).collect();
+    let player_moves: Vec<GameMove> = hashed_moves.into_iter().map(|(_, m)| m).collect();
+    // auction is resolved with what players had before the round, and nobody
+    // can take more than the rights they won
+    let mut auction_bids: Vec<EntryHash> = actions.bids.iter().map(|(h, _)| h.clone()).collect();
+    auction_bids.sort();
+    let (auction_result, player_moves) = match &params.auction {
+        Some(auction) => {
+            let auction_result =
+                resolve_auction(auction, actions.bids, &last_round.state.player_stats);
+            let player_moves = clamp_takes_to_rights(player_moves, &auction_result.rights);
+            (auction_result, player_moves)
+        }
+        None => (AuctionResult::default(), player_moves),
+    };
+    // players can't take more than there is in a patch, even if they asked for it
+    // together
+    let player_moves = match &params.patches {
+        Some(_) => clamp_takes_to_patches(player_moves, &last_round.state.patch_stocks),
+        None => player_moves,
+    };
+    // teams can't take more than their cap, even if every player asked for less
+    let player_moves = match params.teams.and_then(|teams| teams.team_cap) {
+        Some(team_cap) => clamp_takes_to_team_cap(player_moves, team_cap, &game_session.teams),
+        None => player_moves,
+    };
     let consumed_resources_in_round: ResourceAmount =
         player_moves.iter().map(|x| x.resource_amount).sum();
-    let resources_left = last_round.state.resources_left - consumed_resources_in_round;
-    let total_leftover_resource = (resources_left as f32 * params.regeneration_factor) as i32;
+    let mut player_stats = player_stats_from_moves(&last_round.state.player_stats, &player_moves);
+    // players get what they took according to their efficiency, net of their costs
+    let harvest_costs = apply_player_attributes(
+        &mut player_stats,
+        &player_moves,
+        &game_session.player_attributes,
+    );
+    // winners of the auction pay for all the rights they won, even if they took less
+    let (auction_redistributed, auction_reinvested) = match &params.auction {
+        Some(auction) => apply_auction(&mut player_stats, &auction_result, auction, players),
+        None => (0, 0),
+    };
+    // taxes are collected from what players took with their moves
+    let taxation_result = match &params.taxation {
+        Some(taxation) => apply_taxation(&mut player_stats, &player_moves, taxation, players),
+        None => TaxationResult::default(),
+    };
+
+    // team players share what they got after taxes
+    if let Some(teams) = &params.teams {
+        if teams.redistribute_within_team {
+            redistribute_within_teams(
+                &mut player_stats,
+                &last_round.state.player_stats,
+                &game_session.teams,
+                players,
+            );
+        }
+    }
+
+    // players reinvest and save what they have after taxes
+    let investment_result = match &params.investment {
+        Some(investment) => apply_investments(
+            &mut player_stats,
+            &last_round.state.bank_balances,
+            &player_moves,
+            investment,
+        ),
+        None => InvestmentResult::default(),
+    };
+    let resources_restocked: ResourceAmount = investment_result.restocked.values().sum();
+
+    // reinvested taxes and auction revenue, and resources restocked by players
+    // get back into the stock before it regrows
+    let resources_left = last_round.state.resources_left - consumed_resources_in_round
+        + taxation_result.reinvested
+        + auction_reinvested
+        + resources_restocked;
+    // try_to_close_round makes sure we have the shared seed if the game needs it
+    let regeneration_change =
+        regeneration_change_for_round(params, &shared_seed, last_round.round_num + 1)
+            .unwrap_or(0.0);
+    // pollution left from the previous rounds makes resources regrow slower
+    let total_regeneration_change = polluted_regeneration_change(
+        &params.pollution,
+        last_round.state.pollution,
+        regeneration_change,
+    );
+    // if there are patches, every one of them regrows on it's own
+    let (total_leftover_resource, patch_stocks, patch_taken) = match &params.patches {
+        Some(patches) => {
+            let patch_taken = takes_per_patch(&player_moves, patches.patches.len());
+            let patch_stocks = next_patch_stocks(
+                patches,
+                &last_round.state.patch_stocks,
+                &patch_taken,
+                taxation_result.reinvested + auction_reinvested + resources_restocked,
+                total_regeneration_change,
+            );
+            (patch_stocks.iter().sum(), patch_stocks, patch_taken)
+        }
+        None => (
+            regenerate(
+                resources_left,
+                params.regeneration_factor,
+                total_regeneration_change,
+            ),
+            vec![],
+            vec![],
+        ),
+    };
     let grown_resources_in_round = total_leftover_resource - resources_left;
 
-    let player_stats = player_stats_from_moves(player_moves);
+    // trades are settled after the moves, so players can use resources
+    // they've collected this round to pay for trades
+    let (trade_transfers, trade_debts, trades) = settle_trades(
+        &mut player_stats,
+        &last_round.state.trade_debts,
+        actions.trades,
+    );
+    // quota changes hands after trades, and only counts for the next round
+    let (quota_holdings, quota_payments, quota_trades) = match &params.quota {
+        Some(_) => settle_quota_orders(
+            &last_round.state.player_stats,
+            &last_round.state.quota_holdings,
+            actions.quota_orders,
+        ),
+        None => (QuotaHoldings::new(), PlayerStats::new(), vec![]),
+    };
+    for (player, payment) in &quota_payments {
+        *player_stats.entry(player.clone()).or_insert(0) += payment;
+    }
+    // sanctions are applied after the moves and trades, so players can use
+    // resources they've collected this round to pay for sanctions. Targets lose
+    // resources for the sanctions made in the last round
+    let sanction_result = apply_sanctions(
+        &mut player_stats,
+        params.sanction_factor,
+        &last_round.state.sanction_penalties_due,
+        actions.sanctions,
+    );
+    // players who broke the pacts they signed pay the penalty after the sanctions,
+    // and pacts signed during this round are binding from the next one
+    let (pact_violations, pact_penalties) = apply_pacts(
+        &mut player_stats,
+        &last_round.state.active_pacts,
+        &player_moves,
+    );
+    let active_pacts = next_active_pacts(&last_round.state.active_pacts, actions.pacts);
+    // players are held to the pledges they made in the last round, and pledges
+    // made during this round are about the next one
+    let mut trust_history = last_round.state.trust_history.clone();
+    let gaps = pledge_gaps(
+        &last_round.state.pledges,
+        &player_moves,
+        &mut trust_history,
+        last_round.round_num + 1,
+    );
+    let (pledges, pledge_hashes) = pledges_for_next_round(actions.pledges, players);
+    // players clean up the pollution after they've paid for everything else,
+    // and the pollution they clean up doesn't affect this round's regeneration
+    let (pollution, pollution_abated, abatement_costs, abatements) = match &params.pollution {
+        Some(pollution_params) => {
+            let (abatement_costs, abatements) = match pollution_params.abatement_efficiency {
+                Some(_) => apply_abatements(&mut player_stats, actions.abatements),
+                None => (PlayerStats::new(), vec![]),
+            };
+            let abated = pollution_abated(pollution_params, &abatement_costs);
+            let pollution = next_pollution(
+                pollution_params,
+                last_round.state.pollution,
+                consumed_resources_in_round,
+                abated,
+            );
+            (pollution, abated, abatement_costs, abatements)
+        }
+        None => (0.0, 0.0, PlayerStats::new(), vec![]),
+    };
+    // accepted proposals only change params for the rounds that follow
+    let (game_params, accepted_proposals, accepted_votes) = apply_accepted_proposals(
+        params,
+        &last_round.state.accepted_proposals,
+        actions.proposals,
+        actions.votes,
+        players,
+    );
 
-    RoundState {
+    let mut round_state = RoundState {
         resources_left: total_leftover_resource,
+        patch_stocks,
+        patch_taken,
         resources_taken: consumed_resources_in_round,
+        moves,
         resources_grown: grown_resources_in_round,
+        regeneration_change,
+        resources_taxed: taxation_result.taxed,
+        resources_redistributed: taxation_result.redistributed,
+        resources_reinvested: taxation_result.reinvested,
+        resources_restocked,
+        restocks: investment_result.restocked,
+        bank_balances: investment_result.bank_balances,
+        team_stats: team_stats(&player_stats, &game_session.teams),
         player_stats,
-    }
+        harvest_costs,
+        auction_bids,
+        extraction_rights: auction_result.rights,
+        auction_payments: auction_result.payments,
+        auction_redistributed,
+        auction_reinvested,
+        trade_transfers,
+        trade_debts,
+        trades,
+        quota_holdings,
+        quota_payments,
+        quota_trades,
+        sanction_costs: sanction_result.costs,
+        sanction_penalties: sanction_result.penalties,
+        sanction_penalties_due: sanction_result.penalties_due,
+        sanctions: sanction_result.sanctions,
+        active_pacts,
+        pact_violations,
+        pact_penalties,
+        pledges,
+        pledge_hashes,
+        pledge_gaps: gaps,
+        trust_history,
+        pollution,
+        pollution_abated,
+        abatement_costs,
+        abatements,
+        game_params,
+        accepted_proposals,
+        accepted_votes,
+        shared_seed,
+        eliminated_players: vec![],
+    };
+    // players are eliminated once everything that changes their stock has happened
+    round_state.eliminated_players = eliminated_players_for_round(
+        params,
+        &round_state,
+        last_round.round_num + 1,
+        players,
+        &last_round.state.eliminated_players,
+    );
+    round_state
 }
 
-/// Checks if we can start a new round given the game session and
-/// it's latest round (which would be previous round in regard to the one
-/// we want to start)
+/// Checks if we can start a new round given the latest round of the game
+/// (which would be previous round in regard to the one we want to start)
+/// and the state it ended with
 fn can_start_new_round(
-    game_session: &GameSession,
     prev_round: &GameRound,
     round_state: &RoundState,
+    players: &[AgentPubKey],
 ) -> bool {
-    // do we have rounds left to play?
-    prev_round.round_num + 1 < game_session.game_params.num_rounds
-    // are resources not depleted?
-        && round_state.resources_left > 0
+    !game_ends_with_round(prev_round.round_num + 1, round_state, players)
 }
 
-/// Creates a new game round by actually creating the next entry in the update
-/// chain that starts at the round zero we created in game_sessio::new_session
-fn create_new_round(
-    game_session: &GameSession,
+/// Checks if the round with round_num that has round_state is the last round
+/// of the game.
+/// NOTE: this fn is used when closing the round and in GameRound and GameSession validation,
+/// so it only operates with input data
+pub fn game_ends_with_round(
+    round_num: u32,
+    round_state: &RoundState,
+    players: &[AgentPubKey],
+) -> bool {
+    // do we have rounds left to play? Players might have voted to end the game
+    // earlier, so we're checking the params that are in effect now
+    round_num >= round_state.game_params.num_rounds
+    // are resources depleted?
+        || round_state.resources_left <= 0
+    // is there no one left to play?
+        || active_players(players, &round_state.eliminated_players).is_empty()
+    // if players don't know when the game ends, did the draw end it?
+        || game_ends_for_round(&round_state.game_params, &round_state.shared_seed, round_num)
+            != Some(false)
+}
+
+/// Commits the round that follows last_round with round_state as an update
+/// of the last round, and returns it together with it's entry hash
+fn commit_next_round(
     last_round: &GameRound,
     last_round_header_hash: &HeaderHash,
     round_state: &RoundState,
-) -> ExternResult<EntryHash> {
+) -> ExternResult<(GameRound, EntryHash)> {
     info!(
-        "create_new_round: updating game round entry at {:?}. Last round num {:?}",
+        "commit_next_round: updating game round entry at {:?}. Last round num {:?}",
         last_round, last_round.round_num
     );
     // create a Rust struct instance with all the data we need
     let next_round = GameRound::new(
         last_round.round_num + 1,
         last_round.session.clone().into(),
-        round_state.resources_left,
-        round_state.resources_taken,
-        round_state.resources_grown,
-        // making a clone here because GameRound::new would consume player_stats
+        // making a clone here because GameRound::new would consume round_state
         // but we have a shared reference to it which doesn't belong to the current fn
-        round_state.player_stats.clone(),
+        round_state.clone(),
     );
     // commit an update to the DHT
     update_entry(last_round_header_hash.clone(), &next_round)?;
     // calculate the hash of the entry (no DHT writes here)
     let round_entry_hash_update = hash_entry(&next_round)?;
+    Ok((next_round, round_entry_hash_update))
+}
+
+/// Creates a new game round by actually creating the next entry in the update
+/// chain that starts at the round zero we created in game_sessio::new_session
+fn create_new_round(
+    game_session: &GameSession,
+    last_round: &GameRound,
+    last_round_header_hash: &HeaderHash,
+    round_state: &RoundState,
+) -> ExternResult<EntryHash> {
+    let (next_round, round_entry_hash_update) =
+        commit_next_round(last_round, last_round_header_hash, round_state)?;
 
     // Create a signal payload to provide game session and round info
     // in the signal itself
@@ -150,6 +703,13 @@ fn create_new_round(
     let signal = ExternIO::encode(GameSignal::StartNextRound(signal_payload))?;
     // Actually send our signal to all the agents who are listed as game session players
     remote_signal(signal, game_session.players.clone())?;
+    // If players move one after another, the first one can move now
+    signal_first_turn(
+        game_session,
+        last_round.session.clone(),
+        &next_round,
+        round_entry_hash_update.clone(),
+    )?;
 
     Ok(round_entry_hash_update)
 }
@@ -172,83 +732,358 @@ pub fn try_to_close_round(last_round_hash: EntryHash) -> ExternResult<GameRoundI
     let game_session_element = try_get_element(last_round.session.clone(), GetOptions::latest())?;
     let game_session: GameSession = try_from_element(game_session_element.clone())?;
 
+    // Nobody can close rounds while the facilitator has the session paused or ended
+    if is_session_halted(last_round.session.clone())? {
+        let mut info = waiting_round_info(&last_round, last_round_hash);
+        info.next_action = "PAUSED".into();
+        return Ok(info);
+    }
+
     // Retrieve game moves from DHT
     let moves = get_moves_for_round(last_round_hash.clone())?;
+    // Retrieve sanctions made during this round from DHT
+    let sanctions = get_sanctions_for_round(last_round_hash.clone())?;
+    // Retrieve abatements made during this round from DHT
+    let abatements = get_abatements_for_round(last_round_hash.clone())?;
+    // Retrieve trades offered during this round from DHT
+    let trades = get_offered_trades_for_round(last_round_hash.clone())?;
+    // Retrieve quota orders made during this round from DHT
+    let quota_orders = get_filled_quota_orders_for_round(last_round_hash.clone())?;
+    // Retrieve pacts made and signed during this round from DHT
+    let pacts = get_signed_pacts_for_round(last_round.session.clone(), last_round_hash.clone())?;
+    // Retrieve pledges made during this round from DHT
+    let pledges = get_pledges_for_round(last_round_hash.clone())?;
+    // Retrieve proposals and votes made in this game session from DHT
+    let proposals = get_proposals_for_session(last_round.session.clone())?;
+    let votes = get_votes_for_session(last_round.session.clone())?;
+
+    // Eliminated players can't move anymore, so we're only waiting for active ones
+    let players = active_players(&game_session.players, &last_round.state.eliminated_players);
+    let moves: Vec<GameMove> = moves
+        .into_iter()
+        .filter(|m| players.contains(&m.owner))
+        .collect();
+
+    // If players move one after another, their moves have to be in turn
+    let turn_order = last_round
+        .state
+        .game_params
+        .turn_order
+        .map(|turn_order| turn_order_for_round(turn_order, &game_session, &last_round));
 
     // Try to process those moves and see if we have enough to close the round
-    match finalize_moves(moves, game_session.players.len())? {
+    match finalize_moves(moves, players.len(), turn_order.as_deref())? {
         // we get the moves (which are guaranteed to be unique, hence the name),
         // so we can close the round
         Some(unique_moves) => {
-            let mut moves_info: Vec<(ResourceAmount, String, AgentPubKey)> = vec![];
-            for game_move in &unique_moves {
-                moves_info.push((
-                    game_move.resource_amount.clone(),
-                    "playername".into(),
-                    game_move.owner.clone(),
-                ));
-            }
+            // Random draws of the round need the seeds every player has revealed
+            // for it, so we can't close the round until they have, or until the
+            // players who haven't have run out of time
+            let shared_seed = if last_round.state.game_params.needs_shared_seed() {
+                let deadline_passed = match last_move_time(&unique_moves) {
+                    Some(last_move_time) => reveal_deadline_passed(&last_move_time, &sys_time()?),
+                    None => false,
+                };
+                match get_shared_seed(
+                    last_round.session.clone(),
+                    &players,
+                    last_round.round_num,
+                    deadline_passed,
+                )? {
+                    Some(shared_seed) => Some(shared_seed),
+                    None => return Ok(waiting_round_info(&last_round, last_round_hash)),
+                }
+            } else {
+                None
+            };
+            // Auction is resolved with the bids of every player, so we can't close
+            // the round until everyone has revealed their bid
+            let bids = match &last_round.state.game_params.auction {
+                Some(_) => match get_revealed_bids_for_round(last_round_hash.clone(), &players)? {
+                    Some(bids) => bids,
+                    None => return Ok(waiting_round_info(&last_round, last_round_hash)),
+                },
+                None => vec![],
+            };
             info!("all players made their moves: calculating round state");
-            let round_state =
-                calculate_round_state(&last_round, &game_session.game_params, unique_moves);
+            let mut hashed_moves: Vec<(EntryHash, GameMove)> = vec![];
+            for m in &unique_moves {
+                hashed_moves.push((hash_entry(m)?, m.clone()));
+            }
+            let round_state = calculate_round_state(
+                &last_round,
+                &players,
+                &game_session,
+                shared_seed,
+                RoundActions {
+                    moves: hashed_moves,
+                    bids,
+                    sanctions,
+                    abatements,
+                    trades,
+                    quota_orders,
+                    pacts,
+                    pledges,
+                    proposals,
+                    votes,
+                },
+            );
+            signal_eliminations(
+                &game_session,
+                last_round.session.clone(),
+                last_round.round_num + 1,
+                &last_round.state.eliminated_players,
+                &round_state.eliminated_players,
+            )?;
             // Check if we can start the next round
-            if can_start_new_round(&game_session, &last_round, &round_state) {
+            let info = if can_start_new_round(&last_round, &round_state, &game_session.players) {
                 let round_hash = create_new_round(
                     &game_session,
                     &last_round,
                     last_round_element.header_address(),
                     &round_state,
                 )?;
-                return Ok(GameRoundInfo {
-                    current_round_entry_hash: Some(round_hash),
-                    prev_round_entry_hash: Some(last_round_hash),
-                    game_session_hash: None,
-                    resources_left: Some(round_state.resources_left),
-                    resources_taken_round: Some(round_state.resources_taken),
-                    resources_grown_round: Some(round_state.resources_grown),
-                    round_num: last_round.round_num + 1,
-                    next_action: "START_NEXT_ROUND".into(),
-                    moves: moves_info,
-                });
+                closed_round_info(
+                    &round_state,
+                    last_round.round_num + 1,
+                    Some(round_hash),
+                    last_round_hash,
+                    None,
+                    "START_NEXT_ROUND",
+                    &unique_moves,
+                )
             } else {
+                // the final round is committed too, so that the ended GameSession
+                // can point at the state it's scores come from
+                let (final_round, final_round_hash) = commit_next_round(
+                    &last_round,
+                    last_round_element.header_address(),
+                    &round_state,
+                )?;
                 let game_session_entry_hash = end_game(
                     &game_session,
                     &game_session_element.header_address(),
-                    &last_round,
-                    last_round_element
-                        .header()
-                        .entry_hash()
-                        .expect("Expected to get entry from GameRound element"),
+                    &final_round,
+                    &final_round_hash,
                     &round_state,
                 )?;
-                return Ok(GameRoundInfo {
-                    current_round_entry_hash: None,
-                    prev_round_entry_hash: Some(last_round_hash),
-                    game_session_hash: Some(game_session_entry_hash),
-                    resources_left: Some(round_state.resources_left),
-                    resources_taken_round: Some(round_state.resources_taken),
-                    resources_grown_round: Some(round_state.resources_grown),
-                    round_num: last_round.round_num + 1,
-                    next_action: "SHOW_GAME_RESULTS".into(),
-                    moves: moves_info,
-                });
-            }
+                closed_round_info(
+                    &round_state,
+                    last_round.round_num + 1,
+                    None,
+                    last_round_hash,
+                    Some(game_session_entry_hash),
+                    "SHOW_GAME_RESULTS",
+                    &unique_moves,
+                )
+            };
+            // Players only get to see what the monitoring params of the round allow them to
+            let monitoring = &last_round.state.game_params.monitoring;
+            signal_round_observations(
+                &game_session,
+                last_round.session.clone(),
+                &info,
+                monitoring,
+                &round_state.shared_seed,
+            )?;
+            let me = agent_info()?.agent_latest_pubkey;
+            Ok(observe_round_info(
+                info,
+                monitoring,
+                &round_state.shared_seed,
+                &me,
+            ))
         }
         // There aren't enough moves yet, so we get nothing and wait
+        None => Ok(waiting_round_info(&last_round, last_round_hash)),
+    }
+}
+
+/// Creates the output for the UI with the results of the round that was closed
+/// with round_state
+pub fn closed_round_info(
+    round_state: &RoundState,
+    round_num: u32,
+    current_round_entry_hash: Option<EntryHash>,
+    prev_round_entry_hash: EntryHash,
+    game_session_hash: Option<EntryHash>,
+    next_action: &str,
+    moves: &[GameMove],
+) -> GameRoundInfo {
+    GameRoundInfo {
+        current_round_entry_hash,
+        prev_round_entry_hash: Some(prev_round_entry_hash),
+        game_session_hash,
+        resources_left: Some(round_state.resources_left),
+        resources_taken_round: Some(round_state.resources_taken),
+        resources_grown_round: Some(round_state.resources_grown),
+        resources_taxed_round: Some(round_state.resources_taxed),
+        resources_redistributed_round: Some(round_state.resources_redistributed),
+        resources_reinvested_round: Some(round_state.resources_reinvested),
+        round_num,
+        next_action: next_action.into(),
+        moves: moves
+            .iter()
+            .map(|m| (m.resource_amount, "playername".into(), m.owner.clone()))
+            .collect(),
+        team_leaderboard: team_leaderboard(&round_state.team_stats),
+        patch_stocks: round_state.patch_stocks.clone(),
+        pact_violations: round_state.pact_violations.clone(),
+    }
+}
+
+/// Creates the output for the UI when the round can't be closed yet
+fn waiting_round_info(last_round: &GameRound, last_round_hash: EntryHash) -> GameRoundInfo {
+    GameRoundInfo {
+        current_round_entry_hash: None,
+        prev_round_entry_hash: Some(last_round_hash),
+        game_session_hash: Some(last_round.session.clone()),
+        resources_left: None,
+        resources_taken_round: None,
+        resources_grown_round: None,
+        resources_taxed_round: None,
+        resources_redistributed_round: None,
+        resources_reinvested_round: None,
+        round_num: last_round.round_num,
+        next_action: "WAITING".into(),
+        moves: vec![],
+        team_leaderboard: vec![],
+        patch_stocks: vec![],
+        pact_violations: vec![],
+    }
+}
+
+/// Time of the latest of the moves. None if there are no moves
+fn last_move_time(moves: &[GameMove]) -> Option<Timestamp> {
+    moves.iter().map(|m| m.timestamp).max()
+}
+
+/// Checks if the round at round_hash was already closed, which happens when
+/// someone commits the next round as an update of it
+pub fn round_has_successor(round_hash: EntryHash) -> ExternResult<bool> {
+    match get_details(round_hash, GetOptions::latest())? {
+        Some(Details::Entry(entry_details)) => Ok(!entry_details.updates.is_empty()),
+        _ => Ok(false),
+    }
+}
+
+/// Makes sure that the round at round_hash is still open, so that the agent
+/// executing this fn can still act in it. `action` says what they're trying to do.
+/// NOTE: this check depends on what the agent can see on DHT, so zome fns do it
+/// before committing an entry instead of it's validation. An entry made for the
+/// round after it was closed anyway is simply not counted, since the next round
+/// state refers to everything it counts by hash
+pub fn check_round_is_open(round_hash: EntryHash, action: &str) -> ExternResult<()> {
+    if round_has_successor(round_hash)? {
+        return Err(WasmError::Guest(format!(
+            "Can't {}: it's round is already closed",
+            action
+        )));
+    }
+    Ok(())
+}
+
+/// Loads everything players did during the round at round_hash by the hashes
+/// stored in the state the round was closed with. Fails if some of the stored
+/// hashes point to something that doesn't belong to the round.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+fn must_get_round_actions(
+    prev_state: &RoundState,
+    state: &RoundState,
+    round_hash: &EntryHash,
+    session_hash: &EntryHash,
+    players: &[AgentPubKey],
+) -> ExternResult<RoundActions> {
+    let moves = match must_get_counted_moves(&state.moves, round_hash, players)? {
+        Some(moves) => state.moves.iter().cloned().zip(moves).collect(),
+        None => return Err(WasmError::Guest(String::from(
+            "GameRound has to count exactly one move of every active player made during the round",
+        ))),
+    };
+    let bids =
+        match &prev_state.game_params.auction {
+            Some(_) => match verify_auction_bids(&state.auction_bids, round_hash, players)? {
+                Some(bids) => bids,
+                None => return Err(WasmError::Guest(String::from(
+                    "GameRound has to resolve the auction with one revealed bid of every player",
+                ))),
+            },
+            None => vec![],
+        };
+    let sanctions = match must_get_sanctions(&state.sanctions, round_hash)? {
+        Some(sanctions) => sanctions,
+        None => {
+            return Err(WasmError::Guest(String::from(
+                "GameRound can only count the sanctions made during the round",
+            )))
+        }
+    };
+    let abatements = match must_get_abatements(&state.abatements, round_hash)? {
+        Some(abatements) => abatements,
+        None => {
+            return Err(WasmError::Guest(String::from(
+                "GameRound can only count the abatements made during the round",
+            )))
+        }
+    };
+    let trades = match must_get_settled_trades(&state.trades, round_hash)? {
+        Some(trades) => trades,
+        None => {
+            return Err(WasmError::Guest(String::from(
+                "GameRound can only settle the trades offered and accepted during the round",
+            )))
+        }
+    };
+    let quota_orders = match must_get_settled_quota_orders(&state.quota_trades, round_hash)? {
+        Some(quota_orders) => quota_orders,
+        None => {
+            return Err(WasmError::Guest(String::from(
+                "GameRound can only settle the quota orders made and filled during the round",
+            )))
+        }
+    };
+    let pacts =
+        match must_get_signed_pacts(&state.active_pacts, &prev_state.active_pacts, round_hash)? {
+            Some(pacts) => pacts,
+            None => {
+                return Err(WasmError::Guest(String::from(
+                    "GameRound can only make the pacts binding that were signed during the round",
+                )))
+            }
+        };
+    let pledges = match must_get_pledges(&state.pledge_hashes, round_hash)? {
+        Some(pledges) => pledges,
         None => {
-            return Ok(GameRoundInfo {
-                current_round_entry_hash: None,
-                prev_round_entry_hash: Some(last_round_hash),
-                game_session_hash: Some(last_round.session.clone()),
-                resources_left: None,
-                resources_taken_round: None,
-                resources_grown_round: None,
-                round_num: last_round.round_num,
-                next_action: "WAITING".into(),
-                moves: vec![],
-            });
+            return Err(WasmError::Guest(String::from(
+                "GameRound can only count the pledges made during the round",
+            )))
         }
     };
+    let (proposals, votes) = match must_get_accepted_proposals(
+        session_hash,
+        &prev_state.accepted_proposals,
+        &state.accepted_proposals,
+        &state.accepted_votes,
+    )? {
+        Some(proposals_and_votes) => proposals_and_votes,
+        None => {
+            return Err(WasmError::Guest(String::from(
+                "GameRound can only accept the proposals made in it's GameSession, with the votes on them",
+            )))
+        }
+    };
+    Ok(RoundActions {
+        moves,
+        bids,
+        sanctions,
+        abatements,
+        trades,
+        quota_orders,
+        pacts,
+        pledges,
+        proposals,
+        votes,
+    })
 }
 
 // TODO: as a homework, try to implement validation for creating a game round
@@ -267,13 +1102,15 @@ pub fn validate_update_entry_game_round(
             "Trying to validate an entry that's not a GameRound".into(),
         ))?;
 
-    let game_session = must_get_entry_struct::<GameSession>(game_round.session)?;
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session.clone())?;
     if game_round.round_num > game_session.game_params.num_rounds {
         return Ok(ValidateCallbackResult::Invalid(format!(
             "Can't update GameRound number {} because GameSession only has {} rounds",
             game_round.round_num, game_session.game_params.num_rounds
         )));
     }
+    // NOTE: try_to_close_round checks that the facilitator hasn't paused or ended
+    // the GameSession, since it depends on what the agent can see on DHT
 
     let update_header = data.element.header();
 
@@ -284,6 +1121,98 @@ pub fn validate_update_entry_game_round(
             if (prev_entry.round_num + 1) != game_round.round_num {
                 return Ok(ValidateCallbackResult::Invalid(format!("Can't update GameRound entry to have round num {}: previous GameRound has num {}", game_round.round_num, prev_entry.round_num)));
             }
+            // the round that ended the game is the last one in the chain. Round zero
+            // only holds the initial state, so it can't end the game
+            if prev_entry.round_num > 0
+                && game_ends_with_round(
+                    prev_entry.round_num,
+                    &prev_entry.state,
+                    &game_session.players,
+                )
+            {
+                return Ok(ValidateCallbackResult::Invalid(format!(
+                    "Can't update GameRound entry: the game has ended with round {}",
+                    prev_entry.round_num
+                )));
+            }
+            // random draws are made with the shared seed, so the round has to have it
+            // if the game has random events or an unknown horizon
+            let params = &prev_entry.state.game_params;
+            if regeneration_change_for_round(
+                params,
+                &game_round.state.shared_seed,
+                game_round.round_num,
+            )
+            .is_none()
+            {
+                return Ok(ValidateCallbackResult::Invalid(String::from(
+                    "GameRound has to have a shared seed to draw random events from",
+                )));
+            }
+            if game_ends_for_round(
+                &game_round.state.game_params,
+                &game_round.state.shared_seed,
+                game_round.round_num,
+            )
+            .is_none()
+            {
+                return Ok(ValidateCallbackResult::Invalid(String::from(
+                    "GameRound has to have a shared seed to draw the end of the game from",
+                )));
+            }
+            // everything players did during the round is stored in the new state by
+            // hash, so anyone can load the same actions the round was closed with
+            let round_hash = &update_data.original_entry_address;
+            // players who were still in the game during the round
+            let players =
+                active_players(&game_session.players, &prev_entry.state.eliminated_players);
+            let actions = match must_get_round_actions(
+                &prev_entry.state,
+                &game_round.state,
+                round_hash,
+                &game_round.session,
+                &players,
+            ) {
+                Ok(actions) => actions,
+                Err(WasmError::Guest(e)) => return Ok(ValidateCallbackResult::Invalid(e)),
+                Err(e) => return Err(e),
+            };
+            // shared seed of the round has to be made of the seeds every player has
+            // revealed for it, unless some of them didn't reveal it in time
+            if let Some(shared_seed) = &game_round.state.shared_seed {
+                let counted_moves: Vec<GameMove> =
+                    actions.moves.iter().map(|(_, m)| m.clone()).collect();
+                let deadline_passed = match last_move_time(&counted_moves) {
+                    Some(last_move_time) => {
+                        reveal_deadline_passed(&last_move_time, &update_data.timestamp)
+                    }
+                    None => false,
+                };
+                if !verify_shared_seed(
+                    shared_seed,
+                    &game_round.session,
+                    &players,
+                    prev_entry.round_num,
+                    deadline_passed,
+                )? {
+                    return Ok(ValidateCallbackResult::Invalid(String::from(
+                        "Shared seed has to be made of the seeds revealed for the round by every player, or by those who revealed them before the deadline",
+                    )));
+                }
+            }
+            // and calculate the state of the round again: it has to be exactly the same
+            let round_state = calculate_round_state(
+                &prev_entry,
+                &players,
+                &game_session,
+                game_round.state.shared_seed.clone(),
+                actions,
+            );
+            if round_state != game_round.state {
+                return Ok(ValidateCallbackResult::Invalid(String::from(
+                    "GameRound state has to be the one calculated from the previous round and the actions counted in it",
+                )));
+            }
         }
         _ => {
             return Ok(ValidateCallbackResult::Invalid(String::from(
@@ -294,3 +1223,127 @@ pub fn validate_update_entry_game_round(
 
     Ok(ValidateCallbackResult::Valid)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_auction::AuctionParams;
+    use crate::game_seed::UnknownHorizonParams;
+    use crate::game_taxation::Redistribution;
+    use crate::test_utils::{agent, bid, game_move, game_session, hash, shared_seed, stats};
+
+    fn players() -> Vec<AgentPubKey> {
+        vec![agent(1), agent(2)]
+    }
+
+    fn round_state() -> RoundState {
+        RoundState::initial(&GameParams::default(), &players())
+    }
+
+    #[test]
+    fn game_goes_on_while_there_are_rounds_resources_and_players_left() {
+        assert!(!game_ends_with_round(1, &round_state(), &players()));
+        assert!(!game_ends_with_round(2, &round_state(), &players()));
+        assert!(game_ends_with_round(3, &round_state(), &players()));
+    }
+
+    #[test]
+    fn game_ends_when_resources_are_depleted() {
+        let mut state = round_state();
+        state.resources_left = 0;
+        assert!(game_ends_with_round(1, &state, &players()));
+    }
+
+    #[test]
+    fn game_ends_when_every_player_is_eliminated() {
+        let mut state = round_state();
+        state.eliminated_players = vec![agent(1)];
+        assert!(!game_ends_with_round(1, &state, &players()));
+        state.eliminated_players = players();
+        assert!(game_ends_with_round(1, &state, &players()));
+    }
+
+    #[test]
+    fn game_with_unknown_horizon_ends_by_the_draw() {
+        let mut state = round_state();
+        state.game_params.num_rounds = 10;
+        state.shared_seed = Some(shared_seed(1));
+        state.game_params.unknown_horizon = Some(UnknownHorizonParams {
+            min_rounds: 2,
+            end_probability: 1.0,
+        });
+        assert!(!game_ends_with_round(1, &state, &players()));
+        assert!(game_ends_with_round(2, &state, &players()));
+        state.game_params.unknown_horizon = Some(UnknownHorizonParams {
+            min_rounds: 2,
+            end_probability: 0.0,
+        });
+        assert!(!game_ends_with_round(5, &state, &players()));
+    }
+
+    fn auction_actions(
+        bids: Vec<(EntryHash, BidReveal)>,
+        moves: Vec<(EntryHash, GameMove)>,
+    ) -> RoundActions {
+        RoundActions {
+            moves,
+            bids,
+            sanctions: vec![],
+            abatements: vec![],
+            trades: vec![],
+            quota_orders: vec![],
+            pacts: vec![],
+            pledges: vec![],
+            proposals: vec![],
+            votes: vec![],
+        }
+    }
+
+    #[test]
+    fn auction_can_be_played_from_the_first_round() {
+        let params = GameParams {
+            auction: Some(AuctionParams {
+                rights_per_round: 10,
+                reserve_price: 0,
+                revenue: Redistribution::Reinvest,
+            }),
+            ..GameParams::default()
+        };
+        let session = game_session(params.clone(), &[1, 2]);
+        let round_zero = GameRound::new(0, hash(0), RoundState::initial(&params, &players()));
+
+        // nobody has anything yet, but player 1 can pay for the rights
+        // with what they take, and player 2 gets the rest for free
+        let state = calculate_round_state(
+            &round_zero,
+            &players(),
+            &session,
+            None,
+            auction_actions(
+                vec![bid(10, 1, 6, 1), bid(11, 2, 6, 0)],
+                vec![(hash(20), game_move(1, 8)), (hash(21), game_move(2, 4))],
+            ),
+        );
+        assert_eq!(state.extraction_rights, stats(&[(1, 6), (2, 4)]));
+        assert_eq!(state.auction_payments, stats(&[(1, 6), (2, 0)]));
+        assert_eq!(state.resources_taken, 10);
+        assert_eq!(state.auction_reinvested, 6);
+        assert_eq!(state.player_stats, stats(&[(1, 0), (2, 4)]));
+
+        // in the next round player 2 pays with what they've got so far
+        let round_one = GameRound::new(1, hash(0), state);
+        let state = calculate_round_state(
+            &round_one,
+            &players(),
+            &session,
+            None,
+            auction_actions(
+                vec![bid(12, 1, 10, 0), bid(13, 2, 5, 1)],
+                vec![(hash(22), game_move(1, 5)), (hash(23), game_move(2, 5))],
+            ),
+        );
+        assert_eq!(state.extraction_rights, stats(&[(1, 5), (2, 5)]));
+        assert_eq!(state.auction_payments, stats(&[(1, 0), (2, 5)]));
+        assert_eq!(state.player_stats, stats(&[(1, 5), (2, 4)]));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::{
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+
+pub const GAME_SANCTION_LINK_TAG: &str = "GAME_SANCTION";
+
+/// Sanction is a costly punishment: the owner spends `cost` of their own resources
+/// when the round is closed, to make the target lose `cost * sanction_factor`
+/// of their resources when the next round is closed
+#[hdk_entry(id = "game_sanction", visibility = "public")]
+#[derive(Clone, PartialEq)]
+pub struct GameSanction {
+    pub owner: AgentPubKey,
+    pub target: AgentPubKey,
+    pub round_hash: EntryHash,
+    pub cost: ResourceAmount,
+}
+
+/// Outcome of the sanctions of a single round
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SanctionResult {
+    // resources every punisher spent on the sanctions made during the round
+    pub costs: PlayerStats,
+    // resources every target lost because of the sanctions made in the previous round
+    pub penalties: PlayerStats,
+    // resources every target is going to lose in the next round
+    pub penalties_due: PlayerStats,
+    // hashes of the sanctions counted in the round, sorted
+    pub sanctions: Vec<EntryHash>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameSanctionInput {
+    pub target: AgentPubKey,
+    pub cost: ResourceAmount,
+    pub round_hash: EntryHash,
+}
+
+/// Create a new sanction entry, and link it from its round
+pub fn new_sanction(
+    target: AgentPubKey,
+    cost: ResourceAmount,
+    round_hash: EntryHash,
+) -> ExternResult<HeaderHash> {
+    check_round_is_open(round_hash.clone(), "make a GameSanction")?;
+    // Same as with moves, the agent executing this code is the one who punishes
+    let agent_info = agent_info()?;
+
+    let game_sanction = GameSanction {
+        owner: agent_info.agent_latest_pubkey,
+        target,
+        round_hash: round_hash.clone(),
+        cost,
+    };
+    create_entry(game_sanction.clone())?;
+    let sanction_entry_hash = hash_entry(game_sanction)?;
+
+    // Link from the round entry to the sanction so that whoever closes the round can find it
+    let create_link_header_hash = create_link(
+        round_hash,
+        sanction_entry_hash,
+        LinkTag::new(String::from(GAME_SANCTION_LINK_TAG)),
+    )?;
+
+    Ok(create_link_header_hash)
+}
+
+/// Get all sanctions attached to the round that we have so far, together with
+/// their entry hashes
+pub fn get_sanctions_for_round(
+    last_round_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, GameSanction)>> {
+    let links = get_links(
+        last_round_hash,
+        Some(LinkTag::new(String::from(GAME_SANCTION_LINK_TAG))),
+    )?;
+    let mut sanctions: Vec<(EntryHash, GameSanction)> = vec![];
+    for link in links.into_inner() {
+        let game_sanction: GameSanction =
+            try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        sanctions.push((link.target, game_sanction));
+    }
+    Ok(sanctions)
+}
+
+/// Loads the sanctions counted in the round by their hashes, and checks that all
+/// of them were made during the round at round_hash.
+/// Returns None if some of them were made in another round.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_sanctions(
+    sanction_hashes: &[EntryHash],
+    round_hash: &EntryHash,
+) -> ExternResult<Option<Vec<(EntryHash, GameSanction)>>> {
+    let mut sanctions: Vec<(EntryHash, GameSanction)> = vec![];
+    for sanction_hash in sanction_hashes {
+        let game_sanction = must_get_entry_struct::<GameSanction>(sanction_hash.clone())?;
+        if &game_sanction.round_hash != round_hash {
+            return Ok(None);
+        }
+        sanctions.push((sanction_hash.clone(), game_sanction));
+    }
+    Ok(Some(sanctions))
+}
+
+/// Takes the penalties of the sanctions made in the previous round from their
+/// targets, and charges the punishers for the sanctions made during this round,
+/// whose penalties are only due in the next round.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent. That's why
+/// sanctions are sorted by their hashes first, and the same sanction is counted
+/// only once. Validation can only check every sanction against the punisher's
+/// balance on it's own, so here we skip the sanctions that the punisher can't
+/// afford anymore. If players voted to disable sanctions, only the penalties
+/// that are already due are taken.
+pub fn apply_sanctions(
+    player_stats: &mut PlayerStats,
+    sanction_factor: Option<ResourceAmount>,
+    penalties_due: &PlayerStats,
+    mut sanctions: Vec<(EntryHash, GameSanction)>,
+) -> SanctionResult {
+    let mut result = SanctionResult {
+        penalties: apply_sanction_penalties(player_stats, penalties_due),
+        ..SanctionResult::default()
+    };
+    let sanction_factor = match sanction_factor {
+        Some(sanction_factor) => sanction_factor,
+        None => return result,
+    };
+    sanctions.sort_by(|a, b| a.0.cmp(&b.0));
+    sanctions.dedup_by(|a, b| a.0 == b.0);
+
+    for (sanction_hash, s) in sanctions {
+        let balance = *player_stats.get(&s.owner).unwrap_or(&0);
+        if s.cost > balance {
+            info!("Skipping sanction: {:?} can't afford it anymore", s.owner);
+            continue;
+        }
+        player_stats.insert(s.owner.clone(), balance - s.cost);
+        *result.costs.entry(s.owner).or_insert(0) += s.cost;
+        // a large cost times a large factor would overflow, and no target
+        // can lose more than they have anyway
+        let due = result.penalties_due.entry(s.target).or_insert(0);
+        *due = due.saturating_add(s.cost.saturating_mul(sanction_factor));
+        result.sanctions.push(sanction_hash);
+    }
+    result
+}
+
+/// Takes the penalties that are due from their targets, and returns the resources
+/// every target lost. Targets can't lose more resources than they have.
+pub fn apply_sanction_penalties(
+    player_stats: &mut PlayerStats,
+    penalties_due: &PlayerStats,
+) -> PlayerStats {
+    let mut penalties = PlayerStats::new();
+    for (target, due) in penalties_due {
+        let balance = std::cmp::max(*player_stats.get(target).unwrap_or(&0), 0);
+        let penalty = std::cmp::min(*due, balance);
+        *player_stats.entry(target.clone()).or_insert(0) -= penalty;
+        penalties.insert(target.clone(), penalty);
+    }
+    penalties
+}
+
+/// Validates creation of GameSanction entries
+pub fn validate_create_entry_game_sanction(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let game_sanction: GameSanction = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &game_sanction.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "GameSanction can only be created by it's owner",
+        )));
+    }
+
+    if game_sanction.cost <= 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "GameSanction has to have cost > 0, but it has {}",
+            game_sanction.cost
+        )));
+    }
+
+    if game_sanction.owner == game_sanction.target {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Players can't sanction themselves",
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(game_sanction.round_hash.clone())?;
+    let game_session = must_get_entry_struct::<GameSession>(game_round.session)?;
+
+    // players might have voted to disable sanctions, so we're checking
+    // the params that are in effect for this round
+    if game_round.state.game_params.sanction_factor.is_none() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Sanctions are disabled for this GameSession",
+        )));
+    }
+
+    if !game_session.players.contains(&game_sanction.owner)
+        || !game_session.players.contains(&game_sanction.target)
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from("Can't make a GameSanction for this GameSession because both punisher and target have to be GameSession players")));
+    }
+
+    // punisher can only spend resources they already have
+    let balance = *game_round
+        .state
+        .player_stats
+        .get(&game_sanction.owner)
+        .unwrap_or(&0);
+    if game_sanction.cost > balance {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "GameSanction costs {} but the punisher only has {}",
+            game_sanction.cost, balance
+        )));
+    }
+
+    // NOTE: new_sanction checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of GameSanction entries
+pub fn validate_update_entry_game_sanction(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update GameSanction entry",
+    )))
+}
+
+/// Validates delete of GameSanction entries
+pub fn validate_delete_entry_game_sanction(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete GameSanction entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{hash, sanction, stats};
+
+    #[test]
+    fn punisher_pays_now_and_target_loses_cost_times_factor_next_round() {
+        let mut player_stats = stats(&[(1, 10), (2, 20)]);
+        let result = apply_sanctions(
+            &mut player_stats,
+            Some(3),
+            &PlayerStats::new(),
+            vec![sanction(10, 1, 2, 2)],
+        );
+        assert_eq!(player_stats, stats(&[(1, 8), (2, 20)]));
+        assert_eq!(result.costs, stats(&[(1, 2)]));
+        assert!(result.penalties.is_empty());
+        assert_eq!(result.penalties_due, stats(&[(2, 6)]));
+        assert_eq!(result.sanctions, vec![hash(10)]);
+
+        let next_result =
+            apply_sanctions(&mut player_stats, Some(3), &result.penalties_due, vec![]);
+        assert_eq!(player_stats, stats(&[(1, 8), (2, 14)]));
+        assert_eq!(next_result.penalties, stats(&[(2, 6)]));
+        assert!(next_result.penalties_due.is_empty());
+    }
+
+    #[test]
+    fn target_cant_lose_more_than_they_have() {
+        let mut player_stats = stats(&[(1, 10), (2, 4)]);
+        let penalties = apply_sanction_penalties(&mut player_stats, &stats(&[(2, 6)]));
+        assert_eq!(player_stats, stats(&[(1, 10), (2, 0)]));
+        assert_eq!(penalties, stats(&[(2, 4)]));
+    }
+
+    #[test]
+    fn unaffordable_and_duplicate_sanctions_are_skipped() {
+        let mut player_stats = stats(&[(1, 3), (2, 20)]);
+        let sanctions = vec![
+            sanction(10, 1, 2, 2),
+            sanction(10, 1, 2, 2),
+            sanction(11, 1, 2, 5),
+        ];
+        let result = apply_sanctions(&mut player_stats, Some(1), &PlayerStats::new(), sanctions);
+        assert_eq!(result.costs, stats(&[(1, 2)]));
+        assert_eq!(result.sanctions, vec![hash(10)]);
+        assert_eq!(player_stats, stats(&[(1, 1), (2, 20)]));
+    }
+
+    #[test]
+    fn due_penalties_are_taken_even_when_sanctions_are_disabled() {
+        let mut player_stats = stats(&[(1, 10), (2, 20)]);
+        let result = apply_sanctions(
+            &mut player_stats,
+            None,
+            &stats(&[(2, 6)]),
+            vec![sanction(10, 1, 2, 2)],
+        );
+        assert_eq!(player_stats, stats(&[(1, 10), (2, 14)]));
+        assert!(result.costs.is_empty());
+        assert!(result.sanctions.is_empty());
+    }
+
+    #[test]
+    fn sanctions_give_the_same_result_in_any_order() {
+        let sanctions = vec![
+            sanction(10, 1, 2, 3),
+            sanction(11, 2, 1, 4),
+            sanction(12, 1, 2, 1),
+        ];
+        let mut reversed = sanctions.clone();
+        reversed.reverse();
+        let mut player_stats = stats(&[(1, 4), (2, 5)]);
+        let mut player_stats_reversed = player_stats.clone();
+        let due = stats(&[(1, 1)]);
+        let result = apply_sanctions(&mut player_stats, Some(2), &due, sanctions);
+        let result_reversed = apply_sanctions(&mut player_stats_reversed, Some(2), &due, reversed);
+        assert_eq!(player_stats, player_stats_reversed);
+        assert_eq!(result, result_reversed);
+    }
+
+    #[test]
+    fn huge_penalties_saturate_instead_of_overflowing() {
+        let big = ResourceAmount::MAX / 2;
+        let mut player_stats = stats(&[(1, ResourceAmount::MAX), (2, 20)]);
+        let sanctions = vec![sanction(10, 1, 2, big), sanction(11, 1, 2, 1)];
+        let result = apply_sanctions(&mut player_stats, Some(3), &PlayerStats::new(), sanctions);
+        assert_eq!(result.penalties_due, stats(&[(2, ResourceAmount::MAX)]));
+    }
+}
@@ -0,0 +1,683 @@
+use crate::{
+    game_move::get_moves_for_round,
+    game_round::GameRound,
+    game_session::GameSession,
+    game_subsistence::active_players,
+    utils::{
+        earlier_entries_in_package, must_get_entry_struct, try_from_element, try_get_and_convert,
+    },
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const SEED_COMMITMENT_LINK_TAG: &str = "SEED_COMMITMENT";
+// size of every player's seed in bytes
+pub const SEED_SIZE: usize = 32;
+// every random draw we make from the shared seed has it's own purpose,
+// so that different draws for the same round aren't correlated
+pub const REGENERATION_EVENT_DRAW: u8 = 0;
+pub const GAME_END_DRAW: u8 = 1;
+pub const MONITORING_NOISE_DRAW: u8 = 2;
+// seconds players have to reveal their seeds after the last move of the round.
+// Once it's over, the round can be closed with the seeds of those who did
+pub const SEED_REVEAL_TIMEOUT: i64 = 300;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct RandomEventsParams {
+    // max change of the regeneration caused by a random event, e.g. 0.2 means
+    // that every round resources can regrow up to 20% less or more than usual
+    pub max_regeneration_change: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct UnknownHorizonParams {
+    // number of rounds that are always played
+    pub min_rounds: u32,
+    // chance that the game ends after every round that follows, from 0.0 to 1.0
+    pub end_probability: f32,
+}
+
+/// Makes sure that the random events make sense for the game
+pub fn check_random_events(params: &RandomEventsParams) -> ExternResult<()> {
+    if !(0.0..=1.0).contains(&params.max_regeneration_change) {
+        return Err(WasmError::Guest(format!(
+            "Max regeneration change has to be between 0.0 and 1.0, but it is {}",
+            params.max_regeneration_change
+        )));
+    }
+    Ok(())
+}
+
+/// Makes sure that the unknown horizon makes sense for the game with num_rounds rounds
+pub fn check_unknown_horizon(params: &UnknownHorizonParams, num_rounds: u32) -> ExternResult<()> {
+    if !(0.0..=1.0).contains(&params.end_probability) {
+        return Err(WasmError::Guest(format!(
+            "Probability of the game end has to be between 0.0 and 1.0, but it is {}",
+            params.end_probability
+        )));
+    }
+    if params.min_rounds > num_rounds {
+        return Err(WasmError::Guest(format!(
+            "Min number of rounds {} can't be greater than the number of rounds {}",
+            params.min_rounds, num_rounds
+        )));
+    }
+    Ok(())
+}
+
+/// Player's seeds for every round, which stay private on their source chain
+/// until they reveal them
+#[hdk_entry(id = "seed_secret", visibility = "private")]
+#[derive(Clone)]
+pub struct SeedSecret {
+    pub session: EntryHash,
+    pub seeds: Vec<Vec<u8>>,
+}
+
+/// Commitment to the player's seeds: it only contains the hashes of the SeedReveal
+/// entries that the player would publish later, one for every round, so nobody
+/// can learn the seeds from it.
+/// Validators get the author's earlier commitments with every commitment, so they
+/// can check that players only commit to one seed per GameSession
+#[hdk_entry(
+    id = "seed_commitment",
+    visibility = "public",
+    required_validation_type = "sub_chain"
+)]
+#[derive(Clone)]
+pub struct SeedCommitment {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // hash of the reveal of the seed for every round, starting from the round zero
+    pub reveal_hashes: Vec<EntryHash>,
+}
+
+/// Player's revealed seed for the round. Since it's entry hash was committed in
+/// advance, the player can't change the seed after seeing the seeds of others
+#[hdk_entry(id = "seed_reveal", visibility = "public")]
+#[derive(Clone)]
+pub struct SeedReveal {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    pub round_num: u32,
+    pub seed: Vec<u8>,
+}
+
+/// Seed that all the players agreed on for a round, together with the hashes of
+/// the commitments to the seeds it was made from
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SharedSeed {
+    pub commitments: Vec<EntryHash>,
+    pub seed: Vec<u8>,
+}
+
+/// Generates a new seed for every round of the game for the agent executing this fn,
+/// keeps them private and publishes a commitment to them
+pub fn commit_seed(session_hash: EntryHash) -> ExternResult<EntryHash> {
+    let owner = agent_info()?.agent_latest_pubkey;
+    let game_session: GameSession =
+        try_get_and_convert(session_hash.clone(), GetOptions::latest())?;
+    let mut seeds: Vec<Vec<u8>> = vec![];
+    let mut reveal_hashes: Vec<EntryHash> = vec![];
+    for round_num in 0..game_session.game_params.num_rounds {
+        let seed = random_bytes(SEED_SIZE as u32)?.to_vec();
+        let seed_reveal = SeedReveal {
+            owner: owner.clone(),
+            session: session_hash.clone(),
+            round_num,
+            seed: seed.clone(),
+        };
+        // NOTE: hash_entry doesn't write anything to DHT, so the reveal stays secret
+        reveal_hashes.push(hash_entry(&seed_reveal)?);
+        seeds.push(seed);
+    }
+    // we would need the seeds later to reveal them
+    create_entry(&SeedSecret {
+        session: session_hash.clone(),
+        seeds,
+    })?;
+
+    let seed_commitment = SeedCommitment {
+        owner,
+        session: session_hash.clone(),
+        reveal_hashes,
+    };
+    create_entry(&seed_commitment)?;
+    let seed_commitment_entry_hash = hash_entry(&seed_commitment)?;
+
+    // Link from the game session so that every player can discover the commitment
+    create_link(
+        session_hash,
+        seed_commitment_entry_hash.clone(),
+        LinkTag::new(String::from(SEED_COMMITMENT_LINK_TAG)),
+    )?;
+
+    Ok(seed_commitment_entry_hash)
+}
+
+/// Publishes the seed for the round at round_hash the agent executing this fn
+/// has committed to. There's no need to link the reveal from anywhere: it's hash
+/// is stored in the commitment already
+pub fn reveal_seed(round_hash: EntryHash) -> ExternResult<EntryHash> {
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let session_hash = game_round.session.clone();
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(SeedSecret)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Private,
+        )));
+    let mut seeds: Option<Vec<Vec<u8>>> = None;
+    for el in query(filter)? {
+        let seed_secret: SeedSecret = try_from_element(el)?;
+        if seed_secret.session == session_hash {
+            seeds = Some(seed_secret.seeds);
+            break;
+        }
+    }
+    let seed = seeds
+        .and_then(|seeds| seeds.get(game_round.round_num as usize).cloned())
+        .ok_or(WasmError::Guest(format!(
+            "Can't reveal the seed: there's no seed committed for round {}",
+            game_round.round_num
+        )))?;
+
+    // seeds can only be revealed after everyone has committed to theirs,
+    // otherwise the last player to commit could choose their seeds knowing the others
+    let game_session: GameSession =
+        try_get_and_convert(session_hash.clone(), GetOptions::latest())?;
+    let mut committed_players: Vec<AgentPubKey> = vec![];
+    for (_, commitment) in get_seed_commitments_for_session(session_hash.clone())? {
+        if game_session.players.contains(&commitment.owner)
+            && !committed_players.contains(&commitment.owner)
+        {
+            committed_players.push(commitment.owner);
+        }
+    }
+    if committed_players.len() < game_session.players.len() {
+        return Err(WasmError::Guest(String::from(
+            "Can't reveal the seed until every player has committed to theirs",
+        )));
+    }
+
+    // the draws of the round are made from the seeds revealed for it, so they're only
+    // revealed once every player has moved: nobody can know the draws before their move
+    let players = active_players(&game_session.players, &game_round.state.eliminated_players);
+    let moves = get_moves_for_round(round_hash)?;
+    if players
+        .iter()
+        .any(|player| !moves.iter().any(|m| &m.owner == player))
+    {
+        return Err(WasmError::Guest(String::from(
+            "Can't reveal the seed for the round until every player has moved in it",
+        )));
+    }
+
+    let seed_reveal = SeedReveal {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: session_hash,
+        round_num: game_round.round_num,
+        seed,
+    };
+    create_entry(&seed_reveal)?;
+    hash_entry(&seed_reveal)
+}
+
+/// Get all seed commitments made in the game session, together with their entry hashes
+pub fn get_seed_commitments_for_session(
+    session_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, SeedCommitment)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(SEED_COMMITMENT_LINK_TAG))),
+    )?;
+    let mut commitments: Vec<(EntryHash, SeedCommitment)> = vec![];
+    for link in links.into_inner() {
+        let commitment: SeedCommitment =
+            try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        commitments.push((link.target, commitment));
+    }
+    Ok(commitments)
+}
+
+/// Tries to build the shared seed for the round round_num from the seeds every
+/// player has revealed for it.
+/// Returns None if some of the players haven't committed to or revealed their seed yet,
+/// unless the reveal deadline has passed: then the seed is built from the seeds
+/// that were revealed, as long as there's at least one.
+/// If a player has several commitments, the one with the smallest entry hash is used.
+pub fn get_shared_seed(
+    session_hash: EntryHash,
+    players: &[AgentPubKey],
+    round_num: u32,
+    deadline_passed: bool,
+) -> ExternResult<Option<SharedSeed>> {
+    let mut commitment_per_player: BTreeMap<AgentPubKey, EntryHash> = BTreeMap::new();
+    for (commitment_hash, commitment) in get_seed_commitments_for_session(session_hash)? {
+        if !players.contains(&commitment.owner) {
+            continue;
+        }
+        let is_first = match commitment_per_player.get(&commitment.owner) {
+            Some(prev_hash) => commitment_hash < *prev_hash,
+            None => true,
+        };
+        if is_first {
+            commitment_per_player.insert(commitment.owner, commitment_hash);
+        }
+    }
+    if commitment_per_player.len() < players.len() && !deadline_passed {
+        info!(
+            "Can't build the shared seed: only {} out of {} players have committed to their seeds",
+            commitment_per_player.len(),
+            players.len()
+        );
+        return Ok(None);
+    }
+
+    let mut commitments: Vec<EntryHash> = vec![];
+    let mut reveals: Vec<SeedReveal> = vec![];
+    for (_, commitment_hash) in commitment_per_player {
+        let commitment: SeedCommitment =
+            try_get_and_convert(commitment_hash.clone(), GetOptions::latest())?;
+        let reveal_hash = match commitment.reveal_hashes.get(round_num as usize) {
+            Some(reveal_hash) => reveal_hash.clone(),
+            None => continue,
+        };
+        match get(reveal_hash, GetOptions::latest())? {
+            Some(element) => reveals.push(try_from_element(element)?),
+            None if deadline_passed => {
+                info!(
+                    "{:?} hasn't revealed their seed in time, so it's left out of the shared seed",
+                    commitment.owner
+                );
+                continue;
+            }
+            None => {
+                info!(
+                    "Can't build the shared seed: {:?} hasn't revealed their seed yet",
+                    commitment.owner
+                );
+                return Ok(None);
+            }
+        }
+        commitments.push(commitment_hash);
+    }
+    if reveals.is_empty() {
+        info!("Can't build the shared seed: nobody has revealed their seed yet");
+        return Ok(None);
+    }
+
+    Ok(Some(SharedSeed {
+        commitments,
+        seed: combine_seeds(&reveals),
+    }))
+}
+
+/// Checks if players have had enough time to reveal their seeds at the time now,
+/// given the time of the last move made in the round. Timestamps of the moves
+/// can't be later than their headers, so the deadline can't be pushed away by
+/// post-dating a move
+pub fn reveal_deadline_passed(last_move_time: &Timestamp, now: &Timestamp) -> bool {
+    match last_move_time.checked_add_signed(&chrono::Duration::seconds(SEED_REVEAL_TIMEOUT)) {
+        Some(deadline) => *now >= deadline,
+        None => false,
+    }
+}
+
+/// Checks that the shared seed was built from the seeds revealed for the round
+/// round_num by every player, or by some of them if the reveal deadline has passed.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn verify_shared_seed(
+    shared_seed: &SharedSeed,
+    session_hash: &EntryHash,
+    players: &[AgentPubKey],
+    round_num: u32,
+    deadline_passed: bool,
+) -> ExternResult<bool> {
+    let all_counted = shared_seed.commitments.len() == players.len();
+    if shared_seed.commitments.is_empty() || !(all_counted || deadline_passed) {
+        return Ok(false);
+    }
+    let mut owners: Vec<AgentPubKey> = vec![];
+    let mut reveals: Vec<SeedReveal> = vec![];
+    for commitment_hash in &shared_seed.commitments {
+        let commitment = must_get_entry_struct::<SeedCommitment>(commitment_hash.clone())?;
+        if &commitment.session != session_hash
+            || !players.contains(&commitment.owner)
+            || owners.contains(&commitment.owner)
+        {
+            return Ok(false);
+        }
+        // reveal hashes are the hashes of the SeedReveal entries, so if we can get
+        // the one for the round, it's guaranteed to be the seed the player has committed to
+        let reveal_hash = match commitment.reveal_hashes.get(round_num as usize) {
+            Some(reveal_hash) => reveal_hash.clone(),
+            None => return Ok(false),
+        };
+        reveals.push(must_get_entry_struct::<SeedReveal>(reveal_hash)?);
+        owners.push(commitment.owner);
+    }
+    Ok(combine_seeds(&reveals) == shared_seed.seed)
+}
+
+/// Combines seeds of all players into one by XOR-ing them, so that
+/// the result is unpredictable as long as at least one seed is random
+fn combine_seeds(reveals: &[SeedReveal]) -> Vec<u8> {
+    let mut seed = vec![0u8; SEED_SIZE];
+    for reveal in reveals {
+        for (byte, reveal_byte) in seed.iter_mut().zip(reveal.seed.iter()) {
+            *byte ^= reveal_byte;
+        }
+    }
+    seed
+}
+
+/// Derives a pseudo-random number in [0.0, 1.0) from the shared seed for the given
+/// round and purpose. It's a pure fn, so every agent (and every validator)
+/// would get the same number for the same input.
+pub fn random_from_seed(shared_seed: &SharedSeed, round_num: u32, purpose: u8) -> f32 {
+    // FNV-1a hash over the seed, round number and purpose...
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let round_bytes = round_num.to_le_bytes();
+    for byte in shared_seed
+        .seed
+        .iter()
+        .chain(round_bytes.iter())
+        .chain([purpose].iter())
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    // ...mixed with the splitmix64 finalizer to spread the bits evenly
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^= hash >> 31;
+    // take the top 24 bits, since that's how many fit into f32 precisely
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Calculates the random change of the regeneration for the given round,
+/// which is anywhere from -max_regeneration_change to +max_regeneration_change
+pub fn random_regeneration_change(
+    params: &RandomEventsParams,
+    shared_seed: &SharedSeed,
+    round_num: u32,
+) -> f32 {
+    let draw = random_from_seed(shared_seed, round_num, REGENERATION_EVENT_DRAW);
+    (draw * 2.0 - 1.0) * params.max_regeneration_change
+}
+
+/// Draws whether the game ends after the given number of rounds were played.
+/// NOTE: the shared seed is made anew for every round from the seeds players reveal
+/// once everyone has moved, so nobody can predict the draw before the round's moves.
+pub fn game_ends_by_chance(
+    params: &UnknownHorizonParams,
+    shared_seed: &SharedSeed,
+    round_num: u32,
+) -> bool {
+    round_num >= params.min_rounds
+        && random_from_seed(shared_seed, round_num, GAME_END_DRAW) < params.end_probability
+}
+
+/// Validates creation of SeedCommitment entries
+pub fn validate_create_entry_seed_commitment(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let commitment: SeedCommitment = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &commitment.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "SeedCommitment can only be created by it's owner",
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(commitment.session.clone())?;
+    if !game_session.game_params.needs_shared_seed() {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "This GameSession doesn't use a shared seed",
+        )));
+    }
+    if !game_session.players.contains(&commitment.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can commit to a seed",
+        )));
+    }
+    if commitment.reveal_hashes.len() != game_session.game_params.num_rounds as usize {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "SeedCommitment has to commit to a seed for each of {} rounds, but it commits to {}",
+            game_session.game_params.num_rounds,
+            commitment.reveal_hashes.len()
+        )));
+    }
+
+    // check that the player doesn't try to commit to several seeds, so that
+    // they could later reveal the one that suits them best. Their earlier
+    // commitments come in the validation package
+    let prev_commitments = match earlier_entries_in_package::<SeedCommitment>(&data)? {
+        Some(prev_commitments) => prev_commitments,
+        None => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "Can't validate SeedCommitment without the earlier commitments of it's owner",
+            )))
+        }
+    };
+    if prev_commitments
+        .iter()
+        .any(|prev_commitment| prev_commitment.session == commitment.session)
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Players can only commit to one seed per GameSession",
+        )));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of SeedCommitment entries
+pub fn validate_update_entry_seed_commitment(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update SeedCommitment entry",
+    )))
+}
+
+/// Validates delete of SeedCommitment entries
+pub fn validate_delete_entry_seed_commitment(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete SeedCommitment entry",
+    )))
+}
+
+/// Validates creation of SeedReveal entries
+pub fn validate_create_entry_seed_reveal(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let reveal: SeedReveal = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &reveal.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "SeedReveal can only be created by it's owner",
+        )));
+    }
+
+    if reveal.seed.len() != SEED_SIZE {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Seed has to be {} bytes long, but it is {}",
+            SEED_SIZE,
+            reveal.seed.len()
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(reveal.session.clone())?;
+    if !game_session.players.contains(&reveal.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can reveal a seed",
+        )));
+    }
+    if reveal.round_num >= game_session.game_params.num_rounds {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "Can't reveal a seed for round {}: GameSession only has {} rounds",
+            reveal.round_num, game_session.game_params.num_rounds
+        )));
+    }
+
+    // NOTE: reveal_seed checks that every player has committed to their seed
+    // and has moved in the round.
+    // A reveal only counts through the commitment that has it's hash, so
+    // validation of the round that uses it checks that it was committed to
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of SeedReveal entries
+pub fn validate_update_entry_seed_reveal(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update SeedReveal entry",
+    )))
+}
+
+/// Validates delete of SeedReveal entries
+pub fn validate_delete_entry_seed_reveal(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete SeedReveal entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, hash, shared_seed, timestamp};
+
+    fn reveal(owner: u8, seed: Vec<u8>) -> SeedReveal {
+        SeedReveal {
+            owner: agent(owner),
+            session: hash(0),
+            round_num: 0,
+            seed,
+        }
+    }
+
+    #[test]
+    fn seeds_of_all_players_are_combined() {
+        let reveals = vec![
+            reveal(1, vec![0b1100; SEED_SIZE]),
+            reveal(2, vec![0b1010; SEED_SIZE]),
+        ];
+        assert_eq!(combine_seeds(&reveals), vec![0b0110; SEED_SIZE]);
+        // the order of the reveals doesn't matter
+        let reversed: Vec<SeedReveal> = reveals.into_iter().rev().collect();
+        assert_eq!(combine_seeds(&reversed), vec![0b0110; SEED_SIZE]);
+    }
+
+    #[test]
+    fn seeds_can_be_left_out_once_players_had_time_to_reveal_them() {
+        let last_move_time = timestamp(100);
+        assert!(!reveal_deadline_passed(&last_move_time, &timestamp(100)));
+        assert!(!reveal_deadline_passed(
+            &last_move_time,
+            &timestamp(99 + SEED_REVEAL_TIMEOUT)
+        ));
+        assert!(reveal_deadline_passed(
+            &last_move_time,
+            &timestamp(100 + SEED_REVEAL_TIMEOUT)
+        ));
+    }
+
+    #[test]
+    fn draws_are_reproducible_and_in_range() {
+        for byte in 0..=255u8 {
+            let seed = shared_seed(byte);
+            for round_num in 0..10 {
+                let draw = random_from_seed(&seed, round_num, REGENERATION_EVENT_DRAW);
+                assert!((0.0..1.0).contains(&draw));
+                assert_eq!(
+                    draw,
+                    random_from_seed(&seed, round_num, REGENERATION_EVENT_DRAW)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn draws_differ_between_rounds_and_purposes() {
+        let seed = shared_seed(42);
+        let draw = random_from_seed(&seed, 1, REGENERATION_EVENT_DRAW);
+        assert_ne!(draw, random_from_seed(&seed, 2, REGENERATION_EVENT_DRAW));
+        assert_ne!(draw, random_from_seed(&seed, 1, GAME_END_DRAW));
+    }
+
+    #[test]
+    fn regeneration_change_stays_within_the_max_change() {
+        let params = RandomEventsParams {
+            max_regeneration_change: 0.2,
+        };
+        let mut changes: Vec<f32> = vec![];
+        for byte in 0..=255u8 {
+            let change = random_regeneration_change(&params, &shared_seed(byte), 1);
+            assert!((-0.2..=0.2).contains(&change));
+            changes.push(change);
+        }
+        // random events can make resources regrow both slower and faster
+        assert!(changes.iter().any(|c| *c < 0.0));
+        assert!(changes.iter().any(|c| *c > 0.0));
+    }
+
+    fn horizon(min_rounds: u32, end_probability: f32) -> UnknownHorizonParams {
+        UnknownHorizonParams {
+            min_rounds,
+            end_probability,
+        }
+    }
+
+    #[test]
+    fn unknown_horizon_has_to_fit_the_game() {
+        assert!(check_unknown_horizon(&horizon(3, 0.5), 5).is_ok());
+        assert!(check_unknown_horizon(&horizon(5, 1.0), 5).is_ok());
+        assert!(check_unknown_horizon(&horizon(6, 0.5), 5).is_err());
+        assert!(check_unknown_horizon(&horizon(3, -0.1), 5).is_err());
+        assert!(check_unknown_horizon(&horizon(3, 1.1), 5).is_err());
+        assert!(check_unknown_horizon(&horizon(3, f32::NAN), 5).is_err());
+    }
+
+    #[test]
+    fn random_events_cant_change_the_regeneration_by_more_than_all_of_it() {
+        let events = |max_regeneration_change| RandomEventsParams {
+            max_regeneration_change,
+        };
+        assert!(check_random_events(&events(0.0)).is_ok());
+        assert!(check_random_events(&events(1.0)).is_ok());
+        assert!(check_random_events(&events(-0.1)).is_err());
+        assert!(check_random_events(&events(1.5)).is_err());
+        assert!(check_random_events(&events(f32::NAN)).is_err());
+    }
+
+    #[test]
+    fn game_doesnt_end_by_chance_before_min_rounds() {
+        for byte in 0..=255u8 {
+            assert!(!game_ends_by_chance(
+                &horizon(3, 1.0),
+                &shared_seed(byte),
+                2
+            ));
+            assert!(game_ends_by_chance(&horizon(3, 1.0), &shared_seed(byte), 3));
+            assert!(!game_ends_by_chance(
+                &horizon(3, 0.0),
+                &shared_seed(byte),
+                3
+            ));
+        }
+    }
+
+    #[test]
+    fn game_ends_by_chance_with_the_given_probability() {
+        let ended = (0..=255u8)
+            .filter(|byte| game_ends_by_chance(&horizon(0, 0.5), &shared_seed(*byte), 1))
+            .count();
+        // every seed gives a different draw, so about a half of them end the game
+        assert!(ended > 64 && ended < 192);
+    }
+}
@@ -1,8 +1,31 @@
 use crate::{
+    game_attributes::{
+        assign_attributes, check_players_attributes, AttributesAssignment, PlayersAttributes,
+    },
+    game_auction::{check_auction, AuctionParams},
+    game_chat::{check_communication, CommunicationParams},
     game_code::get_game_code_anchor,
-    game_round::{GameRound, RoundState},
+    game_facilitator::get_lobby_facilitator,
+    game_governance::MajorityRule,
+    game_investment::{check_investment, InvestmentParams},
+    game_monitoring::{check_monitoring, MonitoringParams},
+    game_patches::{check_patches, PatchesParams},
+    game_pollution::{check_pollution, PollutionParams},
+    game_quota::{check_quota, QuotaParams},
+    game_round::{game_ends_with_round, GameRound, RoundState},
+    game_sanction::apply_sanction_penalties,
+    game_seed::{
+        check_random_events, check_unknown_horizon, RandomEventsParams, UnknownHorizonParams,
+    },
     game_signals::{GameSignal, SignalPayload},
+    game_subsistence::check_subsistence_need,
+    game_survey::{check_survey, SurveyParams},
+    game_taxation::{check_taxation, TaxationParams},
+    game_team::{check_team_params, check_teams, team_stats, TeamParams, TeamStats, Teams},
+    game_trade::pay_debts,
+    game_turns::{signal_first_turn, TurnOrder},
     player_profile::get_player_profiles_for_game_code,
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
 };
 use hdk::prelude::*;
 use std::collections::BTreeMap;
@@ -30,11 +53,94 @@ pub enum SessionState {
     Finished { last_round: EntryHash },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Copy)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct GameParams {
     pub regeneration_factor: f32, // how would resources re-grow every round
     pub start_amount: ResourceAmount, // how many resources are there when the game starts
     pub num_rounds: u32,          // how many rounds in the game
+    // how many resources a sanctioned player loses for every resource
+    // the punisher spends. None means that sanctions are disabled
+    pub sanction_factor: Option<ResourceAmount>,
+    // max amount of resources a player can take in a single move. None means no cap
+    pub harvest_cap: Option<ResourceAmount>,
+    // how many votes a proposal to change these params needs to be accepted
+    pub majority_rule: MajorityRule,
+    // how players' takes are taxed and redistributed. None means there are no taxes
+    pub taxation: Option<TaxationParams>,
+    // how random events change the regeneration. None means that resources
+    // always regrow according to the regeneration_factor
+    pub random_events: Option<RandomEventsParams>,
+    // how the game can end before num_rounds are played. None means that players
+    // know the game ends after num_rounds; otherwise num_rounds is the max
+    pub unknown_horizon: Option<UnknownHorizonParams>,
+    // how many resources a player needs every round to survive. Players whose
+    // stock falls below the sum of their needs are eliminated. None means
+    // nobody is ever eliminated
+    pub subsistence_need: Option<ResourceAmount>,
+    // how players play in teams. None means everyone plays for themselves
+    pub teams: Option<TeamParams>,
+    // order in which players move one after another, seeing the takes of those
+    // who moved before them. None means that everyone moves simultaneously
+    pub turn_order: Option<TurnOrder>,
+    // patches the commons is split into. If it's set, every patch has it's own
+    // start amount and regeneration factor, which are used instead of the
+    // start_amount and regeneration_factor above. None means a single stock
+    pub patches: Option<PatchesParams>,
+    // how taking resources pollutes the commons. None means there's no pollution
+    pub pollution: Option<PollutionParams>,
+    // how players can reinvest and save their resources. None means they can't
+    pub investment: Option<InvestmentParams>,
+    // total allowable catch per round split into quota units that players
+    // can trade. None means there are no quotas
+    pub quota: Option<QuotaParams>,
+    // rights to take resources that players buy in a sealed-bid auction before
+    // they make their moves. None means that there's no auction
+    pub auction: Option<AuctionParams>,
+    // when and with whom players can talk in the chat. None means that
+    // everyone can talk to everyone at any time
+    pub communication: Option<CommunicationParams>,
+    // what players can observe about the stock and the takes of others.
+    // None means that they see everything exactly
+    pub monitoring: Option<MonitoringParams>,
+    // questionnaire players fill in after the game has ended. None means there's no survey
+    pub survey: Option<SurveyParams>,
+}
+
+impl GameParams {
+    /// Checks if players have to agree on a shared seed before they can play
+    pub fn needs_shared_seed(&self) -> bool {
+        self.random_events.is_some()
+            || self.unknown_horizon.is_some()
+            || self.monitoring.map_or(false, |m| m.is_noisy())
+    }
+}
+
+// Params of the game we're playing if the host didn't choose anything else
+impl Default for GameParams {
+    fn default() -> Self {
+        GameParams {
+            regeneration_factor: 1.1,
+            start_amount: 100,
+            num_rounds: 3,
+            sanction_factor: None,
+            harvest_cap: None,
+            majority_rule: MajorityRule::Simple,
+            taxation: None,
+            random_events: None,
+            unknown_horizon: None,
+            subsistence_need: None,
+            teams: None,
+            turn_order: None,
+            patches: None,
+            pollution: None,
+            investment: None,
+            quota: None,
+            auction: None,
+            communication: None,
+            monitoring: None,
+            survey: None,
+        }
+    }
 }
 
 #[hdk_entry(id = "game_session", visibility = "public")]
@@ -44,31 +150,154 @@ pub struct GameSession {
     pub status: SessionState,      // how the game is going
     pub game_params: GameParams,   // what specific game are we playing
     pub players: Vec<AgentPubKey>, // who is playing
-    pub scores: PlayerStats,       // end scores
-    pub anchor: EntryHash,         // game code anchor that identifies this game
+    // end scores: everything every player collected during the whole game,
+    // net of costs, taxes and penalties, plus their savings in the bank.
+    // NOTE: before sanctions were added, scores only had the takes of the last round
+    pub scores: PlayerStats,
+    pub anchor: EntryHash, // game code anchor that identifies this game
+    // attributes that make players different. Players who aren't
+    // in the map have the default attributes
+    pub player_attributes: PlayersAttributes,
+    // team of every player. Players who aren't in the map don't have a team
+    pub teams: Teams,
+    // end scores of every team
+    pub team_scores: TeamStats,
 }
 
 pub const OWNER_SESSION_TAG: &str = "MY_GAMES";
 pub const GAME_CODE_TO_SESSION_TAG: &str = "GAME_SESSION";
 pub const SESSION_TO_ROUND_TAG: &str = "GAME_ROUND";
 
-/// Collects input info for the GameSession and calls new_session
+/// Struct to receive user input from the UI when the host
+/// wants to choose params of the game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StartGameSessionInput {
+    pub game_code: String,
+    pub game_params: GameParams,
+    // how players get their attributes. None means all players are the same
+    pub player_attributes: Option<AttributesAssignment>,
+    // team of every player. None means there are no teams
+    pub teams: Option<Teams>,
+}
+
+/// Starts a GameSession with the default GameParams
 pub fn start_game_session_with_code(game_code: String) -> ExternResult<EntryHash> {
+    start_game_session_with_params(game_code, GameParams::default(), None, None)
+}
+
+/// Makes sure that the params make sense for the game. It's used when starting
+/// the game, when validating the GameSession entry and when validating proposals
+/// to change the params
+pub fn check_game_params(game_params: &GameParams) -> ExternResult<()> {
+    if !(0.0..=f32::MAX).contains(&game_params.regeneration_factor) {
+        return Err(WasmError::Guest(format!(
+            "Regeneration factor has to be >= 0, but it is {}",
+            game_params.regeneration_factor
+        )));
+    }
+    if game_params.start_amount <= 0 {
+        return Err(WasmError::Guest(format!(
+            "Start amount has to be > 0, but it is {}",
+            game_params.start_amount
+        )));
+    }
+    if game_params.num_rounds < 1 {
+        return Err(WasmError::Guest(String::from(
+            "Game has to have at least 1 round",
+        )));
+    }
+    if let Some(sanction_factor) = game_params.sanction_factor {
+        if sanction_factor <= 0 {
+            return Err(WasmError::Guest(format!(
+                "Sanction factor has to be > 0, but it is {}",
+                sanction_factor
+            )));
+        }
+    }
+    if let Some(harvest_cap) = game_params.harvest_cap {
+        if harvest_cap <= 0 {
+            return Err(WasmError::Guest(format!(
+                "Harvest cap has to be > 0, but it is {}",
+                harvest_cap
+            )));
+        }
+    }
+    if let Some(random_events) = &game_params.random_events {
+        check_random_events(random_events)?;
+    }
+    if let Some(taxation) = &game_params.taxation {
+        check_taxation(taxation)?;
+    }
+    if let Some(unknown_horizon) = &game_params.unknown_horizon {
+        check_unknown_horizon(unknown_horizon, game_params.num_rounds)?;
+    }
+    if let Some(subsistence_need) = game_params.subsistence_need {
+        check_subsistence_need(subsistence_need)?;
+    }
+    if let Some(teams) = &game_params.teams {
+        check_team_params(teams)?;
+    }
+    if let Some(patches) = &game_params.patches {
+        check_patches(patches)?;
+    }
+    if let Some(pollution) = &game_params.pollution {
+        check_pollution(pollution)?;
+    }
+    if let Some(investment) = &game_params.investment {
+        check_investment(investment)?;
+    }
+    if let Some(quota) = &game_params.quota {
+        check_quota(quota)?;
+    }
+    if let Some(auction) = &game_params.auction {
+        check_auction(auction)?;
+    }
+    if let Some(communication) = &game_params.communication {
+        check_communication(communication)?;
+    }
+    if let Some(monitoring) = &game_params.monitoring {
+        check_monitoring(monitoring)?;
+    }
+    if let Some(survey) = &game_params.survey {
+        check_survey(survey)?;
+    }
+    Ok(())
+}
+
+/// Collects input info for the GameSession and calls new_session
+pub fn start_game_session_with_params(
+    game_code: String,
+    game_params: GameParams,
+    attributes_assignment: Option<AttributesAssignment>,
+    teams: Option<Teams>,
+) -> ExternResult<EntryHash> {
+    check_game_params(&game_params)?;
     let anchor = get_game_code_anchor(game_code.clone())?;
+    // if a facilitator is in charge of the lobby, only they can start it's session
+    if let Some(facilitator) = get_lobby_facilitator(anchor.clone())? {
+        if facilitator != agent_info()?.agent_initial_pubkey {
+            return Err(WasmError::Guest(String::from(
+                "Only the facilitator of the lobby can start it's GameSession",
+            )));
+        }
+    }
     let players = get_player_profiles_for_game_code(game_code)?;
-    let game_params = GameParams {
-        regeneration_factor: 1.1,
-        start_amount: 100,
-        num_rounds: 3,
-    };
     let player_keys: Vec<AgentPubKey> = players.iter().map(|x| x.player_id.clone()).collect();
-    new_session(player_keys, game_params, anchor)
+    let player_attributes = match attributes_assignment {
+        Some(assignment) => assign_attributes(assignment, &player_keys)?,
+        None => PlayersAttributes::new(),
+    };
+    let teams = teams.unwrap_or_default();
+    check_teams(&teams, &player_keys)?;
+    new_session(player_keys, game_params, player_attributes, teams, anchor)
 }
 
 /// Creates new Holochain entry for GameSession
 pub fn new_session(
     players: Vec<AgentPubKey>,
     game_params: GameParams,
+    player_attributes: PlayersAttributes,
+    teams: Teams,
     anchor: EntryHash,
 ) -> ExternResult<EntryHash> {
     // Agent who executes this fn is automatically the owner of the game
@@ -82,6 +311,10 @@ pub fn new_session(
         // there's no score yet, so we just create an empty instance of PlayerStats
         scores: PlayerStats::new(),
         anchor: anchor.clone(),
+        player_attributes,
+        teams,
+        // there's no score yet for teams as well
+        team_scores: TeamStats::new(),
     };
     // Create a Holochain entry on DHT
     create_entry(&game_session)?;
@@ -108,10 +341,7 @@ pub fn new_session(
     let round_zero = GameRound::new(
         0,
         game_session_entry_hash.clone(),
-        game_session.game_params.start_amount,
-        0,
-        0,
-        PlayerStats::new(),
+        RoundState::initial(&game_session.game_params, &game_session.players),
     );
     // Commit round_zero to DHT
     create_entry(&round_zero)?;
@@ -132,13 +362,20 @@ pub fn new_session(
     // WARNING: remote_signal is fire and forget, no error if it fails,
     // might be a weak point if this were production hApp
     let signal_payload = SignalPayload {
-        game_session_entry_hash: game_session_entry_hash.into(),
+        game_session_entry_hash: game_session_entry_hash.clone().into(),
         round_entry_hash_update: entry_hash_round_zero.clone().into(),
     };
 
     let signal = ExternIO::encode(GameSignal::StartGame(signal_payload))?;
     let other_players = others(players)?;
     remote_signal(signal, other_players)?;
+    // If players move one after another, the first one can move now
+    signal_first_turn(
+        &game_session,
+        game_session_entry_hash.clone(),
+        &round_zero,
+        entry_hash_round_zero.clone(),
+    )?;
 
     // Return hash of the round zero because players would need it
     // to make their moves, and we're saving them a lookup by doing so
@@ -206,29 +443,21 @@ pub fn end_game(
     round_state: &RoundState,
 ) -> ExternResult<EntryHash> {
     info!("Ending the game");
-    // If there are no resources, then the game is lost,
-    // otherwise it's finished
-    // NOTE: this is a Rust trick where we define value of the game_status
-    // as a result of executing if and it's branches.
-    let game_status = if round_state.resources_left <= 0 {
-        SessionState::Lost {
-            last_round: last_round_entry_hash.clone(),
-        }
-    } else {
-        SessionState::Finished {
-            last_round: last_round_entry_hash.clone(),
-        }
-    };
+    let game_status = final_status(round_state, last_round_entry_hash);
+    let scores = final_scores(round_state);
     // Create a Rust struct instance with new data of our game session
     // Most of the fields come from the original GameSession,
-    // but state and scores are different
+    // but state and scores of players and teams are different
     let game_session_update = GameSession {
         owner: game_session.owner.clone(),
         status: game_status,
         game_params: game_session.game_params.clone(),
         players: game_session.players.clone(),
-        scores: round_state.player_stats.clone(),
+        team_scores: team_stats(&scores, &game_session.teams),
+        scores,
         anchor: game_session.anchor.clone(),
+        player_attributes: game_session.player_attributes.clone(),
+        teams: game_session.teams.clone(),
     };
     // Update the original game session entry on DHT with the game_session_update
     // contents. We're making an update chain from the game_session_header_hash
@@ -260,3 +489,281 @@ pub fn end_game(
     // Return hash of the entry as the ID of the new data we commited to DHT
     Ok(game_session_entry_hash_update.clone())
 }
+
+/// Calculates the status of the game that ended with the round at last_round_entry_hash
+pub fn final_status(round_state: &RoundState, last_round_entry_hash: &EntryHash) -> SessionState {
+    // If there are no resources, then the game is lost,
+    // otherwise it's finished
+    // NOTE: this is a Rust trick where we define value of the game_status
+    // as a result of executing if and it's branches.
+    if round_state.resources_left <= 0 {
+        SessionState::Lost {
+            last_round: last_round_entry_hash.clone(),
+        }
+    } else {
+        SessionState::Finished {
+            last_round: last_round_entry_hash.clone(),
+        }
+    }
+}
+
+/// Calculates the scores of the players from the state of the last round.
+/// NOTE: this fn is used both when ending the game and in validation of the
+/// GameSession update, so it only operates with input data
+pub fn final_scores(round_state: &RoundState) -> PlayerStats {
+    // Player stats add up over the whole game, so they are the scores,
+    // and savings are paid out to the players when the game ends
+    let mut scores = round_state.player_stats.clone();
+    for (player, saved) in &round_state.bank_balances {
+        *scores.entry(player.clone()).or_insert(0) += saved;
+    }
+    // debts from trades are paid out of the final scores, since there is no next round
+    // to pay them in. Whatever a player can't pay anymore is forgiven
+    pay_debts(
+        &mut scores,
+        &mut PlayerStats::new(),
+        &round_state.trade_debts,
+    );
+    // same goes for the penalties of the sanctions made in the final round
+    apply_sanction_penalties(&mut scores, &round_state.sanction_penalties_due);
+    scores
+}
+
+/// Looks for the update of the game session that ended it, and returns it together
+/// with the hash of it's update header. Returns None if the game is still in progress
+pub fn get_ended_session(
+    session_hash: EntryHash,
+) -> ExternResult<Option<(HeaderHash, GameSession)>> {
+    let entry_details = match get_details(session_hash, GetOptions::latest())? {
+        Some(Details::Entry(entry_details)) => entry_details,
+        _ => return Ok(None),
+    };
+    // end_game is the only place where the game session is updated
+    for update_header in entry_details.updates {
+        let update_hash = update_header.header().entry_hash().ok_or(WasmError::Guest(
+            "GameSession update header doesn't have an entry".into(),
+        ))?;
+        let game_session: GameSession =
+            try_get_and_convert(update_hash.clone(), GetOptions::latest())?;
+        if game_session.status != SessionState::InProgress {
+            return Ok(Some((update_header.header_address().clone(), game_session)));
+        }
+    }
+    Ok(None)
+}
+
+/// Checks that the element at ended_session_header is a valid update of the
+/// game session at session_hash that ended the game.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn verify_ended_session(
+    session_hash: &EntryHash,
+    ended_session_header: &HeaderHash,
+) -> ExternResult<bool> {
+    let element = must_get_valid_element(ended_session_header.clone())?;
+    match element.header() {
+        Header::Update(update_data) if &update_data.original_entry_address == session_hash => {}
+        _ => return Ok(false),
+    }
+    match try_from_element::<GameSession>(element) {
+        Ok(ended_session) => Ok(ended_session.status != SessionState::InProgress),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Validates creation of GameSession entries
+pub fn validate_create_entry_game_session(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let game_session: GameSession = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &game_session.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "GameSession can only be created by it's owner",
+        )));
+    }
+
+    // anyone can create the entry without going through start_game_session_with_params,
+    // so the params are checked here as well
+    match check_game_params(&game_session.game_params) {
+        Ok(()) => {}
+        Err(WasmError::Guest(error)) => return Ok(ValidateCallbackResult::Invalid(error)),
+        Err(error) => return Err(error),
+    }
+
+    match check_teams(&game_session.teams, &game_session.players) {
+        Ok(()) => {}
+        Err(WasmError::Guest(error)) => return Ok(ValidateCallbackResult::Invalid(error)),
+        Err(error) => return Err(error),
+    }
+
+    match check_players_attributes(&game_session.player_attributes, &game_session.players) {
+        Ok(()) => {}
+        Err(WasmError::Guest(error)) => return Ok(ValidateCallbackResult::Invalid(error)),
+        Err(error) => return Err(error),
+    }
+
+    // NOTE: start_game_session_with_params checks that nobody but the facilitator
+    // starts the session of a facilitated lobby, since it depends on what the
+    // agent can see on DHT
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of GameSession entries, which only happens when the game ends
+pub fn validate_update_entry_game_session(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let game_session: GameSession = try_from_element(data.element.clone())?;
+    let update_data = match data.element.header() {
+        Header::Update(update_data) => update_data,
+        _ => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "GameSession's element has the wrong header: expected Update",
+            )));
+        }
+    };
+
+    let original_session =
+        must_get_entry_struct::<GameSession>(update_data.original_entry_address.clone())?;
+    if original_session.status != SessionState::InProgress {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Can't update GameSession: it has already ended",
+        )));
+    }
+    // only the status and the scores change when the game ends
+    if game_session.owner != original_session.owner
+        || game_session.game_params != original_session.game_params
+        || game_session.players != original_session.players
+        || game_session.anchor != original_session.anchor
+        || game_session.player_attributes != original_session.player_attributes
+        || game_session.teams != original_session.teams
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only the status and the scores of the GameSession can change when the game ends",
+        )));
+    }
+
+    let last_round_hash = match &game_session.status {
+        SessionState::Finished { last_round } | SessionState::Lost { last_round } => last_round,
+        SessionState::InProgress => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "GameSession can only be updated to end the game",
+            )));
+        }
+    };
+    let last_round = must_get_entry_struct::<GameRound>(last_round_hash.clone())?;
+    if last_round.session != update_data.original_entry_address {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "GameSession has to end with one of it's own rounds",
+        )));
+    }
+    // anyone can calculate the scores from the state of the last round
+    let scores = final_scores(&last_round.state);
+    if game_session.status != final_status(&last_round.state, last_round_hash)
+        || game_session.team_scores != team_stats(&scores, &game_session.teams)
+        || game_session.scores != scores
+    {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "GameSession has to end with the status and the scores of it's last round",
+        )));
+    }
+    // any player who closes the final round can end the game, since GameRound
+    // validation doesn't let the game go on after it. Round zero only holds the
+    // initial state, so it can't end the game. Only the owner can end it earlier
+    let ended_by_round = last_round.round_num > 0
+        && game_ends_with_round(
+            last_round.round_num,
+            &last_round.state,
+            &original_session.players,
+        );
+    if !ended_by_round && update_data.author != original_session.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only the owner can end the GameSession before it's final round",
+        )));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates delete of GameSession entries
+pub fn validate_delete_entry_game_session(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete GameSession entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, debt, hash, stats};
+
+    fn round_state() -> RoundState {
+        RoundState::initial(&GameParams::default(), &[agent(1), agent(2)])
+    }
+
+    #[test]
+    fn params_without_resources_rounds_or_positive_factors_are_rejected() {
+        assert!(check_game_params(&GameParams::default()).is_ok());
+        let invalid = vec![
+            GameParams {
+                start_amount: 0,
+                ..GameParams::default()
+            },
+            GameParams {
+                num_rounds: 0,
+                ..GameParams::default()
+            },
+            GameParams {
+                sanction_factor: Some(0),
+                ..GameParams::default()
+            },
+            GameParams {
+                harvest_cap: Some(-1),
+                ..GameParams::default()
+            },
+        ];
+        for params in invalid {
+            assert!(check_game_params(&params).is_err());
+        }
+    }
+
+    #[test]
+    fn game_is_lost_when_resources_are_depleted() {
+        let last_round = hash(7);
+        let mut state = round_state();
+        assert_eq!(
+            final_status(&state, &last_round),
+            SessionState::Finished {
+                last_round: last_round.clone()
+            }
+        );
+        state.resources_left = 0;
+        assert_eq!(
+            final_status(&state, &last_round),
+            SessionState::Lost {
+                last_round: last_round.clone()
+            }
+        );
+    }
+
+    #[test]
+    fn savings_are_paid_out_and_debts_are_paid_from_the_scores() {
+        let mut state = round_state();
+        state.player_stats = stats(&[(1, 10), (2, 3)]);
+        state.bank_balances = stats(&[(2, 4), (3, 5)]);
+        state.trade_debts = vec![
+            debt(1, 2, 6),
+            // player 2 can only pay what they have, the rest is forgiven
+            debt(2, 1, 20),
+        ];
+        assert_eq!(final_scores(&state), stats(&[(1, 17), (2, 0), (3, 5)]));
+    }
+
+    #[test]
+    fn penalties_of_the_final_sanctions_are_taken_from_the_scores() {
+        let mut state = round_state();
+        state.player_stats = stats(&[(1, 10), (2, 3)]);
+        state.sanction_penalties_due = stats(&[(1, 4), (2, 6)]);
+        assert_eq!(final_scores(&state), stats(&[(1, 6), (2, 0)]));
+    }
+}
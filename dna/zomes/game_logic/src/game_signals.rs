@@ -1,4 +1,7 @@
-use crate::player_profile::PlayerProfile;
+use crate::{
+    game_chat::ChatMessage, game_round::GameRoundInfo, game_session::ResourceAmount,
+    player_profile::PlayerProfile,
+};
 use hdk::prelude::*;
 
 /// Our signals aren't too different from each other, so
@@ -9,6 +12,39 @@ pub struct SignalPayload {
     pub round_entry_hash_update: EntryHash,
 }
 
+/// Payload of the signal that it's the player's turn to move
+#[derive(Debug, Serialize, Deserialize, SerializedBytes)]
+pub struct YourTurnPayload {
+    pub game_session_entry_hash: EntryHash,
+    pub round_entry_hash_update: EntryHash,
+    // takes of the players who moved earlier in the round, in the turn order
+    pub earlier_moves: Vec<(ResourceAmount, AgentPubKey)>,
+}
+
+/// Payload of the signal that some players fell below the subsistence line
+#[derive(Debug, Serialize, Deserialize, SerializedBytes)]
+pub struct EliminationPayload {
+    pub game_session_entry_hash: EntryHash,
+    pub round_num: u32,
+    // players eliminated in this round only
+    pub eliminated_players: Vec<AgentPubKey>,
+}
+
+/// Payload of the signal that a player has sent a chat message
+#[derive(Debug, Serialize, Deserialize, SerializedBytes)]
+pub struct ChatMessagePayload {
+    pub game_session_entry_hash: EntryHash,
+    pub message_entry_hash: EntryHash,
+    pub message: ChatMessage,
+}
+
+/// Payload of the signal with what a player can observe of the round that was just closed
+#[derive(Debug, Serialize, Deserialize, SerializedBytes)]
+pub struct RoundObservationPayload {
+    pub game_session_entry_hash: EntryHash,
+    pub round_info: GameRoundInfo,
+}
+
 // Different kinds of signals available in our hApp
 #[derive(Serialize, Deserialize, SerializedBytes, Debug)]
 #[serde(tag = "signal_name", content = "signal_payload")]
@@ -17,4 +53,10 @@ pub enum GameSignal {
     StartGame(SignalPayload),
     StartNextRound(SignalPayload),
     GameOver(SignalPayload),
+    PlayersEliminated(EliminationPayload),
+    // sent to the player whose turn it is to move when players move one after another
+    YourTurn(YourTurnPayload),
+    ChatMessage(ChatMessagePayload),
+    // sent to every player separately, since what they can observe might differ
+    RoundObserved(RoundObservationPayload),
 }
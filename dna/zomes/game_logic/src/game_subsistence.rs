@@ -0,0 +1,116 @@
+use crate::{
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    game_signals::{EliminationPayload, GameSignal},
+};
+use hdk::prelude::*;
+
+/// Makes sure that the subsistence need makes sense for the game
+pub fn check_subsistence_need(subsistence_need: ResourceAmount) -> ExternResult<()> {
+    if subsistence_need <= 0 {
+        return Err(WasmError::Guest(format!(
+            "Subsistence need has to be > 0, but it is {}",
+            subsistence_need
+        )));
+    }
+    Ok(())
+}
+
+/// Players that are still in the game: everyone from the session
+/// who wasn't eliminated so far
+pub fn active_players(players: &[AgentPubKey], eliminated: &[AgentPubKey]) -> Vec<AgentPubKey> {
+    players
+        .iter()
+        .filter(|p| !eliminated.contains(p))
+        .cloned()
+        .collect()
+}
+
+/// Calculates which players are eliminated after round_num rounds were played.
+/// Every player needs subsistence_need resources per round to survive, so the
+/// subsistence line after round_num rounds is subsistence_need * round_num, and
+/// everyone whose stock is below that line is eliminated.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent:
+/// - players who were eliminated once stay eliminated
+/// - returned list is sorted by player's pub key
+pub fn eliminate_players(
+    player_stats: &PlayerStats,
+    subsistence_need: ResourceAmount,
+    round_num: u32,
+    players: &[AgentPubKey],
+    already_eliminated: &[AgentPubKey],
+) -> Vec<AgentPubKey> {
+    let subsistence_line = subsistence_need * round_num as ResourceAmount;
+    let mut eliminated = already_eliminated.to_vec();
+    for p in active_players(players, already_eliminated) {
+        if *player_stats.get(&p).unwrap_or(&0) < subsistence_line {
+            info!("Player {:?} fell below the subsistence line", p);
+            eliminated.push(p);
+        }
+    }
+    eliminated.sort();
+    eliminated.dedup();
+    eliminated
+}
+
+/// Lets all players of the session know who was eliminated in the round.
+/// Doesn't send anything if nobody was eliminated
+pub fn signal_eliminations(
+    game_session: &GameSession,
+    game_session_entry_hash: EntryHash,
+    round_num: u32,
+    prev_eliminated: &[AgentPubKey],
+    eliminated: &[AgentPubKey],
+) -> ExternResult<()> {
+    let newly_eliminated = active_players(eliminated, prev_eliminated);
+    if newly_eliminated.is_empty() {
+        return Ok(());
+    }
+    let signal_payload = EliminationPayload {
+        game_session_entry_hash,
+        round_num,
+        eliminated_players: newly_eliminated,
+    };
+    let signal = ExternIO::encode(GameSignal::PlayersEliminated(signal_payload))?;
+    // eliminated players get this signal as well, so their UI can tell them they're out
+    remote_signal(signal, game_session.players.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, stats};
+
+    #[test]
+    fn subsistence_need_has_to_be_positive() {
+        assert!(check_subsistence_need(1).is_ok());
+        assert!(check_subsistence_need(0).is_err());
+        assert!(check_subsistence_need(-1).is_err());
+    }
+
+    #[test]
+    fn players_below_the_subsistence_line_are_eliminated() {
+        let players = vec![agent(3), agent(1), agent(2)];
+        let player_stats = stats(&[(1, 5), (2, 6), (3, 4)]);
+        // the line after 2 rounds with the need of 3 is 6
+        let eliminated = eliminate_players(&player_stats, 3, 2, &players, &[]);
+        assert_eq!(eliminated, vec![agent(1), agent(3)]);
+        assert_eq!(active_players(&players, &eliminated), vec![agent(2)]);
+    }
+
+    #[test]
+    fn eliminated_players_stay_eliminated() {
+        let players = vec![agent(1), agent(2)];
+        // player 2 is above the line again, but they were eliminated already
+        let player_stats = stats(&[(1, 10), (2, 10)]);
+        let eliminated = eliminate_players(&player_stats, 3, 2, &players, &[agent(2)]);
+        assert_eq!(eliminated, vec![agent(2)]);
+    }
+
+    #[test]
+    fn players_without_stats_have_nothing() {
+        let players = vec![agent(1), agent(2)];
+        let eliminated = eliminate_players(&stats(&[(1, 3)]), 3, 1, &players, &[]);
+        assert_eq!(eliminated, vec![agent(2)]);
+    }
+}
@@ -0,0 +1,500 @@
+use crate::{
+    game_session::{get_ended_session, verify_ended_session, GameSession},
+    utils::{
+        earlier_entries_in_package, must_get_entry_struct, try_from_element, try_get_and_convert,
+    },
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub const SURVEY_RESPONSE_LINK_TAG: &str = "SURVEY_RESPONSE";
+// max number of characters in a single text answer
+pub const MAX_SURVEY_TEXT_LENGTH: usize = 2000;
+
+/// Kind of answer the question expects
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum QuestionKind {
+    // number from min to max, e.g. 1 to 5 for "strongly disagree" to "strongly agree"
+    Scale { min: u32, max: u32 },
+    // one of the options, answered with it's index
+    Choice { options: Vec<String> },
+    // free text
+    Text,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SurveyQuestion {
+    pub text: String,
+    pub kind: QuestionKind,
+}
+
+/// Questionnaire players fill in after the game has ended
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SurveyParams {
+    pub questions: Vec<SurveyQuestion>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SurveyAnswer {
+    Scale(u32),
+    // index of the chosen option
+    Choice(u32),
+    Text(String),
+}
+
+/// Answers of the player to every question of the session survey, in the same order.
+/// Validators get the author's earlier responses with every response, so they
+/// can check that players only fill in the survey once
+#[hdk_entry(
+    id = "survey_response",
+    visibility = "public",
+    required_validation_type = "sub_chain"
+)]
+#[derive(Clone)]
+pub struct SurveyResponse {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // header of the game session update that ended the game
+    pub ended_session: HeaderHash,
+    pub answers: Vec<SurveyAnswer>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SurveyInput {
+    pub session_hash: EntryHash,
+    pub answers: Vec<SurveyAnswer>,
+}
+
+/// Aggregated answers to a single question of the survey
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuestionResults {
+    pub question: SurveyQuestion,
+    // mean of the answers. None if it isn't a scale question or nobody answered yet
+    pub mean: Option<f32>,
+    // number of answers for every option. Empty if it isn't a choice question
+    pub choice_counts: Vec<u32>,
+    // every text answer. Empty if it isn't a text question
+    pub texts: Vec<String>,
+}
+
+/// Helper struct to package the survey results for the host
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SurveyResults {
+    pub responses: u32,
+    pub questions: Vec<QuestionResults>,
+}
+
+/// Makes sure that the survey params make sense for the game
+pub fn check_survey(params: &SurveyParams) -> ExternResult<()> {
+    if params.questions.is_empty() {
+        return Err(WasmError::Guest(String::from(
+            "Survey has to have at least one question",
+        )));
+    }
+    for question in &params.questions {
+        match &question.kind {
+            QuestionKind::Scale { min, max } if min >= max => {
+                return Err(WasmError::Guest(format!(
+                    "Scale of the question '{}' has to go from a smaller number to a bigger one",
+                    question.text
+                )));
+            }
+            QuestionKind::Choice { options } if options.is_empty() => {
+                return Err(WasmError::Guest(format!(
+                    "Question '{}' has to have at least one option",
+                    question.text
+                )));
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every question has an answer of the kind it expects.
+/// Returns the description of the first problem found
+fn check_answers(params: &SurveyParams, answers: &[SurveyAnswer]) -> Option<String> {
+    if answers.len() != params.questions.len() {
+        return Some(format!(
+            "Survey has {} questions, but there are {} answers",
+            params.questions.len(),
+            answers.len()
+        ));
+    }
+    for (question, answer) in params.questions.iter().zip(answers) {
+        let is_valid = match (&question.kind, answer) {
+            (QuestionKind::Scale { min, max }, SurveyAnswer::Scale(value)) => {
+                min <= value && value <= max
+            }
+            (QuestionKind::Choice { options }, SurveyAnswer::Choice(index)) => {
+                (*index as usize) < options.len()
+            }
+            (QuestionKind::Text, SurveyAnswer::Text(text)) => {
+                text.chars().count() <= MAX_SURVEY_TEXT_LENGTH
+            }
+            _ => false,
+        };
+        if !is_valid {
+            return Some(format!(
+                "Answer to the question '{}' doesn't fit it",
+                question.text
+            ));
+        }
+    }
+    None
+}
+
+/// Submits the answers of the agent executing this fn to the survey of the session,
+/// and links them from the session
+pub fn submit_survey(input: SurveyInput) -> ExternResult<EntryHash> {
+    let (ended_session_header, ended_session) = get_ended_session(input.session_hash.clone())?
+        .ok_or(WasmError::Guest(
+            "Can't fill in the survey until the game has ended".into(),
+        ))?;
+    let params = ended_session.game_params.survey.ok_or(WasmError::Guest(
+        "This GameSession doesn't have a survey".into(),
+    ))?;
+    if let Some(problem) = check_answers(&params, &input.answers) {
+        return Err(WasmError::Guest(problem));
+    }
+
+    // Responses are written by the players themselves, so their earlier
+    // responses are already on their source chain
+    let filter = ChainQueryFilter::new()
+        .include_entries(true)
+        .header_type(HeaderType::Create)
+        .entry_type(EntryType::App(AppEntryType::new(
+            entry_def_index!(SurveyResponse)?,
+            zome_info()?.zome_id,
+            EntryVisibility::Public,
+        )));
+    for el in query(filter)? {
+        let prev_response: SurveyResponse = try_from_element(el)?;
+        if prev_response.session == input.session_hash {
+            return Err(WasmError::Guest(String::from(
+                "Players can only fill in the survey once",
+            )));
+        }
+    }
+
+    let response = SurveyResponse {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: input.session_hash.clone(),
+        ended_session: ended_session_header,
+        answers: input.answers,
+    };
+    create_entry(&response)?;
+    let response_entry_hash = hash_entry(&response)?;
+
+    // Link from the game session so that the host can collect the responses
+    create_link(
+        input.session_hash,
+        response_entry_hash.clone(),
+        LinkTag::new(String::from(SURVEY_RESPONSE_LINK_TAG)),
+    )?;
+
+    Ok(response_entry_hash)
+}
+
+/// Get all survey responses submitted in the session so far, together with their entry hashes
+pub fn get_survey_responses(
+    session_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, SurveyResponse)>> {
+    let links = get_links(
+        session_hash,
+        Some(LinkTag::new(String::from(SURVEY_RESPONSE_LINK_TAG))),
+    )?;
+    let mut responses: Vec<(EntryHash, SurveyResponse)> = vec![];
+    for link in links.into_inner() {
+        let response: SurveyResponse =
+            try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        responses.push((link.target, response));
+    }
+    Ok(responses)
+}
+
+/// Aggregates the survey responses of the session for every question.
+/// Only the host of the session can see the results
+pub fn get_survey_results(session_hash: EntryHash) -> ExternResult<SurveyResults> {
+    let game_session: GameSession =
+        try_get_and_convert(session_hash.clone(), GetOptions::latest())?;
+    if game_session.owner != agent_info()?.agent_latest_pubkey {
+        return Err(WasmError::Guest(
+            "Only the host of the GameSession can see the survey results".into(),
+        ));
+    }
+    let params = game_session.game_params.survey.ok_or(WasmError::Guest(
+        "This GameSession doesn't have a survey".into(),
+    ))?;
+    let responses = get_survey_responses(session_hash)?;
+    Ok(survey_results(&params, &game_session.players, responses))
+}
+
+/// Aggregates the valid responses of the session players for every question.
+/// NOTE: it only operates with input data, so it doesn't matter in which order
+/// the responses were found
+fn survey_results(
+    params: &SurveyParams,
+    players: &[AgentPubKey],
+    responses: Vec<(EntryHash, SurveyResponse)>,
+) -> SurveyResults {
+    // every player only counts once: if they somehow submitted several responses,
+    // the one with the smallest entry hash is used
+    let mut response_per_player: BTreeMap<AgentPubKey, (EntryHash, SurveyResponse)> =
+        BTreeMap::new();
+    for (response_hash, response) in responses {
+        if !players.contains(&response.owner) || check_answers(params, &response.answers).is_some()
+        {
+            continue;
+        }
+        let is_first = match response_per_player.get(&response.owner) {
+            Some((prev_hash, _)) => response_hash < *prev_hash,
+            None => true,
+        };
+        if is_first {
+            response_per_player.insert(response.owner.clone(), (response_hash, response));
+        }
+    }
+
+    let mut questions: Vec<QuestionResults> = params
+        .questions
+        .iter()
+        .map(|question| QuestionResults {
+            question: question.clone(),
+            mean: None,
+            choice_counts: match &question.kind {
+                QuestionKind::Choice { options } => vec![0; options.len()],
+                _ => vec![],
+            },
+            texts: vec![],
+        })
+        .collect();
+    let mut scale_sums: Vec<u32> = vec![0; questions.len()];
+    for (_, response) in response_per_player.values() {
+        for (i, answer) in response.answers.iter().enumerate() {
+            match answer {
+                SurveyAnswer::Scale(value) => scale_sums[i] += value,
+                SurveyAnswer::Choice(index) => questions[i].choice_counts[*index as usize] += 1,
+                SurveyAnswer::Text(text) => questions[i].texts.push(text.clone()),
+            }
+        }
+    }
+    let responses = response_per_player.len() as u32;
+    if responses > 0 {
+        for (question, sum) in questions.iter_mut().zip(scale_sums) {
+            if let QuestionKind::Scale { .. } = question.question.kind {
+                question.mean = Some(sum as f32 / responses as f32);
+            }
+        }
+    }
+
+    SurveyResults {
+        responses,
+        questions,
+    }
+}
+
+/// Validates creation of SurveyResponse entries
+pub fn validate_create_entry_survey_response(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let response: SurveyResponse = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &response.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "SurveyResponse can only be created by it's owner",
+        )));
+    }
+
+    // SessionState of the ended session can only be Lost or Finished
+    if !verify_ended_session(&response.session, &response.ended_session)? {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Survey can only be filled in after it's GameSession has ended",
+        )));
+    }
+
+    let game_session = must_get_entry_struct::<GameSession>(response.session.clone())?;
+    if !game_session.players.contains(&response.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can fill in the survey",
+        )));
+    }
+    let params = match &game_session.game_params.survey {
+        Some(params) => params,
+        None => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "This GameSession doesn't have a survey",
+            )))
+        }
+    };
+    if let Some(problem) = check_answers(params, &response.answers) {
+        return Ok(ValidateCallbackResult::Invalid(problem));
+    }
+
+    // player's earlier responses come in the validation package
+    let prev_responses = match earlier_entries_in_package::<SurveyResponse>(&data)? {
+        Some(prev_responses) => prev_responses,
+        None => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "Can't validate SurveyResponse without the earlier responses of it's owner",
+            )))
+        }
+    };
+    if let Some(problem) = check_single_response(&response, &prev_responses) {
+        return Ok(ValidateCallbackResult::Invalid(problem));
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Checks that none of the earlier responses of the player is a response to the
+/// survey of the same GameSession. Returns the problem if there is one
+fn check_single_response(
+    response: &SurveyResponse,
+    prev_responses: &[SurveyResponse],
+) -> Option<String> {
+    if prev_responses
+        .iter()
+        .any(|prev_response| prev_response.session == response.session)
+    {
+        return Some(String::from("Players can only fill in the survey once"));
+    }
+    None
+}
+
+/// Validates update of SurveyResponse entries
+pub fn validate_update_entry_survey_response(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update SurveyResponse entry",
+    )))
+}
+
+/// Validates delete of SurveyResponse entries
+pub fn validate_delete_entry_survey_response(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete SurveyResponse entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, hash};
+
+    fn question(kind: QuestionKind) -> SurveyQuestion {
+        SurveyQuestion {
+            text: String::from("?"),
+            kind,
+        }
+    }
+
+    fn survey() -> SurveyParams {
+        SurveyParams {
+            questions: vec![
+                question(QuestionKind::Scale { min: 1, max: 5 }),
+                question(QuestionKind::Choice {
+                    options: vec![String::from("yes"), String::from("no")],
+                }),
+                question(QuestionKind::Text),
+            ],
+        }
+    }
+
+    fn answers(scale: u32, choice: u32, text: &str) -> Vec<SurveyAnswer> {
+        vec![
+            SurveyAnswer::Scale(scale),
+            SurveyAnswer::Choice(choice),
+            SurveyAnswer::Text(String::from(text)),
+        ]
+    }
+
+    fn response(n: u8, owner: u8, answers: Vec<SurveyAnswer>) -> (EntryHash, SurveyResponse) {
+        (
+            hash(n),
+            SurveyResponse {
+                owner: agent(owner),
+                session: hash(0),
+                ended_session: HeaderHash::from_raw_36(vec![0; 36]),
+                answers,
+            },
+        )
+    }
+
+    #[test]
+    fn survey_needs_questions_that_can_be_answered() {
+        assert!(check_survey(&survey()).is_ok());
+        assert!(check_survey(&SurveyParams { questions: vec![] }).is_err());
+        let scale = SurveyParams {
+            questions: vec![question(QuestionKind::Scale { min: 3, max: 3 })],
+        };
+        assert!(check_survey(&scale).is_err());
+        let choice = SurveyParams {
+            questions: vec![question(QuestionKind::Choice { options: vec![] })],
+        };
+        assert!(check_survey(&choice).is_err());
+    }
+
+    #[test]
+    fn every_question_needs_an_answer_of_its_kind() {
+        assert_eq!(check_answers(&survey(), &answers(5, 1, "ok")), None);
+        assert!(check_answers(&survey(), &answers(5, 1, "ok")[..2]).is_some());
+        assert!(check_answers(&survey(), &answers(0, 1, "ok")).is_some());
+        assert!(check_answers(&survey(), &answers(6, 1, "ok")).is_some());
+        assert!(check_answers(&survey(), &answers(3, 2, "ok")).is_some());
+        let too_long = "a".repeat(MAX_SURVEY_TEXT_LENGTH + 1);
+        assert!(check_answers(&survey(), &answers(3, 0, &too_long)).is_some());
+        let mut swapped = answers(3, 0, "ok");
+        swapped.swap(0, 1);
+        assert!(check_answers(&survey(), &swapped).is_some());
+    }
+
+    #[test]
+    fn players_only_fill_in_the_survey_once() {
+        let (_, first) = response(10, 1, answers(3, 0, "first"));
+        let (_, again) = response(11, 1, answers(4, 0, "again"));
+        assert_eq!(check_single_response(&first, &[]), None);
+        assert!(check_single_response(&again, &[first.clone()]).is_some());
+        // responses to the surveys of other sessions don't count
+        let other_session = SurveyResponse {
+            session: hash(9),
+            ..first
+        };
+        assert_eq!(check_single_response(&again, &[other_session]), None);
+    }
+
+    #[test]
+    fn results_count_one_valid_response_per_player() {
+        let players = vec![agent(1), agent(2), agent(3)];
+        let responses = vec![
+            response(30, 1, answers(1, 1, "late")),
+            response(10, 1, answers(3, 0, "first")),
+            response(20, 2, answers(4, 0, "second")),
+            // answers don't fit the survey
+            response(40, 3, answers(9, 0, "invalid")),
+            // not a player of the session
+            response(50, 4, answers(5, 1, "outsider")),
+        ];
+        let results = survey_results(&survey(), &players, responses);
+        assert_eq!(results.responses, 2);
+        assert_eq!(results.questions[0].mean, Some(3.5));
+        assert!(results.questions[0].choice_counts.is_empty());
+        assert_eq!(results.questions[1].mean, None);
+        assert_eq!(results.questions[1].choice_counts, vec![2, 0]);
+        assert_eq!(
+            results.questions[2].texts,
+            vec![String::from("first"), String::from("second")]
+        );
+    }
+
+    #[test]
+    fn results_without_responses_have_no_mean() {
+        let results = survey_results(&survey(), &[agent(1)], vec![]);
+        assert_eq!(results.responses, 0);
+        assert_eq!(results.questions[0].mean, None);
+        assert_eq!(results.questions[1].choice_counts, vec![0, 0]);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    game_move::GameMove,
+    game_session::{PlayerStats, ResourceAmount},
+};
+use hdk::prelude::*;
+
+/// Defines what happens with the taxes collected during the round
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum Redistribution {
+    // taxes are split equally between all players
+    Equal,
+    // taxes are put back into the resource stock
+    Reinvest,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct TaxationParams {
+    // share of every player's take that is collected as tax, from 0.0 to 1.0
+    pub tax_rate: f32,
+    pub redistribution: Redistribution,
+}
+
+/// Makes sure that the taxation makes sense for the game
+pub fn check_taxation(params: &TaxationParams) -> ExternResult<()> {
+    if !(0.0..=1.0).contains(&params.tax_rate) {
+        return Err(WasmError::Guest(format!(
+            "Tax rate has to be between 0.0 and 1.0, but it is {}",
+            params.tax_rate
+        )));
+    }
+    Ok(())
+}
+
+/// Amounts of resources that went through the public pool during the round
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaxationResult {
+    // total amount of taxes collected from the players
+    pub taxed: ResourceAmount,
+    // amount of taxes given back to the players
+    pub redistributed: ResourceAmount,
+    // amount of taxes put back into the resource stock
+    pub reinvested: ResourceAmount,
+}
+
+/// Collects taxes from the resources every player took with their move
+/// and redistributes them according to the taxation params.
+/// NOTE: taxes are rounded down for every move, and if the pool can't be split
+/// equally between players, the remainder is reinvested into the resource stock,
+/// so no resources are lost or created on the way.
+pub fn apply_taxation(
+    player_stats: &mut PlayerStats,
+    player_moves: &[GameMove],
+    taxation: &TaxationParams,
+    players: &[AgentPubKey],
+) -> TaxationResult {
+    let mut taxed = 0;
+    for m in player_moves {
+        let tax = (m.resource_amount as f32 * taxation.tax_rate) as ResourceAmount;
+        *player_stats.entry(m.owner.clone()).or_insert(0) -= tax;
+        taxed += tax;
+    }
+
+    match taxation.redistribution {
+        Redistribution::Equal if !players.is_empty() => {
+            let share = taxed / players.len() as ResourceAmount;
+            for p in players {
+                *player_stats.entry(p.clone()).or_insert(0) += share;
+            }
+            let redistributed = share * players.len() as ResourceAmount;
+            TaxationResult {
+                taxed,
+                redistributed,
+                reinvested: taxed - redistributed,
+            }
+        }
+        _ => TaxationResult {
+            taxed,
+            redistributed: 0,
+            reinvested: taxed,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move, stats};
+
+    fn taxation(tax_rate: f32, redistribution: Redistribution) -> TaxationParams {
+        TaxationParams {
+            tax_rate,
+            redistribution,
+        }
+    }
+
+    #[test]
+    fn tax_rate_has_to_be_a_share() {
+        assert!(check_taxation(&taxation(0.0, Redistribution::Equal)).is_ok());
+        assert!(check_taxation(&taxation(1.0, Redistribution::Reinvest)).is_ok());
+        assert!(check_taxation(&taxation(-0.1, Redistribution::Equal)).is_err());
+        assert!(check_taxation(&taxation(1.1, Redistribution::Equal)).is_err());
+        assert!(check_taxation(&taxation(f32::NAN, Redistribution::Equal)).is_err());
+    }
+
+    #[test]
+    fn taxes_are_split_equally_and_the_remainder_is_reinvested() {
+        let players = vec![agent(1), agent(2), agent(3)];
+        let mut player_stats = stats(&[(1, 10), (2, 7), (3, 0)]);
+        let moves = vec![game_move(1, 10), game_move(2, 7), game_move(3, 0)];
+        let result = apply_taxation(
+            &mut player_stats,
+            &moves,
+            &taxation(0.5, Redistribution::Equal),
+            &players,
+        );
+        // taxes are rounded down for every move: 5 + 3
+        assert_eq!(
+            result,
+            TaxationResult {
+                taxed: 8,
+                redistributed: 6,
+                reinvested: 2,
+            }
+        );
+        assert_eq!(player_stats, stats(&[(1, 7), (2, 6), (3, 2)]));
+    }
+
+    #[test]
+    fn reinvested_taxes_go_back_into_the_stock() {
+        let players = vec![agent(1), agent(2)];
+        let mut player_stats = stats(&[(1, 10), (2, 4)]);
+        let moves = vec![game_move(1, 10), game_move(2, 4)];
+        let result = apply_taxation(
+            &mut player_stats,
+            &moves,
+            &taxation(0.5, Redistribution::Reinvest),
+            &players,
+        );
+        assert_eq!(
+            result,
+            TaxationResult {
+                taxed: 7,
+                redistributed: 0,
+                reinvested: 7,
+            }
+        );
+        assert_eq!(player_stats, stats(&[(1, 5), (2, 2)]));
+    }
+}
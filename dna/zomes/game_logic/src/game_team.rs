@@ -0,0 +1,250 @@
+use crate::{
+    game_move::GameMove,
+    game_patches::split_in_proportion,
+    game_round::GameRound,
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+pub type TeamId = u32;
+// Alias to avoid writing the generic type specification every time
+// Every player can be in one team at most
+pub type Teams = BTreeMap<AgentPubKey, TeamId>;
+// Same as PlayerStats, only for teams
+pub type TeamStats = BTreeMap<TeamId, ResourceAmount>;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub struct TeamParams {
+    // max amount of resources all players of a team can take in a single round.
+    // If they ask for more together, their takes are reduced to the cap when
+    // the round is closed. None means no cap
+    pub team_cap: Option<ResourceAmount>,
+    // if true, everything players of a team take during the round
+    // is split equally between them
+    pub redistribute_within_team: bool,
+}
+
+/// Makes sure that the team params make sense for the game
+pub fn check_team_params(params: &TeamParams) -> ExternResult<()> {
+    match params.team_cap {
+        Some(team_cap) if team_cap <= 0 => Err(WasmError::Guest(format!(
+            "Team cap has to be > 0, but it is {}",
+            team_cap
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Makes sure that every player from the team assignment plays in the game
+pub fn check_teams(teams: &Teams, players: &[AgentPubKey]) -> ExternResult<()> {
+    for player in teams.keys() {
+        if !players.contains(player) {
+            return Err(WasmError::Guest(format!(
+                "Can't assign {:?} to a team: they aren't a player of this game",
+                player
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Sums up stats of the players for every team they're in.
+/// Players without a team aren't counted
+pub fn team_stats(player_stats: &PlayerStats, teams: &Teams) -> TeamStats {
+    let mut stats = TeamStats::new();
+    for (player, team) in teams {
+        *stats.entry(*team).or_insert(0) += *player_stats.get(player).unwrap_or(&0);
+    }
+    stats
+}
+
+/// Orders teams from the one with most resources to the one with least
+pub fn team_leaderboard(team_stats: &TeamStats) -> Vec<(TeamId, ResourceAmount)> {
+    let mut leaderboard: Vec<(TeamId, ResourceAmount)> =
+        team_stats.iter().map(|(t, r)| (*t, *r)).collect();
+    leaderboard.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    leaderboard
+}
+
+/// Splits what players of every team got during the round equally between them.
+/// NOTE: this fn is used when calculating the round state, so it has to give the
+/// same result for every agent:
+/// - only players who are still active share the team's resources
+/// - if the resources can't be split equally, the remainder goes one by one
+///   to the team's players in the order of their pub keys
+pub fn redistribute_within_teams(
+    player_stats: &mut PlayerStats,
+    prev_stats: &PlayerStats,
+    teams: &Teams,
+    players: &[AgentPubKey],
+) {
+    let mut members: BTreeMap<TeamId, Vec<AgentPubKey>> = BTreeMap::new();
+    for (player, team) in teams {
+        if players.contains(player) {
+            members.entry(*team).or_default().push(player.clone());
+        }
+    }
+    for team_players in members.values() {
+        let mut pool = 0;
+        for p in team_players {
+            let gained = player_stats.get(p).unwrap_or(&0) - prev_stats.get(p).unwrap_or(&0);
+            *player_stats.entry(p.clone()).or_insert(0) -= gained;
+            pool += gained;
+        }
+        let share = pool.div_euclid(team_players.len() as ResourceAmount);
+        let remainder = pool.rem_euclid(team_players.len() as ResourceAmount);
+        // BTreeMap iterates in the order of keys, so team players are already sorted
+        for (i, p) in team_players.iter().enumerate() {
+            let extra = if (i as ResourceAmount) < remainder {
+                1
+            } else {
+                0
+            };
+            *player_stats.entry(p.clone()).or_insert(0) += share + extra;
+        }
+    }
+}
+
+/// Reduces the takes of every team whose players together asked for more resources
+/// than the team cap, so that the team takes exactly the cap.
+/// NOTE: this fn is used both when calculating the round state and in it's
+/// validation, so it has to give the same result for every agent. Same as with
+/// patches, the cap is split in proportion to what every player asked for, and
+/// what is left after the rounding goes to the players in the order of their pub keys
+pub fn clamp_takes_to_team_cap(
+    mut player_moves: Vec<GameMove>,
+    team_cap: ResourceAmount,
+    teams: &Teams,
+) -> Vec<GameMove> {
+    let mut order: Vec<usize> = (0..player_moves.len()).collect();
+    order.sort_by(|a, b| player_moves[*a].owner.cmp(&player_moves[*b].owner));
+    let mut team_ids: Vec<TeamId> = teams.values().cloned().collect();
+    team_ids.sort();
+    team_ids.dedup();
+    for team in team_ids {
+        let in_team: Vec<usize> = order
+            .iter()
+            .cloned()
+            .filter(|i| teams.get(&player_moves[*i].owner) == Some(&team))
+            .collect();
+        let asked: ResourceAmount = in_team
+            .iter()
+            .map(|i| player_moves[*i].resource_amount)
+            .sum();
+        if asked > team_cap {
+            split_in_proportion(&mut player_moves, &in_team, asked, team_cap);
+        }
+    }
+    player_moves
+}
+
+/// Checks that the move doesn't take more than the team cap on it's own. Takes of
+/// the whole team are clamped to the cap when the round is closed, since a move
+/// can't be checked against the moves of the teammates in validation.
+/// Returns None if the move is within the cap
+pub fn validate_team_cap(
+    game_move: &GameMove,
+    game_round: &GameRound,
+    game_session: &GameSession,
+) -> Option<ValidateCallbackResult> {
+    let team_cap = match game_round.state.game_params.teams {
+        Some(TeamParams {
+            team_cap: Some(team_cap),
+            ..
+        }) => team_cap,
+        _ => return None,
+    };
+    if game_session.teams.contains_key(&game_move.owner) && game_move.resource_amount > team_cap {
+        return Some(ValidateCallbackResult::Invalid(format!(
+            "GameMove can't take more than the team cap of {} resources, but it takes {}",
+            team_cap, game_move.resource_amount
+        )));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move, stats};
+
+    fn teams(assignment: &[(u8, TeamId)]) -> Teams {
+        assignment.iter().map(|(n, t)| (agent(*n), *t)).collect()
+    }
+
+    #[test]
+    fn team_params_and_members_have_to_fit_the_game() {
+        let params = |team_cap| TeamParams {
+            team_cap,
+            redistribute_within_team: false,
+        };
+        assert!(check_team_params(&params(None)).is_ok());
+        assert!(check_team_params(&params(Some(1))).is_ok());
+        assert!(check_team_params(&params(Some(0))).is_err());
+        let players = vec![agent(1), agent(2)];
+        assert!(check_teams(&teams(&[(1, 0), (2, 1)]), &players).is_ok());
+        assert!(check_teams(&teams(&[(1, 0), (3, 1)]), &players).is_err());
+    }
+
+    #[test]
+    fn team_stats_add_up_and_teams_are_ranked() {
+        let player_stats = stats(&[(1, 5), (2, 7), (3, 4), (4, 9)]);
+        // player 4 doesn't have a team
+        let team_stats = team_stats(&player_stats, &teams(&[(1, 0), (2, 1), (3, 1)]));
+        let expected: TeamStats = vec![(0, 5), (1, 11)].into_iter().collect();
+        assert_eq!(team_stats, expected);
+        assert_eq!(team_leaderboard(&team_stats), vec![(1, 11), (0, 5)]);
+    }
+
+    #[test]
+    fn teams_with_the_same_stats_are_ranked_by_id() {
+        let team_stats: TeamStats = vec![(2, 5), (1, 5), (0, 3)].into_iter().collect();
+        assert_eq!(team_leaderboard(&team_stats), vec![(1, 5), (2, 5), (0, 3)]);
+    }
+
+    #[test]
+    fn team_takes_are_split_equally_with_the_remainder_in_pub_key_order() {
+        let players = vec![agent(1), agent(2), agent(3), agent(4)];
+        let prev_stats = stats(&[(1, 10), (2, 10), (3, 10), (4, 10)]);
+        // team 0 gained 3 + 1 + 3, player 4 plays for themselves
+        let mut player_stats = stats(&[(1, 13), (2, 11), (3, 13), (4, 15)]);
+        redistribute_within_teams(
+            &mut player_stats,
+            &prev_stats,
+            &teams(&[(1, 0), (2, 0), (3, 0)]),
+            &players,
+        );
+        assert_eq!(player_stats, stats(&[(1, 13), (2, 12), (3, 12), (4, 15)]));
+    }
+
+    #[test]
+    fn eliminated_players_dont_share_team_takes() {
+        // player 2 was eliminated, so only player 1 is left in the team
+        let players = vec![agent(1)];
+        let prev_stats = stats(&[(1, 10), (2, 0)]);
+        let mut player_stats = stats(&[(1, 14), (2, 0)]);
+        redistribute_within_teams(
+            &mut player_stats,
+            &prev_stats,
+            &teams(&[(1, 0), (2, 0)]),
+            &players,
+        );
+        assert_eq!(player_stats, stats(&[(1, 14), (2, 0)]));
+    }
+
+    #[test]
+    fn team_takes_are_clamped_to_the_cap() {
+        // team 0 asks for 8 with a cap of 5, team 1 and player 4 stay as they are
+        let moves = vec![
+            game_move(2, 6),
+            game_move(1, 2),
+            game_move(3, 4),
+            game_move(4, 9),
+        ];
+        let clamped = clamp_takes_to_team_cap(moves, 5, &teams(&[(1, 0), (2, 0), (3, 1)]));
+        let takes: Vec<ResourceAmount> = clamped.iter().map(|m| m.resource_amount).collect();
+        // 5 * 6 / 8 = 3 and 5 * 2 / 8 = 1, the one left goes to player 1
+        assert_eq!(takes, vec![3, 2, 4, 9]);
+    }
+}
@@ -0,0 +1,471 @@
+use crate::{
+    game_round::{check_round_is_open, GameRound},
+    game_session::{GameSession, PlayerStats, ResourceAmount},
+    utils::{must_get_entry_struct, try_from_element, try_get_and_convert},
+};
+use hdk::prelude::*;
+
+pub const TRADE_OFFER_LINK_TAG: &str = "TRADE_OFFER";
+pub const TRADE_ACCEPTANCE_LINK_TAG: &str = "TRADE_ACCEPTANCE";
+
+/// Offer to give give_amount of owner's resources to another player
+/// in exchange for ask_amount of their resources in the next round.
+/// E.g. "give 5 for 3 next round" is an offer with give_amount 5 and ask_amount 3.
+#[hdk_entry(id = "trade_offer", visibility = "public")]
+#[derive(Clone)]
+pub struct TradeOffer {
+    pub owner: AgentPubKey,
+    pub session: EntryHash,
+    // round during which the offer can be accepted
+    pub round_hash: EntryHash,
+    // player to whom the offer is made. None means anyone can accept it
+    pub recipient: Option<AgentPubKey>,
+    pub give_amount: ResourceAmount,
+    pub ask_amount: ResourceAmount,
+}
+
+#[hdk_entry(id = "trade_acceptance", visibility = "public")]
+#[derive(Clone)]
+pub struct TradeAcceptance {
+    pub owner: AgentPubKey,
+    pub offer: EntryHash,
+}
+
+/// Resources that one player owes to another after accepting a trade offer.
+/// Debts are paid when the next round is closed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TradeDebt {
+    pub debtor: AgentPubKey,
+    pub creditor: AgentPubKey,
+    pub amount: ResourceAmount,
+}
+
+/// TradeOffer together with all the acceptances it got
+#[derive(Clone, Debug)]
+pub struct OfferedTrade {
+    pub offer_hash: EntryHash,
+    pub offer: TradeOffer,
+    pub acceptances: Vec<(EntryHash, TradeAcceptance)>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TradeOfferInput {
+    pub round_hash: EntryHash,
+    pub recipient: Option<AgentPubKey>,
+    pub give_amount: ResourceAmount,
+    pub ask_amount: ResourceAmount,
+}
+
+/// Creates a new trade offer for the given round and links it from the game session
+pub fn new_trade_offer(input: TradeOfferInput) -> ExternResult<EntryHash> {
+    let game_round: GameRound =
+        try_get_and_convert(input.round_hash.clone(), GetOptions::latest())?;
+    check_round_is_open(input.round_hash.clone(), "make a TradeOffer")?;
+    let trade_offer = TradeOffer {
+        owner: agent_info()?.agent_latest_pubkey,
+        session: game_round.session.clone(),
+        round_hash: input.round_hash,
+        recipient: input.recipient,
+        give_amount: input.give_amount,
+        ask_amount: input.ask_amount,
+    };
+    create_entry(&trade_offer)?;
+    let trade_offer_entry_hash = hash_entry(&trade_offer)?;
+
+    // Link from the game session so that every player can discover the offer
+    create_link(
+        game_round.session,
+        trade_offer_entry_hash.clone(),
+        LinkTag::new(String::from(TRADE_OFFER_LINK_TAG)),
+    )?;
+
+    // Return the hash of the offer because players would need it to accept it
+    Ok(trade_offer_entry_hash)
+}
+
+/// Accepts the trade offer and links the acceptance from it
+pub fn accept_trade_offer(offer_hash: EntryHash) -> ExternResult<HeaderHash> {
+    let trade_offer: TradeOffer = try_get_and_convert(offer_hash.clone(), GetOptions::latest())?;
+    check_round_is_open(trade_offer.round_hash, "accept a TradeOffer")?;
+    let trade_acceptance = TradeAcceptance {
+        owner: agent_info()?.agent_latest_pubkey,
+        offer: offer_hash.clone(),
+    };
+    create_entry(&trade_acceptance)?;
+    let trade_acceptance_entry_hash = hash_entry(&trade_acceptance)?;
+
+    create_link(
+        offer_hash,
+        trade_acceptance_entry_hash,
+        LinkTag::new(String::from(TRADE_ACCEPTANCE_LINK_TAG)),
+    )
+}
+
+/// Get all trade offers made during the round, together with their entry hashes
+pub fn get_trade_offers_for_round(
+    round_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, TradeOffer)>> {
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let links = get_links(
+        game_round.session,
+        Some(LinkTag::new(String::from(TRADE_OFFER_LINK_TAG))),
+    )?;
+    let mut offers: Vec<(EntryHash, TradeOffer)> = vec![];
+    for link in links.into_inner() {
+        let offer: TradeOffer = try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+        // offers of all rounds are linked from the session, so we filter out the others
+        if offer.round_hash == round_hash {
+            offers.push((link.target, offer));
+        }
+    }
+    Ok(offers)
+}
+
+/// Get all trade offers made during the round together with their acceptances
+pub fn get_offered_trades_for_round(round_hash: EntryHash) -> ExternResult<Vec<OfferedTrade>> {
+    let mut trades: Vec<OfferedTrade> = vec![];
+    for (offer_hash, offer) in get_trade_offers_for_round(round_hash)? {
+        let links = get_links(
+            offer_hash.clone(),
+            Some(LinkTag::new(String::from(TRADE_ACCEPTANCE_LINK_TAG))),
+        )?;
+        let mut acceptances: Vec<(EntryHash, TradeAcceptance)> = vec![];
+        for link in links.into_inner() {
+            let acceptance: TradeAcceptance =
+                try_get_and_convert(link.target.clone(), GetOptions::latest())?;
+            acceptances.push((link.target, acceptance));
+        }
+        trades.push(OfferedTrade {
+            offer_hash,
+            offer,
+            acceptances,
+        });
+    }
+    Ok(trades)
+}
+
+/// Moves resources between players according to the debts from the last round
+/// and trade offers accepted during this round. Returns a tuple of (net amount of
+/// resources every player received through trades, debts to be paid next round,
+/// hashes of the settled offers together with the acceptances that won them).
+/// NOTE: this fn is used when calculating the round state, so it has to give the
+/// same result for every agent:
+/// - debts are paid first, but nobody can pay more than they have: the rest of the
+///   debt is carried forward to the next round
+/// - trade offers are settled in the order of their entry hashes
+/// - if an offer got several acceptances, the one with the smallest entry hash wins
+/// - offers that the owner can't afford anymore are skipped, since validation can
+///   only check every offer against the owner's balance on it's own
+pub fn settle_trades(
+    player_stats: &mut PlayerStats,
+    debts: &[TradeDebt],
+    mut trades: Vec<OfferedTrade>,
+) -> (PlayerStats, Vec<TradeDebt>, Vec<(EntryHash, EntryHash)>) {
+    let mut transfers = PlayerStats::new();
+    let mut new_debts = pay_debts(player_stats, &mut transfers, debts);
+    let mut settled: Vec<(EntryHash, EntryHash)> = vec![];
+
+    trades.sort_by(|a, b| a.offer_hash.cmp(&b.offer_hash));
+    trades.dedup_by(|a, b| a.offer_hash == b.offer_hash);
+    for mut trade in trades {
+        trade.acceptances.sort_by(|a, b| a.0.cmp(&b.0));
+        let (acceptance_hash, acceptor) = match trade.acceptances.into_iter().next() {
+            Some((acceptance_hash, acceptance)) => (acceptance_hash, acceptance.owner),
+            None => continue,
+        };
+        let balance = *player_stats.get(&trade.offer.owner).unwrap_or(&0);
+        if trade.offer.give_amount > balance {
+            info!(
+                "Skipping trade offer: {:?} can't afford it anymore",
+                trade.offer.owner
+            );
+            continue;
+        }
+        transfer(
+            player_stats,
+            &mut transfers,
+            &trade.offer.owner,
+            &acceptor,
+            trade.offer.give_amount,
+        );
+        if trade.offer.ask_amount > 0 {
+            new_debts.push(TradeDebt {
+                debtor: acceptor,
+                creditor: trade.offer.owner,
+                amount: trade.offer.ask_amount,
+            });
+        }
+        settled.push((trade.offer_hash, acceptance_hash));
+    }
+    (transfers, new_debts, settled)
+}
+
+/// Loads the trades settled in the round by the hashes of their offers and acceptances,
+/// and checks that every offer was made during the round at round_hash and every
+/// acceptance accepts it's offer. Returns None if they weren't.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn must_get_settled_trades(
+    trades: &[(EntryHash, EntryHash)],
+    round_hash: &EntryHash,
+) -> ExternResult<Option<Vec<OfferedTrade>>> {
+    let mut offered_trades: Vec<OfferedTrade> = vec![];
+    for (offer_hash, acceptance_hash) in trades {
+        let offer = must_get_entry_struct::<TradeOffer>(offer_hash.clone())?;
+        let acceptance = must_get_entry_struct::<TradeAcceptance>(acceptance_hash.clone())?;
+        if &offer.round_hash != round_hash || &acceptance.offer != offer_hash {
+            return Ok(None);
+        }
+        offered_trades.push(OfferedTrade {
+            offer_hash: offer_hash.clone(),
+            offer,
+            acceptances: vec![(acceptance_hash.clone(), acceptance)],
+        });
+    }
+    Ok(Some(offered_trades))
+}
+
+/// Pays the debts in the given order out of what every debtor has, keeping track
+/// of the transfers made. Returns the part of the debts that is left unpaid
+pub fn pay_debts(
+    player_stats: &mut PlayerStats,
+    transfers: &mut PlayerStats,
+    debts: &[TradeDebt],
+) -> Vec<TradeDebt> {
+    let mut unpaid_debts: Vec<TradeDebt> = vec![];
+    for debt in debts {
+        let balance = *player_stats.get(&debt.debtor).unwrap_or(&0);
+        let amount = std::cmp::min(debt.amount, std::cmp::max(balance, 0));
+        transfer(
+            player_stats,
+            transfers,
+            &debt.debtor,
+            &debt.creditor,
+            amount,
+        );
+        if amount < debt.amount {
+            unpaid_debts.push(TradeDebt {
+                amount: debt.amount - amount,
+                ..debt.clone()
+            });
+        }
+    }
+    unpaid_debts
+}
+
+/// Moves amount of resources from one player to another, keeping track
+/// of the transfers made
+fn transfer(
+    player_stats: &mut PlayerStats,
+    transfers: &mut PlayerStats,
+    from: &AgentPubKey,
+    to: &AgentPubKey,
+    amount: ResourceAmount,
+) {
+    *player_stats.entry(from.clone()).or_insert(0) -= amount;
+    *player_stats.entry(to.clone()).or_insert(0) += amount;
+    *transfers.entry(from.clone()).or_insert(0) -= amount;
+    *transfers.entry(to.clone()).or_insert(0) += amount;
+}
+
+/// Validates creation of TradeOffer entries
+pub fn validate_create_entry_trade_offer(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let trade_offer: TradeOffer = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &trade_offer.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "TradeOffer can only be created by it's owner",
+        )));
+    }
+
+    if trade_offer.give_amount <= 0 || trade_offer.ask_amount < 0 {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "TradeOffer has to give > 0 and ask >= 0 resources, but it gives {} and asks {}",
+            trade_offer.give_amount, trade_offer.ask_amount
+        )));
+    }
+
+    let game_round = must_get_entry_struct::<GameRound>(trade_offer.round_hash.clone())?;
+    if game_round.session != trade_offer.session {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "TradeOffer's round doesn't belong to it's GameSession",
+        )));
+    }
+    let game_session = must_get_entry_struct::<GameSession>(trade_offer.session.clone())?;
+    if !game_session.players.contains(&trade_offer.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can make trade offers",
+        )));
+    }
+    if let Some(recipient) = &trade_offer.recipient {
+        if !game_session.players.contains(recipient) || recipient == &trade_offer.owner {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "TradeOffer can only be made to another GameSession player",
+            )));
+        }
+    }
+
+    // player can only give resources they already have
+    let balance = *game_round
+        .state
+        .player_stats
+        .get(&trade_offer.owner)
+        .unwrap_or(&0);
+    if trade_offer.give_amount > balance {
+        return Ok(ValidateCallbackResult::Invalid(format!(
+            "TradeOffer gives {} but the owner only has {}",
+            trade_offer.give_amount, balance
+        )));
+    }
+
+    // NOTE: new_trade_offer checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of TradeOffer entries
+pub fn validate_update_entry_trade_offer(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update TradeOffer entry",
+    )))
+}
+
+/// Validates delete of TradeOffer entries
+pub fn validate_delete_entry_trade_offer(_: ValidateData) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete TradeOffer entry",
+    )))
+}
+
+/// Validates creation of TradeAcceptance entries
+pub fn validate_create_entry_trade_acceptance(
+    data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    let trade_acceptance: TradeAcceptance = try_from_element(data.element.clone())?;
+
+    if data.element.header().author() != &trade_acceptance.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "TradeAcceptance can only be created by it's owner",
+        )));
+    }
+
+    let trade_offer = must_get_entry_struct::<TradeOffer>(trade_acceptance.offer)?;
+    if trade_offer.owner == trade_acceptance.owner {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Players can't accept their own trade offers",
+        )));
+    }
+    match &trade_offer.recipient {
+        Some(recipient) if recipient != &trade_acceptance.owner => {
+            return Ok(ValidateCallbackResult::Invalid(String::from(
+                "TradeOffer can only be accepted by it's recipient",
+            )));
+        }
+        _ => {}
+    }
+    let game_session = must_get_entry_struct::<GameSession>(trade_offer.session)?;
+    if !game_session.players.contains(&trade_acceptance.owner) {
+        return Ok(ValidateCallbackResult::Invalid(String::from(
+            "Only GameSession players can accept trade offers",
+        )));
+    }
+
+    // NOTE: accept_trade_offer checks that the round is still open
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+/// Validates update of TradeAcceptance entries
+pub fn validate_update_entry_trade_acceptance(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't update TradeAcceptance entry",
+    )))
+}
+
+/// Validates delete of TradeAcceptance entries
+pub fn validate_delete_entry_trade_acceptance(
+    _: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(
+        "Can't delete TradeAcceptance entry",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{debt, hash, stats, trade};
+
+    #[test]
+    fn accepted_offer_gives_now_and_asks_next_round() {
+        let mut player_stats = stats(&[(1, 10), (2, 10)]);
+        let (transfers, debts, settled) =
+            settle_trades(&mut player_stats, &[], vec![trade(10, 1, 5, 3, &[(20, 2)])]);
+        assert_eq!(player_stats, stats(&[(1, 5), (2, 15)]));
+        assert_eq!(transfers, stats(&[(1, -5), (2, 5)]));
+        assert_eq!(debts, vec![debt(2, 1, 3)]);
+        assert_eq!(settled, vec![(hash(10), hash(20))]);
+    }
+
+    #[test]
+    fn acceptance_with_the_smallest_hash_wins() {
+        let mut player_stats = stats(&[(1, 10), (2, 0), (3, 0)]);
+        let (_, debts, settled) = settle_trades(
+            &mut player_stats,
+            &[],
+            vec![trade(10, 1, 5, 3, &[(21, 2), (20, 3)])],
+        );
+        assert_eq!(player_stats, stats(&[(1, 5), (2, 0), (3, 5)]));
+        assert_eq!(debts, vec![debt(3, 1, 3)]);
+        assert_eq!(settled, vec![(hash(10), hash(20))]);
+    }
+
+    #[test]
+    fn unaffordable_and_unaccepted_offers_are_skipped() {
+        let mut player_stats = stats(&[(1, 6), (2, 0)]);
+        let trades = vec![
+            trade(10, 1, 4, 1, &[(20, 2)]),
+            trade(11, 1, 4, 1, &[(21, 2)]),
+            trade(12, 1, 1, 1, &[]),
+        ];
+        let (_, debts, settled) = settle_trades(&mut player_stats, &[], trades);
+        assert_eq!(player_stats, stats(&[(1, 2), (2, 4)]));
+        assert_eq!(debts, vec![debt(2, 1, 1)]);
+        assert_eq!(settled, vec![(hash(10), hash(20))]);
+    }
+
+    #[test]
+    fn debts_are_paid_before_new_trades() {
+        let mut player_stats = stats(&[(1, 0), (2, 4)]);
+        let (transfers, debts, _) = settle_trades(
+            &mut player_stats,
+            &[debt(2, 1, 3)],
+            vec![trade(10, 1, 3, 0, &[(20, 2)])],
+        );
+        assert_eq!(player_stats, stats(&[(1, 0), (2, 4)]));
+        assert_eq!(transfers, stats(&[(1, 0), (2, 0)]));
+        assert!(debts.is_empty());
+    }
+
+    #[test]
+    fn unpaid_part_of_the_debt_is_carried_forward() {
+        let mut player_stats = stats(&[(1, 0), (2, 2)]);
+        let mut transfers = PlayerStats::new();
+        let unpaid = pay_debts(&mut player_stats, &mut transfers, &[debt(2, 1, 5)]);
+        assert_eq!(player_stats, stats(&[(1, 2), (2, 0)]));
+        assert_eq!(transfers, stats(&[(1, 2), (2, -2)]));
+        assert_eq!(unpaid, vec![debt(2, 1, 3)]);
+    }
+
+    #[test]
+    fn negative_balances_dont_pay_debts() {
+        let mut player_stats = stats(&[(1, 0), (2, -2)]);
+        let mut transfers = PlayerStats::new();
+        let unpaid = pay_debts(&mut player_stats, &mut transfers, &[debt(2, 1, 5)]);
+        assert_eq!(player_stats, stats(&[(1, 0), (2, -2)]));
+        assert_eq!(unpaid, vec![debt(2, 1, 5)]);
+    }
+}
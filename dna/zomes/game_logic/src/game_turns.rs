@@ -0,0 +1,446 @@
+use crate::{
+    game_monitoring::can_observe_take,
+    game_move::{get_moves_for_round, get_my_original_move, GameMove},
+    game_round::GameRound,
+    game_session::{GameSession, ResourceAmount},
+    game_signals::{GameSignal, YourTurnPayload},
+    game_subsistence::active_players,
+    utils::{earlier_entries_in_package, must_get_entry_struct, try_get_and_convert},
+};
+use hdk::prelude::*;
+
+/// Defines the order in which players move when they move one after another
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq)]
+pub enum TurnOrder {
+    // players move in the same order every round
+    Fixed,
+    // player who moved first in the round moves last in the next one
+    Rotating,
+}
+
+/// Calculates in which order active players move in the given round.
+/// Fixed order is the order of players in the GameSession entry
+pub fn turn_order_for_round(
+    turn_order: TurnOrder,
+    game_session: &GameSession,
+    game_round: &GameRound,
+) -> Vec<AgentPubKey> {
+    let mut players = active_players(&game_session.players, &game_round.state.eliminated_players);
+    if turn_order == TurnOrder::Rotating && !players.is_empty() {
+        let shift = game_round.round_num as usize % players.len();
+        players.rotate_left(shift);
+    }
+    players
+}
+
+/// Keeps only the moves players made in their turn, in the turn order.
+/// The move of the first player can't come after any other move, and the move
+/// of every next player has to come after the move of the player before them,
+/// so we stop at the first player who hasn't moved after the player before them yet.
+/// Players only move once in a round when they move one after another, but if
+/// several moves come after the same move, we take the one with the smallest hash,
+/// so every agent selects the same moves
+pub fn moves_in_turn_order(
+    mut moves: Vec<(EntryHash, GameMove)>,
+    turn_order: &[AgentPubKey],
+) -> Vec<(EntryHash, GameMove)> {
+    moves.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut ordered_moves: Vec<(EntryHash, GameMove)> = vec![];
+    for player in turn_order {
+        let prev_move = ordered_moves.last().map(|(hash, _)| hash.clone());
+        let position = match moves
+            .iter()
+            .position(|(_, m)| &m.owner == player && m.prev_move == prev_move)
+        {
+            Some(position) => position,
+            None => {
+                info!("Player {:?} hasn't moved in turn yet", player);
+                break;
+            }
+        };
+        ordered_moves.push(moves.remove(position));
+    }
+    ordered_moves
+}
+
+/// Get the moves the given players made in the round one after another,
+/// in the turn order, together with their hashes
+fn get_moves_in_turn(
+    round_hash: EntryHash,
+    players: &[AgentPubKey],
+) -> ExternResult<Vec<(EntryHash, GameMove)>> {
+    let mut moves: Vec<(EntryHash, GameMove)> = vec![];
+    for m in get_moves_for_round(round_hash)? {
+        if players.contains(&m.owner) {
+            moves.push((hash_entry(&m)?, m));
+        }
+    }
+    Ok(moves_in_turn_order(moves, players))
+}
+
+/// Get the hash of the move that the move of the given player has to come after:
+/// the move of the player before them in the turn order. None if the player moves
+/// first or if players move simultaneously.
+/// Fails if it isn't the player's turn yet, or if they've already moved in the round
+pub fn prev_move_in_turn(
+    round_hash: &EntryHash,
+    player: &AgentPubKey,
+) -> ExternResult<Option<EntryHash>> {
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let game_session: GameSession =
+        try_get_and_convert(game_round.session.clone(), GetOptions::latest())?;
+    let turn_order = match game_round.state.game_params.turn_order {
+        Some(turn_order) => turn_order_for_round(turn_order, &game_session, &game_round),
+        None => return Ok(None),
+    };
+    if get_my_original_move(round_hash)?.is_some() {
+        return Err(WasmError::Guest(String::from(
+            "Can't make a move: players who move one after another can only move once in a round",
+        )));
+    }
+    let earlier_players: Vec<AgentPubKey> =
+        turn_order.into_iter().take_while(|p| p != player).collect();
+    let earlier_moves = get_moves_in_turn(round_hash.clone(), &earlier_players)?;
+    if earlier_moves.len() < earlier_players.len() {
+        return Err(WasmError::Guest(format!(
+            "Can't make a move: it's {:?}'s turn to move",
+            earlier_players[earlier_moves.len()]
+        )));
+    }
+    Ok(earlier_moves.last().map(|(hash, _)| hash.clone()))
+}
+
+/// Lets the player know that it's their turn to move in the round,
+/// together with what the players who moved before them took
+pub fn signal_your_turn(
+    game_session_entry_hash: EntryHash,
+    round_entry_hash: EntryHash,
+    player: AgentPubKey,
+    earlier_moves: &[GameMove],
+) -> ExternResult<()> {
+    let signal_payload = YourTurnPayload {
+        game_session_entry_hash,
+        round_entry_hash_update: round_entry_hash,
+        earlier_moves: takes_of_moves(earlier_moves),
+    };
+    let signal = ExternIO::encode(GameSignal::YourTurn(signal_payload))?;
+    remote_signal(signal, vec![player])
+}
+
+/// Lets the first player in the turn order know that the round has started
+/// and they can move. Does nothing if players move simultaneously
+pub fn signal_first_turn(
+    game_session: &GameSession,
+    game_session_entry_hash: EntryHash,
+    game_round: &GameRound,
+    round_entry_hash: EntryHash,
+) -> ExternResult<()> {
+    let turn_order = match game_round.state.game_params.turn_order {
+        Some(turn_order) => turn_order_for_round(turn_order, game_session, game_round),
+        None => return Ok(()),
+    };
+    match turn_order.into_iter().next() {
+        Some(first_player) => {
+            signal_your_turn(game_session_entry_hash, round_entry_hash, first_player, &[])
+        }
+        None => Ok(()),
+    }
+}
+
+/// Get the moves made in the round by the players who move before the given player,
+/// in the turn order, whose takes the player is allowed to observe.
+/// Empty if players move simultaneously
+pub fn get_earlier_moves(
+    round_hash: EntryHash,
+    game_round: &GameRound,
+    game_session: &GameSession,
+    player: &AgentPubKey,
+) -> ExternResult<Vec<GameMove>> {
+    let turn_order = match game_round.state.game_params.turn_order {
+        Some(turn_order) => turn_order_for_round(turn_order, game_session, game_round),
+        None => return Ok(vec![]),
+    };
+    let earlier_players: Vec<AgentPubKey> =
+        turn_order.into_iter().take_while(|p| p != player).collect();
+    // players only see the earlier takes they're allowed to observe
+    Ok(get_moves_in_turn(round_hash, &earlier_players)?
+        .into_iter()
+        .map(|(_, m)| m)
+        .filter(|m| can_observe_take(&game_round.state.game_params.monitoring, player, &m.owner))
+        .collect())
+}
+
+/// Get the takes of the players who moved before the agent executing this fn in the
+/// round, so the UI can show them even if it has missed the YourTurn signal
+pub fn get_my_earlier_moves(
+    round_hash: EntryHash,
+) -> ExternResult<Vec<(ResourceAmount, AgentPubKey)>> {
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let game_session: GameSession =
+        try_get_and_convert(game_round.session.clone(), GetOptions::latest())?;
+    let me = agent_info()?.agent_latest_pubkey;
+    let earlier_moves = get_earlier_moves(round_hash, &game_round, &game_session, &me)?;
+    Ok(takes_of_moves(&earlier_moves))
+}
+
+/// Keeps only who took how much from the moves
+fn takes_of_moves(moves: &[GameMove]) -> Vec<(ResourceAmount, AgentPubKey)> {
+    moves
+        .iter()
+        .map(|m| (m.resource_amount, m.owner.clone()))
+        .collect()
+}
+
+/// Lets the next player in the turn order know it's their turn after
+/// the agent executing this fn has moved. Does nothing if players
+/// move simultaneously or if the agent was the last one to move
+pub fn signal_next_turn(round_hash: EntryHash) -> ExternResult<()> {
+    let game_round: GameRound = try_get_and_convert(round_hash.clone(), GetOptions::latest())?;
+    let game_session: GameSession =
+        try_get_and_convert(game_round.session.clone(), GetOptions::latest())?;
+    let turn_order = match game_round.state.game_params.turn_order {
+        Some(turn_order) => turn_order_for_round(turn_order, &game_session, &game_round),
+        None => return Ok(()),
+    };
+    let me = agent_info()?.agent_latest_pubkey;
+    let next_player = turn_order.iter().skip_while(|p| **p != me).nth(1).cloned();
+    match next_player {
+        Some(next_player) => {
+            let earlier_moves =
+                get_earlier_moves(round_hash.clone(), &game_round, &game_session, &next_player)?;
+            signal_your_turn(game_round.session, round_hash, next_player, &earlier_moves)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Checks that the move comes after the move of the player who moves just before
+/// it's owner in the turn order. That move had to come after the move of the
+/// player before them in turn, so every earlier player has moved before the owner.
+/// Returns None if it does.
+/// NOTE: this fn only uses must_get_* fns, so it's safe to use in validation
+pub fn validate_turn(
+    game_move: &GameMove,
+    game_round: &GameRound,
+    game_session: &GameSession,
+) -> ExternResult<Option<ValidateCallbackResult>> {
+    let turn_order = match game_round.state.game_params.turn_order {
+        Some(turn_order) => turn_order_for_round(turn_order, game_session, game_round),
+        None if game_move.prev_move.is_some() => {
+            return Ok(Some(ValidateCallbackResult::Invalid(String::from(
+                "Can't make a GameMove after another move when players move simultaneously",
+            ))))
+        }
+        None => return Ok(None),
+    };
+    let prev_move = match &game_move.prev_move {
+        Some(prev_move_hash) => Some(must_get_entry_struct::<GameMove>(prev_move_hash.clone())?),
+        None => None,
+    };
+    Ok(check_prev_move(game_move, prev_move.as_ref(), &turn_order))
+}
+
+/// Checks that prev_move is the move of the player who moves just before the
+/// owner of game_move in the turn order, made in the same round.
+/// Returns None if it is
+fn check_prev_move(
+    game_move: &GameMove,
+    prev_move: Option<&GameMove>,
+    turn_order: &[AgentPubKey],
+) -> Option<ValidateCallbackResult> {
+    let prev_player = turn_order
+        .iter()
+        .take_while(|p| **p != game_move.owner)
+        .last();
+    match (prev_player, prev_move) {
+        (None, None) => None,
+        (Some(player), Some(prev_move))
+            if &prev_move.owner == player && prev_move.round_hash == game_move.round_hash =>
+        {
+            None
+        }
+        (Some(player), _) => Some(ValidateCallbackResult::Invalid(format!(
+            "Can't make a GameMove: it has to come after the move of {:?}",
+            player
+        ))),
+        (None, Some(_)) => Some(ValidateCallbackResult::Invalid(String::from(
+            "Can't make a GameMove after another move: it's owner moves first",
+        ))),
+    }
+}
+
+/// Checks that the owner of the move hasn't moved in it's round before, when
+/// players move one after another. Earlier moves of the owner come in the
+/// validation package, since GameMove entries are validated with their sub chain.
+/// Returns None if they haven't
+pub fn validate_single_move_in_turn(
+    game_move: &GameMove,
+    game_round: &GameRound,
+    data: &ValidateData,
+) -> ExternResult<Option<ValidateCallbackResult>> {
+    if game_round.state.game_params.turn_order.is_none() {
+        return Ok(None);
+    }
+    match earlier_entries_in_package::<GameMove>(data)? {
+        Some(earlier_moves) => Ok(check_single_move(game_move, &earlier_moves)),
+        None => Ok(Some(ValidateCallbackResult::Invalid(String::from(
+            "Can't validate GameMove without the earlier moves of it's owner",
+        )))),
+    }
+}
+
+/// Checks that none of the earlier moves is a move of the same player in the
+/// same round. Returns None if there isn't one
+fn check_single_move(
+    game_move: &GameMove,
+    earlier_moves: &[GameMove],
+) -> Option<ValidateCallbackResult> {
+    if earlier_moves
+        .iter()
+        .any(|m| m.owner == game_move.owner && m.round_hash == game_move.round_hash)
+    {
+        return Some(ValidateCallbackResult::Invalid(String::from(
+            "Can't make a GameMove: players who move one after another can only move once in a round",
+        )));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{agent, game_move, hash};
+    use crate::{
+        game_attributes::PlayersAttributes,
+        game_round::RoundState,
+        game_session::{GameParams, PlayerStats, SessionState},
+        game_team::{TeamStats, Teams},
+    };
+
+    fn move_after(owner: u8, prev_move: Option<u8>) -> GameMove {
+        GameMove {
+            prev_move: prev_move.map(hash),
+            ..game_move(owner, 1)
+        }
+    }
+
+    fn session_and_round(round_num: u32, eliminated: &[u8]) -> (GameSession, GameRound) {
+        let players = vec![agent(3), agent(1), agent(2)];
+        let mut state = RoundState::initial(&GameParams::default(), &players);
+        state.eliminated_players = eliminated.iter().map(|n| agent(*n)).collect();
+        let game_session = GameSession {
+            owner: agent(3),
+            status: SessionState::InProgress,
+            game_params: GameParams::default(),
+            players,
+            scores: PlayerStats::new(),
+            anchor: hash(0),
+            player_attributes: PlayersAttributes::new(),
+            teams: Teams::new(),
+            team_scores: TeamStats::new(),
+        };
+        let game_round = GameRound::new(round_num, hash(1), state);
+        (game_session, game_round)
+    }
+
+    #[test]
+    fn fixed_order_is_the_order_of_session_players() {
+        let (game_session, game_round) = session_and_round(4, &[]);
+        assert_eq!(
+            turn_order_for_round(TurnOrder::Fixed, &game_session, &game_round),
+            vec![agent(3), agent(1), agent(2)]
+        );
+    }
+
+    #[test]
+    fn rotating_order_shifts_every_round() {
+        let (game_session, game_round) = session_and_round(1, &[]);
+        assert_eq!(
+            turn_order_for_round(TurnOrder::Rotating, &game_session, &game_round),
+            vec![agent(1), agent(2), agent(3)]
+        );
+        let (game_session, game_round) = session_and_round(3, &[]);
+        assert_eq!(
+            turn_order_for_round(TurnOrder::Rotating, &game_session, &game_round),
+            vec![agent(3), agent(1), agent(2)]
+        );
+    }
+
+    #[test]
+    fn eliminated_players_dont_get_a_turn() {
+        let (game_session, game_round) = session_and_round(1, &[1]);
+        assert_eq!(
+            turn_order_for_round(TurnOrder::Rotating, &game_session, &game_round),
+            vec![agent(2), agent(3)]
+        );
+    }
+
+    #[test]
+    fn moves_are_taken_while_players_move_in_turn() {
+        let turn_order = vec![agent(1), agent(2), agent(3)];
+        let owners = |moves: Vec<(EntryHash, GameMove)>| -> Vec<AgentPubKey> {
+            moves.into_iter().map(|(_, m)| m.owner).collect()
+        };
+        let in_turn = vec![
+            (hash(13), move_after(3, Some(12))),
+            (hash(11), move_after(1, None)),
+            (hash(12), move_after(2, Some(11))),
+        ];
+        assert_eq!(
+            owners(moves_in_turn_order(in_turn, &turn_order)),
+            turn_order
+        );
+        // player 3 moved right after player 1, so their move doesn't count
+        let out_of_turn = vec![
+            (hash(11), move_after(1, None)),
+            (hash(12), move_after(2, Some(11))),
+            (hash(13), move_after(3, Some(11))),
+        ];
+        assert_eq!(
+            owners(moves_in_turn_order(out_of_turn, &turn_order)),
+            vec![agent(1), agent(2)]
+        );
+        // player 2 hasn't moved yet, so player 3 has to wait for them
+        let missing = vec![
+            (hash(11), move_after(1, None)),
+            (hash(13), move_after(3, Some(11))),
+        ];
+        assert_eq!(
+            owners(moves_in_turn_order(missing, &turn_order)),
+            vec![agent(1)]
+        );
+    }
+
+    #[test]
+    fn move_has_to_come_after_the_move_of_the_player_before() {
+        let turn_order = vec![agent(1), agent(2), agent(3)];
+        let first = move_after(1, None);
+        let second = move_after(2, Some(11));
+        assert!(check_prev_move(&first, None, &turn_order).is_none());
+        assert!(check_prev_move(&second, Some(&first), &turn_order).is_none());
+        assert!(check_prev_move(&move_after(3, Some(12)), Some(&second), &turn_order).is_none());
+        // player 3 can't skip player 2
+        assert!(check_prev_move(&move_after(3, Some(11)), Some(&first), &turn_order).is_some());
+        // only the first player moves without coming after anyone
+        assert!(check_prev_move(&move_after(2, None), None, &turn_order).is_some());
+        assert!(check_prev_move(&move_after(1, Some(12)), Some(&second), &turn_order).is_some());
+        // the move before has to be made in the same round
+        let other_round = GameMove {
+            round_hash: hash(9),
+            ..first
+        };
+        assert!(check_prev_move(&second, Some(&other_round), &turn_order).is_some());
+    }
+
+    #[test]
+    fn players_only_move_once_in_a_round() {
+        let earlier_moves = vec![GameMove {
+            round_hash: hash(9),
+            ..move_after(1, None)
+        }];
+        // the earlier move was made in another round
+        assert!(check_single_move(&move_after(1, None), &earlier_moves).is_none());
+        let earlier_moves = vec![move_after(1, None)];
+        assert!(check_single_move(&move_after(1, None), &earlier_moves).is_some());
+    }
+}
@@ -2,19 +2,67 @@ use hdk::prelude::*;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
+mod game_attributes;
+mod game_auction;
+mod game_chat;
 mod game_code;
+mod game_facilitator;
+mod game_governance;
+mod game_investment;
+mod game_journal;
+mod game_monitoring;
 mod game_move;
+mod game_pact;
+mod game_patches;
+mod game_pledge;
+mod game_pollution;
+mod game_quota;
 mod game_round;
+mod game_sanction;
+mod game_seed;
 mod game_session;
 mod game_signals;
+mod game_subsistence;
+mod game_survey;
+mod game_taxation;
+mod game_team;
+mod game_trade;
+mod game_turns;
 mod player_profile;
+#[cfg(test)]
+mod test_utils;
 mod utils;
 
 pub use crate::{
-    game_move::GameMoveInput,
+    game_attributes::{AttributesAssignment, PlayerAttributes, RandomAttributesParams},
+    game_auction::{AuctionParams, BidInput},
+    game_chat::{
+        ChatHistoryInput, ChatMessage, ChatMessageInput, CommunicationParams, CommunicationSchedule,
+    },
+    game_facilitator::{
+        FacilitatedLobby, FacilitatedSession, FacilitatedStartInput, SessionControlAction,
+        SessionControlInput,
+    },
+    game_governance::{ParamsChange, Proposal, ProposalInput, VoteInput},
+    game_investment::InvestmentParams,
+    game_journal::{JournalNote, JournalNoteInput, PublishedNote},
+    game_monitoring::{MonitoringParams, StockMonitoring, TakesMonitoring},
+    game_move::{GameMove, GameMoveInput},
+    game_pact::{Pact, PactEnforcement, PactInput},
+    game_patches::{PatchParams, PatchesParams},
+    game_pledge::{PlayerTrust, PledgeInput, PledgeRecord},
+    game_pollution::{AbatementInput, PollutionParams},
+    game_quota::{QuotaOrder, QuotaOrderInput, QuotaParams},
     game_round::GameRoundInfo,
-    game_session::GameSession,
+    game_sanction::GameSanctionInput,
+    game_session::{GameParams, GameSession, ResourceAmount, StartGameSessionInput},
     game_signals::GameSignal,
+    game_survey::{
+        QuestionKind, SurveyAnswer, SurveyInput, SurveyParams, SurveyQuestion, SurveyResults,
+    },
+    game_team::{TeamId, TeamParams},
+    game_trade::{TradeOffer, TradeOfferInput},
+    game_turns::TurnOrder,
     player_profile::{JoinGameInfo, PlayerProfile},
 };
 
@@ -34,7 +82,53 @@ entry_defs![
     // GameRound Holochain entry definition callback
     game_round::GameRound::entry_def(),
     // GameMove Holochain entry definition callback
-    game_move::GameMove::entry_def()
+    game_move::GameMove::entry_def(),
+    // GameSanction Holochain entry definition callback
+    game_sanction::GameSanction::entry_def(),
+    // Abatement Holochain entry definition callback
+    game_pollution::Abatement::entry_def(),
+    // Proposal Holochain entry definition callback
+    game_governance::Proposal::entry_def(),
+    // Vote Holochain entry definition callback
+    game_governance::Vote::entry_def(),
+    // TradeOffer Holochain entry definition callback
+    game_trade::TradeOffer::entry_def(),
+    // TradeAcceptance Holochain entry definition callback
+    game_trade::TradeAcceptance::entry_def(),
+    // QuotaOrder Holochain entry definition callback
+    game_quota::QuotaOrder::entry_def(),
+    // QuotaFill Holochain entry definition callback
+    game_quota::QuotaFill::entry_def(),
+    // Pact Holochain entry definition callback
+    game_pact::Pact::entry_def(),
+    // PactSignature Holochain entry definition callback
+    game_pact::PactSignature::entry_def(),
+    // Pledge Holochain entry definition callback
+    game_pledge::Pledge::entry_def(),
+    // ChatMessage Holochain entry definition callback
+    game_chat::ChatMessage::entry_def(),
+    // JournalNote Holochain entry definition callback
+    game_journal::JournalNote::entry_def(),
+    // PublishedNote Holochain entry definition callback
+    game_journal::PublishedNote::entry_def(),
+    // SurveyResponse Holochain entry definition callback
+    game_survey::SurveyResponse::entry_def(),
+    // FacilitatorRights Holochain entry definition callback
+    game_facilitator::FacilitatorRights::entry_def(),
+    // SessionControl Holochain entry definition callback
+    game_facilitator::SessionControl::entry_def(),
+    // SeedSecret Holochain entry definition callback
+    game_seed::SeedSecret::entry_def(),
+    // SeedCommitment Holochain entry definition callback
+    game_seed::SeedCommitment::entry_def(),
+    // SeedReveal Holochain entry definition callback
+    game_seed::SeedReveal::entry_def(),
+    // BidSecret Holochain entry definition callback
+    game_auction::BidSecret::entry_def(),
+    // BidCommitment Holochain entry definition callback
+    game_auction::BidCommitment::entry_def(),
+    // BidReveal Holochain entry definition callback
+    game_auction::BidReveal::entry_def()
 ];
 
 #[hdk_extern]
@@ -124,16 +218,251 @@ pub fn start_game_session_with_code(game_code: String) -> ExternResult<EntryHash
     game_session::start_game_session_with_code(game_code)
 }
 
+/// Creates a GameSession entry with the params chosen by the host
+#[hdk_extern]
+pub fn start_game_session_with_params(input: StartGameSessionInput) -> ExternResult<EntryHash> {
+    game_session::start_game_session_with_params(
+        input.game_code,
+        input.game_params,
+        input.player_attributes,
+        input.teams,
+    )
+}
+
+/// Creates the given number of lobbies with new game codes, and makes
+/// the agent who calls this fn their facilitator
+#[hdk_extern]
+pub fn create_lobby_batch(count: u32) -> ExternResult<Vec<String>> {
+    game_facilitator::create_lobby_batch(count)
+}
+
+/// Starts the sessions of the facilitator's lobbies with the same params
+#[hdk_extern]
+pub fn start_facilitated_sessions(input: FacilitatedStartInput) -> ExternResult<Vec<EntryHash>> {
+    game_facilitator::start_facilitated_sessions(input)
+}
+
+/// Pauses, resumes or ends the session of one of the facilitator's lobbies
+#[hdk_extern]
+pub fn control_session(input: SessionControlInput) -> ExternResult<EntryHash> {
+    game_facilitator::control_session(input)
+}
+
+/// Lists the players, the session and it's latest round of every lobby
+/// the agent who calls this fn is in charge of
+#[hdk_extern]
+pub fn get_facilitated_lobbies(_: ()) -> ExternResult<Vec<FacilitatedLobby>> {
+    game_facilitator::get_facilitated_lobbies()
+}
+
 /// Lists all game sessions created by the agent who calls this fn
 #[hdk_extern]
 pub fn get_my_owned_sessions(_: ()) -> ExternResult<Vec<(EntryHash, GameSession)>> {
     game_session::get_my_own_sessions_via_source_query()
 }
 
+/// Generates a secret seed for every round of the given session and publishes a
+/// commitment to them. Every player has to do it at the start of the games that use
+/// random draws
+#[hdk_extern]
+pub fn commit_seed(session_hash: EntryHash) -> ExternResult<EntryHash> {
+    game_seed::commit_seed(session_hash)
+}
+
+/// Reveals the seed for the given round the agent has committed to. It's only possible
+/// after every player of the session has committed to their seeds and has moved in the round
+#[hdk_extern]
+pub fn reveal_seed(round_hash: EntryHash) -> ExternResult<EntryHash> {
+    game_seed::reveal_seed(round_hash)
+}
+
+/// Keeps the agent's bid for the rights to take resources in the given round
+/// private and publishes a commitment to it
+#[hdk_extern]
+pub fn commit_bid(input: BidInput) -> ExternResult<EntryHash> {
+    game_auction::commit_bid(input)
+}
+
+/// Reveals the bid the agent has committed to in the given round. It's only
+/// possible after every player of the round has committed to their bid
+#[hdk_extern]
+pub fn reveal_bid(round_hash: EntryHash) -> ExternResult<EntryHash> {
+    game_auction::reveal_bid(round_hash)
+}
+
 /// Creates a new move for the given round
 #[hdk_extern]
 pub fn make_new_move(input: GameMoveInput) -> ExternResult<HeaderHash> {
-    game_move::new_move(input.resource_amount, input.round_hash)
+    game_move::new_move(input)
+}
+
+/// Replaces the move the agent made for the given round while it's still open
+#[hdk_extern]
+pub fn update_move(input: GameMoveInput) -> ExternResult<HeaderHash> {
+    game_move::update_move(input)
+}
+
+/// Get the moves made so far in the given round that the agent is allowed to observe
+#[hdk_extern]
+pub fn get_moves_for_round(round_hash: EntryHash) -> ExternResult<Vec<GameMove>> {
+    game_monitoring::get_observable_moves(round_hash)
+}
+
+/// Get the takes of the players who moved before the agent in the given round,
+/// when players move one after another
+#[hdk_extern]
+pub fn get_earlier_moves(
+    round_hash: EntryHash,
+) -> ExternResult<Vec<(ResourceAmount, AgentPubKey)>> {
+    game_turns::get_my_earlier_moves(round_hash)
+}
+
+/// Spends some of the agent's resources to punish another player of the given round
+#[hdk_extern]
+pub fn make_sanction(input: GameSanctionInput) -> ExternResult<HeaderHash> {
+    game_sanction::new_sanction(input.target, input.cost, input.round_hash)
+}
+
+/// Spends some of the agent's resources to clean up the pollution in the given round
+#[hdk_extern]
+pub fn make_abatement(input: AbatementInput) -> ExternResult<HeaderHash> {
+    game_pollution::new_abatement(input.cost, input.round_hash)
+}
+
+/// Offers to give some of the agent's resources to another player
+/// in exchange for some of theirs in the next round
+#[hdk_extern]
+pub fn make_trade_offer(input: TradeOfferInput) -> ExternResult<EntryHash> {
+    game_trade::new_trade_offer(input)
+}
+
+/// Accepts the trade offer made by another player
+#[hdk_extern]
+pub fn accept_trade_offer(offer_hash: EntryHash) -> ExternResult<HeaderHash> {
+    game_trade::accept_trade_offer(offer_hash)
+}
+
+/// Lists all trade offers made during the given round
+#[hdk_extern]
+pub fn get_trade_offers(round_hash: EntryHash) -> ExternResult<Vec<(EntryHash, TradeOffer)>> {
+    game_trade::get_trade_offers_for_round(round_hash)
+}
+
+/// Offers to sell some of the agent's quota units to any other player
+#[hdk_extern]
+pub fn make_quota_order(input: QuotaOrderInput) -> ExternResult<EntryHash> {
+    game_quota::new_quota_order(input)
+}
+
+/// Buys all quota units of the order made by another player
+#[hdk_extern]
+pub fn fill_quota_order(order_hash: EntryHash) -> ExternResult<HeaderHash> {
+    game_quota::fill_quota_order(order_hash)
+}
+
+/// Lists all quota orders made during the given round
+#[hdk_extern]
+pub fn get_quota_orders(round_hash: EntryHash) -> ExternResult<Vec<(EntryHash, QuotaOrder)>> {
+    game_quota::get_quota_orders_for_round(round_hash)
+}
+
+/// Proposes a pact that caps the takes of it's signatories, and signs it
+#[hdk_extern]
+pub fn make_pact(input: PactInput) -> ExternResult<EntryHash> {
+    game_pact::new_pact(input)
+}
+
+/// Signs the pact made by another player
+#[hdk_extern]
+pub fn sign_pact(pact_hash: EntryHash) -> ExternResult<HeaderHash> {
+    game_pact::sign_pact(pact_hash)
+}
+
+/// Lists all pacts made in the game session
+#[hdk_extern]
+pub fn get_pacts(session_hash: EntryHash) -> ExternResult<Vec<(EntryHash, Pact)>> {
+    game_pact::get_pacts_for_session(session_hash)
+}
+
+/// Makes a non-binding pledge of how much the agent would take in the next round
+#[hdk_extern]
+pub fn make_pledge(input: PledgeInput) -> ExternResult<HeaderHash> {
+    game_pledge::new_pledge(input)
+}
+
+/// Lists every player's pledges and how they kept them up to the given round
+#[hdk_extern]
+pub fn get_trust_history(round_hash: EntryHash) -> ExternResult<Vec<PlayerTrust>> {
+    game_pledge::get_trust_history(round_hash)
+}
+
+/// Sends a chat message to the other players of the session
+#[hdk_extern]
+pub fn send_chat_message(input: ChatMessageInput) -> ExternResult<EntryHash> {
+    game_chat::send_chat_message(input)
+}
+
+/// Lists a page of the chat messages sent in the session, from the latest ones
+#[hdk_extern]
+pub fn get_chat_messages(input: ChatHistoryInput) -> ExternResult<Vec<(EntryHash, ChatMessage)>> {
+    game_chat::get_chat_messages(input)
+}
+
+/// Writes a note to the private journal of the agent, which only they can read
+#[hdk_extern]
+pub fn write_journal_note(input: JournalNoteInput) -> ExternResult<EntryHash> {
+    game_journal::write_journal_note(input)
+}
+
+/// Lists the notes the agent wrote in their private journal during the session
+#[hdk_extern]
+pub fn get_journal_notes(session_hash: EntryHash) -> ExternResult<Vec<(EntryHash, JournalNote)>> {
+    game_journal::get_journal_notes(session_hash)
+}
+
+/// Publishes every note the agent wrote during the session, once the game has ended
+#[hdk_extern]
+pub fn publish_journal(session_hash: EntryHash) -> ExternResult<Vec<EntryHash>> {
+    game_journal::publish_journal(session_hash)
+}
+
+/// Lists the notes players published after the session ended
+#[hdk_extern]
+pub fn get_published_notes(
+    session_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, PublishedNote)>> {
+    game_journal::get_published_notes(session_hash)
+}
+
+/// Submits the agent's answers to the survey of the session, once the game has ended
+#[hdk_extern]
+pub fn submit_survey(input: SurveyInput) -> ExternResult<EntryHash> {
+    game_survey::submit_survey(input)
+}
+
+/// Aggregates the answers players gave to the survey of the session.
+/// Only the host of the session can call it
+#[hdk_extern]
+pub fn get_survey_results(session_hash: EntryHash) -> ExternResult<SurveyResults> {
+    game_survey::get_survey_results(session_hash)
+}
+
+/// Proposes to change the params of the game session for the rounds that follow
+#[hdk_extern]
+pub fn make_proposal(input: ProposalInput) -> ExternResult<EntryHash> {
+    game_governance::new_proposal(input.session_hash, input.change)
+}
+
+/// Votes in favor or against the proposal
+#[hdk_extern]
+pub fn vote_on_proposal(input: VoteInput) -> ExternResult<HeaderHash> {
+    game_governance::new_vote(input.proposal_hash, input.in_favor)
+}
+
+/// Lists all proposals made in the game session
+#[hdk_extern]
+pub fn get_proposals(session_hash: EntryHash) -> ExternResult<Vec<(EntryHash, Proposal)>> {
+    game_governance::get_proposals_for_session(session_hash)
 }
 
 /// Function to call from the UI on a regular basis to try and close the currently
@@ -144,6 +473,34 @@ pub fn try_to_close_round(prev_round_hash: EntryHash) -> ExternResult<GameRoundI
     game_round::try_to_close_round(prev_round_hash.into())
 }
 
+/// Lists every closed round of the game up to the given round, showing only
+/// what the agent executing this fn can observe
+#[hdk_extern]
+pub fn get_round_history(round_hash: EntryHash) -> ExternResult<Vec<GameRoundInfo>> {
+    game_monitoring::get_round_history(round_hash)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_game_session(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_session::validate_create_entry_game_session(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_game_session(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_session::validate_update_entry_game_session(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_game_session(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_session::validate_delete_entry_game_session(validate_data)
+}
+
 #[hdk_extern]
 pub fn validate_update_entry_game_round(
     data: ValidateData,
@@ -171,3 +528,430 @@ pub fn validate_delete_entry_game_move(
 ) -> ExternResult<ValidateCallbackResult> {
     game_move::validate_delete_entry_game_move(validate_data)
 }
+
+#[hdk_extern]
+pub fn validate_create_entry_game_sanction(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_sanction::validate_create_entry_game_sanction(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_game_sanction(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_sanction::validate_update_entry_game_sanction(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_game_sanction(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_sanction::validate_delete_entry_game_sanction(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_proposal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_governance::validate_create_entry_proposal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_proposal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_governance::validate_update_entry_proposal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_proposal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_governance::validate_delete_entry_proposal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_vote(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_governance::validate_create_entry_vote(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_vote(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_governance::validate_update_entry_vote(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_vote(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_governance::validate_delete_entry_vote(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_trade_offer(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_trade::validate_create_entry_trade_offer(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_trade_offer(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_trade::validate_update_entry_trade_offer(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_trade_offer(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_trade::validate_delete_entry_trade_offer(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_trade_acceptance(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_trade::validate_create_entry_trade_acceptance(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_trade_acceptance(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_trade::validate_update_entry_trade_acceptance(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_trade_acceptance(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_trade::validate_delete_entry_trade_acceptance(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_seed_commitment(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_seed::validate_create_entry_seed_commitment(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_seed_commitment(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_seed::validate_update_entry_seed_commitment(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_seed_commitment(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_seed::validate_delete_entry_seed_commitment(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_seed_reveal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_seed::validate_create_entry_seed_reveal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_seed_reveal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_seed::validate_update_entry_seed_reveal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_seed_reveal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_seed::validate_delete_entry_seed_reveal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_abatement(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pollution::validate_create_entry_abatement(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_abatement(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pollution::validate_update_entry_abatement(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_abatement(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pollution::validate_delete_entry_abatement(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_quota_order(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_quota::validate_create_entry_quota_order(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_quota_order(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_quota::validate_update_entry_quota_order(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_quota_order(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_quota::validate_delete_entry_quota_order(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_quota_fill(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_quota::validate_create_entry_quota_fill(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_quota_fill(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_quota::validate_update_entry_quota_fill(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_quota_fill(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_quota::validate_delete_entry_quota_fill(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_pact(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pact::validate_create_entry_pact(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_pact(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pact::validate_update_entry_pact(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_pact(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pact::validate_delete_entry_pact(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_pact_signature(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pact::validate_create_entry_pact_signature(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_pact_signature(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pact::validate_update_entry_pact_signature(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_pact_signature(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pact::validate_delete_entry_pact_signature(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_pledge(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pledge::validate_create_entry_pledge(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_pledge(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pledge::validate_update_entry_pledge(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_pledge(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_pledge::validate_delete_entry_pledge(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_chat_message(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_chat::validate_create_entry_chat_message(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_chat_message(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_chat::validate_update_entry_chat_message(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_chat_message(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_chat::validate_delete_entry_chat_message(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_published_note(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_journal::validate_create_entry_published_note(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_published_note(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_journal::validate_update_entry_published_note(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_published_note(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_journal::validate_delete_entry_published_note(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_survey_response(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_survey::validate_create_entry_survey_response(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_survey_response(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_survey::validate_update_entry_survey_response(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_survey_response(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_survey::validate_delete_entry_survey_response(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_facilitator_rights(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_facilitator::validate_create_entry_facilitator_rights(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_facilitator_rights(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_facilitator::validate_update_entry_facilitator_rights(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_facilitator_rights(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_facilitator::validate_delete_entry_facilitator_rights(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_link(
+    validate_data: ValidateCreateLinkData,
+) -> ExternResult<ValidateLinkCallbackResult> {
+    game_facilitator::validate_create_link_facilitator(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_session_control(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_facilitator::validate_create_entry_session_control(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_session_control(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_facilitator::validate_update_entry_session_control(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_session_control(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_facilitator::validate_delete_entry_session_control(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_bid_commitment(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_auction::validate_create_entry_bid_commitment(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_bid_commitment(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_auction::validate_update_entry_bid_commitment(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_bid_commitment(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_auction::validate_delete_entry_bid_commitment(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_create_entry_bid_reveal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_auction::validate_create_entry_bid_reveal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_update_entry_bid_reveal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_auction::validate_update_entry_bid_reveal(validate_data)
+}
+
+#[hdk_extern]
+pub fn validate_delete_entry_bid_reveal(
+    validate_data: ValidateData,
+) -> ExternResult<ValidateCallbackResult> {
+    game_auction::validate_delete_entry_bid_reveal(validate_data)
+}
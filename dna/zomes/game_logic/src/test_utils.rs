@@ -0,0 +1,198 @@
+use crate::{
+    game_auction::BidReveal,
+    game_facilitator::{SessionControl, SessionControlAction},
+    game_move::GameMove,
+    game_pact::PactViolation,
+    game_sanction::GameSanction,
+    game_seed::{SharedSeed, SEED_SIZE},
+    game_session::{GameParams, GameSession, PlayerStats, ResourceAmount, SessionState},
+    game_trade::{OfferedTrade, TradeAcceptance, TradeDebt, TradeOffer},
+};
+use chrono::{TimeZone, Utc};
+use hdk::prelude::*;
+use std::collections::BTreeMap;
+
+// Helpers shared by the unit tests of every module. Test agents and entries
+// are identified by a single byte, so tests can refer to them by number
+
+/// Pub key of the test agent number n
+pub fn agent(n: u8) -> AgentPubKey {
+    AgentPubKey::from_raw_36(vec![n; 36])
+}
+
+/// Hash of the test entry number n
+pub fn hash(n: u8) -> EntryHash {
+    EntryHash::from_raw_36(vec![n; 36])
+}
+
+/// Timestamp that is secs seconds after the epoch
+pub fn timestamp(secs: i64) -> Timestamp {
+    Timestamp::from(Utc.timestamp(secs, 0))
+}
+
+/// PlayerStats with the given balances of the test agents
+pub fn stats(balances: &[(u8, ResourceAmount)]) -> PlayerStats {
+    balances.iter().map(|(n, b)| (agent(*n), *b)).collect()
+}
+
+/// Move of the test agent that takes resource_amount in the round hash(0)
+/// at the epoch, and doesn't reinvest or save anything or come after another move.
+/// Tests that need other values change them with the struct update syntax
+pub fn game_move(owner: u8, resource_amount: ResourceAmount) -> GameMove {
+    GameMove {
+        owner: agent(owner),
+        round_hash: hash(0),
+        resource_amount,
+        patch: None,
+        reinvest_amount: 0,
+        save_amount: 0,
+        timestamp: timestamp(0),
+        prev_move: None,
+    }
+}
+
+/// Move of the test agent that reinvests and saves the given amounts
+/// without taking anything
+pub fn investing_move(
+    owner: u8,
+    reinvest_amount: ResourceAmount,
+    save_amount: ResourceAmount,
+) -> GameMove {
+    GameMove {
+        reinvest_amount,
+        save_amount,
+        ..game_move(owner, 0)
+    }
+}
+
+/// GameSession of the test agents with the given params, owned by the first of them.
+/// Players have the default attributes and don't play in teams
+pub fn game_session(game_params: GameParams, players: &[u8]) -> GameSession {
+    GameSession {
+        owner: agent(players[0]),
+        status: SessionState::InProgress,
+        game_params,
+        players: players.iter().map(|n| agent(*n)).collect(),
+        scores: PlayerStats::new(),
+        anchor: hash(0),
+        player_attributes: BTreeMap::new(),
+        teams: BTreeMap::new(),
+        team_scores: BTreeMap::new(),
+    }
+}
+
+/// Revealed bid of the test agent in the round hash(0), together with the hash
+/// of it's commitment, which is the test entry number n
+pub fn bid(
+    n: u8,
+    owner: u8,
+    units: ResourceAmount,
+    price_per_unit: ResourceAmount,
+) -> (EntryHash, BidReveal) {
+    (
+        hash(n),
+        BidReveal {
+            owner: agent(owner),
+            round_hash: hash(0),
+            units,
+            price_per_unit,
+            salt: vec![],
+        },
+    )
+}
+
+/// Shared seed made only of the given byte, without commitments
+pub fn shared_seed(byte: u8) -> SharedSeed {
+    SharedSeed {
+        commitments: vec![],
+        seed: vec![byte; SEED_SIZE],
+    }
+}
+
+/// Sanction of the owner against the target in the round hash(0),
+/// together with it's hash, which is the test entry number n
+pub fn sanction(n: u8, owner: u8, target: u8, cost: ResourceAmount) -> (EntryHash, GameSanction) {
+    (
+        hash(n),
+        GameSanction {
+            owner: agent(owner),
+            target: agent(target),
+            round_hash: hash(0),
+            cost,
+        },
+    )
+}
+
+/// Trade offer number n of the owner in the round hash(1), together with
+/// it's acceptances, which are given as (entry number, acceptor) pairs
+pub fn trade(
+    n: u8,
+    owner: u8,
+    give: ResourceAmount,
+    ask: ResourceAmount,
+    acceptors: &[(u8, u8)],
+) -> OfferedTrade {
+    OfferedTrade {
+        offer_hash: hash(n),
+        offer: TradeOffer {
+            owner: agent(owner),
+            session: hash(0),
+            round_hash: hash(1),
+            recipient: None,
+            give_amount: give,
+            ask_amount: ask,
+        },
+        acceptances: acceptors
+            .iter()
+            .map(|(h, acceptor)| {
+                (
+                    hash(*h),
+                    TradeAcceptance {
+                        owner: agent(*acceptor),
+                        offer: hash(n),
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Debt of the debtor to the creditor
+pub fn debt(debtor: u8, creditor: u8, amount: ResourceAmount) -> TradeDebt {
+    TradeDebt {
+        debtor: agent(debtor),
+        creditor: agent(creditor),
+        amount,
+    }
+}
+
+/// Violation of the pact number n with the take cap 3 by the test agent
+pub fn violation(
+    n: u8,
+    player: u8,
+    resource_amount: ResourceAmount,
+    penalty: ResourceAmount,
+) -> PactViolation {
+    PactViolation {
+        pact_hash: hash(n),
+        player: agent(player),
+        resource_amount,
+        take_cap: 3,
+        penalty,
+    }
+}
+
+/// Control of the session hash(0) by the test agent 1, made n seconds
+/// after the epoch, together with it's hash, which is the test entry number n
+pub fn control(n: u8, action: SessionControlAction) -> (EntryHash, SessionControl) {
+    (
+        hash(n),
+        SessionControl {
+            owner: agent(1),
+            session: hash(0),
+            rights: hash(0),
+            action,
+            timestamp: timestamp(n as i64),
+        },
+    )
+}
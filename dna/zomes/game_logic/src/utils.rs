@@ -33,13 +33,7 @@ pub fn try_get_element(entry_hash: EntryHash, get_options: GetOptions) -> Extern
 /// tries to convert it to type T and return the result
 pub fn try_from_element<T: TryFrom<Entry>>(element: Element) -> ExternResult<T> {
     match element.entry() {
-        element::ElementEntry::Present(entry) => {
-            T::try_from(entry.clone()).or(Err(WasmError::Guest(format!(
-                "Couldn't convert Element entry {:?} into data type {}",
-                entry,
-                std::any::type_name::<T>()
-            ))))
-        }
+        element::ElementEntry::Present(entry) => try_from_entry(entry.clone()),
         _ => Err(WasmError::Guest(format!(
             "Element {:?} does not have an entry",
             element
@@ -47,12 +41,45 @@ pub fn try_from_element<T: TryFrom<Entry>>(element: Element) -> ExternResult<T>
     }
 }
 
-/// Generates PlayerStats instance with the state from the input game_moves
-pub fn player_stats_from_moves(game_moves: Vec<GameMove>) -> PlayerStats {
-    game_moves
-        .into_iter()
-        .map(|m| (m.owner.clone(), m.resource_amount))
-        .collect::<PlayerStats>()
+/// Tries to convert the entry to type T and return the result
+pub fn try_from_entry<T: TryFrom<Entry>>(entry: Entry) -> ExternResult<T> {
+    T::try_from(entry.clone()).or(Err(WasmError::Guest(format!(
+        "Couldn't convert Element entry {:?} into data type {}",
+        entry,
+        std::any::type_name::<T>()
+    ))))
+}
+
+/// Converts the entries that the author of the validated element has created
+/// before it into type T. They come in the validation package of the entries
+/// validated with their sub chain, which only has the entries of the same type.
+/// Returns None if there's no validation package
+pub fn earlier_entries_in_package<T: TryFrom<Entry>>(
+    data: &ValidateData,
+) -> ExternResult<Option<Vec<T>>> {
+    let elements = match &data.validation_package {
+        Some(validation_package) => &validation_package.0,
+        None => return Ok(None),
+    };
+    let mut entries: Vec<T> = vec![];
+    for el in elements {
+        if el.header_address() != data.element.header_address()
+            && matches!(el.header(), Header::Create(_))
+        {
+            entries.push(try_from_element(el.clone())?);
+        }
+    }
+    Ok(Some(entries))
+}
+
+/// Generates PlayerStats instance by adding resources from the input game_moves
+/// to the prev_stats
+pub fn player_stats_from_moves(prev_stats: &PlayerStats, game_moves: &[GameMove]) -> PlayerStats {
+    let mut player_stats = prev_stats.clone();
+    for m in game_moves {
+        *player_stats.entry(m.owner.clone()).or_insert(0) += m.resource_amount;
+    }
+    player_stats
 }
 
 /// Retrieves holochain entry with a given hash and then
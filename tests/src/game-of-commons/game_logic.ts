@@ -117,6 +117,37 @@ export default (orchestrator: Orchestrator<any>) =>
     console.log("Verify that Bob's owned games is 0");
     t.ok(bob_owned_games.length == 0);
 
+    const session_hash = alice_owned_games[0][0];
+
+    // Alice says hi to everyone before making her move
+    let chat_message_hash = await alice.call(ZOME_NAME, "send_chat_message", {
+      round_hash: zero_round_entry_hash,
+      recipient: null,
+      content: "Let's take 5 each",
+    });
+    console.log("ROUND 1: Alice sent a chat message: ", chat_message_hash);
+    t.ok(chat_message_hash);
+
+    // wait for the message to propagate
+    await sleep(1000);
+
+    let chat_messages = await bob.call(ZOME_NAME, "get_chat_messages", {
+      session_hash,
+      page: 0,
+      page_size: 10,
+    });
+    console.log("ROUND 1: chat messages Bob sees: ", chat_messages);
+    t.ok(chat_messages.length == 1);
+    t.ok(chat_messages[0][1].content == "Let's take 5 each");
+
+    // Alice writes down her plan in the private journal
+    let journal_note_hash = await alice.call(ZOME_NAME, "write_journal_note", {
+      round_hash: zero_round_entry_hash,
+      content: "Bob will probably take more than 5",
+    });
+    console.log("ROUND 1: Alice wrote a journal note: ", journal_note_hash);
+    t.ok(journal_note_hash);
+
     // ROUND 1
     // Alice makes her move
     let game_move_round_1_alice = await alice.call(ZOME_NAME, "make_new_move", {
@@ -137,6 +168,28 @@ export default (orchestrator: Orchestrator<any>) =>
     // wait for move data to propagate
     await sleep(2000);
 
+    // Alice changes her mind while the round is still open
+    let game_move_round_1_alice_update = await alice.call(ZOME_NAME, "update_move", {
+      resource_amount: 4,
+      round_hash: zero_round_entry_hash,
+    });
+    console.log("ROUND 1: Alice updated her move: ", game_move_round_1_alice_update);
+    t.ok(game_move_round_1_alice_update);
+
+    // wait for the update to propagate
+    await sleep(2000);
+
+    // Bob only sees the latest revision of Alice's move
+    let moves_round_1 = await bob.call(
+      ZOME_NAME,
+      "get_moves_for_round",
+      zero_round_entry_hash
+    );
+    console.log("ROUND 1: moves Bob sees: ", moves_round_1);
+    t.ok(moves_round_1.length == 2);
+    t.ok(moves_round_1.some((m) => m.resource_amount == 4));
+    t.ok(!moves_round_1.some((m) => m.resource_amount == 5));
+
     // Check to close the first round
     let close_game_round_1_bob = await bob.call(
       ZOME_NAME,
@@ -227,4 +280,30 @@ export default (orchestrator: Orchestrator<any>) =>
       close_game_round_3_bob.next_action
     );
     t.ok(close_game_round_3_bob.next_action == "SHOW_GAME_RESULTS");
+    // any player who closes the final round ends the game session
+    t.ok(close_game_round_3_bob.game_session_hash);
+
+    // wait for the ended session to propagate
+    await sleep(2000);
+
+    // Once the game is over, Alice publishes her journal
+    let published_note_hashes = await alice.call(
+      ZOME_NAME,
+      "publish_journal",
+      session_hash
+    );
+    console.log("Alice published her journal: ", published_note_hashes);
+    t.ok(published_note_hashes.length == 1);
+
+    // wait for the published notes to propagate
+    await sleep(1000);
+
+    let published_notes = await bob.call(
+      ZOME_NAME,
+      "get_published_notes",
+      session_hash
+    );
+    console.log("Published notes Bob sees: ", published_notes);
+    t.ok(published_notes.length == 1);
+    t.ok(published_notes[0][1].content == "Bob will probably take more than 5");
   });